@@ -0,0 +1,11 @@
+desktop-comment = KI-Code-Editor
+application-error = Anwendungsfehler: { $error }
+
+metadata-fetched = Metadaten abgerufen: Cursor { $version } ({ $commit }) von { $url }
+download-started = Lade herunter von { $url }
+downloading = Herunterladen
+download-finished = Download erfolgreich abgeschlossen: { $path }
+extracting = Entpacke AppImage...
+copying = Kopiere AppImage nach { $path }...
+backed-up = Sichere { $from } nach { $to }
+installed = Installation abgeschlossen!
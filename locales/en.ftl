@@ -0,0 +1,14 @@
+# English messages. This file is the reference catalog: every other locale
+# falls back to it for ids it does not define.
+
+desktop-comment = AI code editor
+application-error = Application error: { $error }
+
+metadata-fetched = Successfully fetched metadata: Cursor { $version } ({ $commit }) from { $url }
+download-started = Downloading from { $url }
+downloading = Downloading
+download-finished = Download completed successfully to { $path }
+extracting = Extracting AppImage...
+copying = Copying the AppImage to { $path }...
+backed-up = Backing up { $from } to { $to }
+installed = Installation complete!
@@ -0,0 +1,11 @@
+desktop-comment = Editor de código con IA
+application-error = Error de la aplicación: { $error }
+
+metadata-fetched = Metadatos obtenidos: Cursor { $version } ({ $commit }) desde { $url }
+download-started = Descargando desde { $url }
+downloading = Descargando
+download-finished = Descarga completada en { $path }
+extracting = Extrayendo AppImage...
+copying = Copiando el AppImage a { $path }...
+backed-up = Copiando { $from } a { $to }
+installed = ¡Instalación completada!
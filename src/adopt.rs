@@ -0,0 +1,215 @@
+//! `cursorup adopt`: takes over an install made by hand, e.g. an AppImage
+//! downloaded into `~/Applications/cursor` before cursorup was used. Such
+//! files are often renamed, so their version and commit are read from the
+//! `package.json` and `product.json` embedded in the AppImage instead of
+//! the file name.
+
+use crate::config::Config;
+use crate::state::State;
+use crate::{Result, events};
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::process::Command;
+
+/// Location of the app's metadata inside the AppImage; the pattern matches
+/// both `package.json` and `product.json`.
+const METADATA_DIR: &str = "usr/share/cursor/resources/app";
+const METADATA_PATTERN: &str = "usr/share/cursor/resources/app/p*.json";
+
+#[derive(Serialize, Debug)]
+pub struct Adopted {
+    pub appimage: PathBuf,
+    pub version: String,
+    /// Empty when the AppImage doesn't say.
+    pub commit_sha: String,
+    /// How many AppImages the install dir held.
+    pub candidates: usize,
+}
+
+impl fmt::Display for Adopted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Adopted Cursor {} ({})",
+            self.version,
+            self.appimage.display()
+        )?;
+        if !self.commit_sha.is_empty() {
+            writeln!(f, "Commit: {}", self.commit_sha)?;
+        }
+        write!(
+            f,
+            "Chose the newest of {} AppImage(s); `cursorup check` now compares against it.",
+            self.candidates
+        )
+    }
+}
+
+/// Version and commit embedded in one AppImage.
+pub(crate) struct Embedded {
+    pub version: Option<String>,
+    pub commit_sha: Option<String>,
+}
+
+/// Records the newest AppImage in the install dir in the state file, links
+/// it as the current one and adds it to the manifest. Refuses when a state
+/// file already exists, since that install is managed already.
+pub async fn adopt(config: &Config) -> Result<Adopted> {
+    if let Some(state) = State::load().await? {
+        return Err(format!(
+            "cursorup already manages Cursor {} ({:?}); nothing to adopt",
+            state.version, state.appimage
+        )
+        .into());
+    }
+
+    let appimages = appimages(&config.install_dir).await?;
+    if appimages.is_empty() {
+        return Err(format!("No AppImages found in {:?}", config.install_dir).into());
+    }
+
+    let mut newest: Option<(String, String, PathBuf)> = None;
+    for appimage in &appimages {
+        let embedded = inspect(appimage, config.execution_mode).await;
+        let Some(version) = embedded
+            .version
+            .or_else(|| crate::status::version_from_file_name(appimage))
+        else {
+            events::log(format!(
+                "Skipping {:?}: could not determine its version",
+                appimage
+            ));
+            continue;
+        };
+        events::log(format!("Found Cursor {} in {:?}", version, appimage));
+        let newer = newest.as_ref().is_none_or(|(best, _, _)| {
+            crate::version::compare(&version, best).is_some_and(|ordering| ordering.is_gt())
+        });
+        if newer {
+            newest = Some((
+                version,
+                embedded.commit_sha.unwrap_or_default(),
+                appimage.clone(),
+            ));
+        }
+    }
+    let (version, commit_sha, appimage) =
+        newest.ok_or("None of the AppImages has a recognizable version")?;
+
+    crate::wrapper::link_current(&config.install_dir, &appimage).await?;
+    let mut manifest = crate::manifest::Manifest::load(&config.install_dir)
+        .await?
+        .unwrap_or_default();
+    manifest.add(&appimage);
+    let sha256 = crate::cache::sha256_file(&appimage).await?;
+    manifest.appimage = Some(crate::manifest::FileRecord::new(&appimage, &sha256).await?);
+    manifest.save(&config.install_dir).await?;
+
+    let mut state = State::new(
+        &config.desktop_name,
+        &version,
+        &commit_sha,
+        &appimage,
+        config.platform,
+        config.release_track,
+    );
+    state.sha256 = Some(sha256);
+    // The file's age is the best guess at when it was installed.
+    if let Ok(modified) = fs::metadata(&appimage).await.and_then(|m| m.modified()) {
+        state.installed_at = modified
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+    }
+    state.save().await?;
+
+    Ok(Adopted {
+        appimage,
+        version,
+        commit_sha,
+        candidates: appimages.len(),
+    })
+}
+
+pub(crate) async fn appimages(install_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = match fs::read_dir(install_dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut found = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|s| s.to_str()) == Some("AppImage")
+            && entry.file_type().await?.is_file()
+        {
+            found.push(path);
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// Extracts only the metadata files from the AppImage's squashfs payload
+/// with the runtime's `--appimage-extract <pattern>`. Failures leave the
+/// fields empty so the file name can still be used.
+pub(crate) async fn inspect(
+    appimage: &Path,
+    execution: crate::execution::ExecutionMode,
+) -> Embedded {
+    let extracted = async {
+        let tmp_dir = crate::tmpdir::TmpDir::new()?;
+        let mut command = Command::new(appimage);
+        command
+            .arg("--appimage-extract")
+            .arg(METADATA_PATTERN)
+            .current_dir(&tmp_dir.path);
+        execution.configure(&mut command);
+        let output = command.output().await?;
+        if !output.status.success() {
+            return Err(format!(
+                "extraction failed ({}) {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+        let dir = tmp_dir.path.join("squashfs-root").join(METADATA_DIR);
+        Ok::<_, Box<dyn std::error::Error>>((tmp_dir, dir))
+    }
+    .await;
+    // `_tmp_dir` keeps the extracted files until they are read.
+    let (_tmp_dir, dir) = match extracted {
+        Ok(extracted) => extracted,
+        Err(e) => {
+            events::log(format!("Could not inspect {:?}: {}", appimage, e));
+            return Embedded {
+                version: None,
+                commit_sha: None,
+            };
+        }
+    };
+
+    let field = |json: &Option<serde_json::Value>, key: &str| {
+        json.as_ref()
+            .and_then(|json| json.get(key))
+            .and_then(|value| value.as_str())
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    let package = read_json(&dir.join("package.json")).await;
+    let product = read_json(&dir.join("product.json")).await;
+    Embedded {
+        // product.json's own `version`, where present, may be the VS Code
+        // base version, so Cursor's package.json wins.
+        version: field(&package, "version").or_else(|| field(&product, "version")),
+        commit_sha: field(&product, "commit"),
+    }
+}
+
+async fn read_json(path: &Path) -> Option<serde_json::Value> {
+    let contents = fs::read_to_string(path).await.ok()?;
+    serde_json::from_str(&contents).ok()
+}
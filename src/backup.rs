@@ -0,0 +1,340 @@
+use crate::Result;
+use crate::provenance::RecordedChecksum;
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::UNIX_EPOCH;
+use tokio::fs;
+use tokio::io::AsyncReadExt;
+use tokio::process::Command;
+
+/// Directory inside the install dir that `back_file` moves old files into.
+pub const BACKUP_DIR: &str = "back";
+
+/// Extension appended to zstd-compressed backups (`X.AppImage.bak.zst`).
+const COMPRESSED_SUFFIX: &str = ".zst";
+
+/// One backed-up AppImage generation.
+#[derive(Serialize, Debug)]
+pub struct Backup {
+    /// The backup's file name, which `restore_backup` accepts to pick it.
+    pub id: String,
+    pub file: PathBuf,
+    pub version: Option<String>,
+    /// Size on disk, i.e. after compression for compressed backups.
+    pub size: u64,
+    pub compressed: bool,
+    /// Modification time in seconds since the Unix epoch.
+    pub modified: u64,
+}
+
+/// Result of re-hashing one backup against its recorded checksum.
+#[derive(Serialize, Debug)]
+pub struct BackupCheck {
+    pub file: PathBuf,
+    pub sha256: String,
+    pub recorded_sha256: Option<String>,
+    /// Where the recorded checksum was published, if it was checked against
+    /// a published one at install time.
+    pub checksum_source: Option<String>,
+    pub ok: bool,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(transparent)]
+pub struct Backups(pub Vec<Backup>);
+
+#[derive(Serialize, Debug)]
+#[serde(transparent)]
+pub struct BackupChecks(pub Vec<BackupCheck>);
+
+impl fmt::Display for Backups {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "No backups found.");
+        }
+        for (index, backup) in self.0.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{:<12} {:>9.2} MB  {}  {}",
+                backup.version.as_deref().unwrap_or("unknown"),
+                backup.size as f64 / 1_048_576.0,
+                crate::output::format_timestamp(backup.modified),
+                backup.file.display()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for BackupChecks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "No backups found.");
+        }
+        for (index, check) in self.0.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            let verdict = match (&check.recorded_sha256, check.ok) {
+                (None, _) => "UNCHECKED (no recorded checksum)",
+                (Some(_), true) => "OK",
+                (Some(_), false) => "CORRUPT",
+            };
+            write!(f, "{:<8} {}", verdict, check.file.display())?;
+            if check.recorded_sha256.is_some() {
+                match &check.checksum_source {
+                    Some(source) => write!(f, " (published checksum from {})", source)?,
+                    None => write!(f, " (checksum recorded at download)")?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BackupChecks {
+    pub fn all_ok(&self) -> bool {
+        self.0.iter().all(|check| check.ok)
+    }
+}
+
+/// Backed-up AppImages, newest first.
+pub async fn list(install_dir: &Path) -> Result<Backups> {
+    let back_dir = install_dir.join(BACKUP_DIR);
+    let mut entries = match fs::read_dir(&back_dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Backups(Vec::new())),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut backups = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let Some(original) = original_name(&path) else {
+            continue;
+        };
+        if original.extension().and_then(|s| s.to_str()) != Some("AppImage") {
+            continue;
+        }
+        let metadata = entry.metadata().await?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        backups.push(Backup {
+            id: entry.file_name().to_string_lossy().into_owned(),
+            version: crate::status::version_from_file_name(&original),
+            size: metadata.len(),
+            compressed: is_compressed(&path),
+            modified,
+            file: path,
+        });
+    }
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.modified));
+    Ok(Backups(backups))
+}
+
+/// Which backups `prune` deletes. A backup goes if either limit says so;
+/// with neither set nothing is deleted.
+#[derive(Debug, Clone, Default)]
+pub struct PrunePolicy {
+    /// Keep this many of the newest backups.
+    pub keep: Option<usize>,
+    /// Delete backups older than this many seconds.
+    pub older_than: Option<u64>,
+}
+
+/// What `prune` deleted.
+#[derive(Serialize, Debug)]
+pub struct Pruned {
+    pub removed: Vec<Backup>,
+    /// Bytes freed, counting the backups themselves.
+    pub freed: u64,
+}
+
+impl fmt::Display for Pruned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.removed.is_empty() {
+            return write!(f, "No backups to prune.");
+        }
+        for backup in &self.removed {
+            writeln!(f, "Removed {}", backup.file.display())?;
+        }
+        write!(f, "Freed {:.2} MB", self.freed as f64 / 1_048_576.0)
+    }
+}
+
+/// Deletes the backed-up AppImages `policy` selects, with their `.sha256`
+/// sidecars.
+pub async fn prune(install_dir: &Path, policy: &PrunePolicy) -> Result<Pruned> {
+    let cutoff = policy
+        .older_than
+        .map(|age| crate::state::now().saturating_sub(age));
+    let mut removed = Vec::new();
+    let mut freed = 0;
+    // `list` is newest first, so the index says how many are newer.
+    for (index, backup) in list(install_dir).await?.0.into_iter().enumerate() {
+        let surplus = policy.keep.is_some_and(|keep| index >= keep);
+        let expired = cutoff.is_some_and(|cutoff| backup.modified < cutoff);
+        if !surplus && !expired {
+            continue;
+        }
+        fs::remove_file(&backup.file).await?;
+        if let Some(sidecar) = checksum_sidecar(&backup.file) {
+            match fs::remove_file(&sidecar).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        freed += backup.size;
+        removed.push(backup);
+    }
+    Ok(Pruned { removed, freed })
+}
+
+/// Re-hashes every backed-up AppImage and compares it with the `.sha256`
+/// sidecar that was backed up alongside it.
+pub async fn verify(install_dir: &Path) -> Result<BackupChecks> {
+    let mut checks = Vec::new();
+    for backup in list(install_dir).await?.0 {
+        let sha256 = sha256_backup(&backup.file).await?;
+        let recorded = recorded_checksum(&backup.file).await?;
+        let (recorded_sha256, checksum_source) = match recorded {
+            Some(recorded) => (Some(recorded.sha256), recorded.source),
+            None => (None, None),
+        };
+        checks.push(BackupCheck {
+            ok: recorded_sha256
+                .as_deref()
+                .is_none_or(|recorded| recorded == sha256),
+            file: backup.file,
+            sha256,
+            recorded_sha256,
+            checksum_source,
+        });
+    }
+    Ok(BackupChecks(checks))
+}
+
+/// `Cursor-1.2.3-x86_64.AppImage.bak[.zst]` -> `Cursor-1.2.3-x86_64.AppImage`.
+pub fn original_name(backup: &Path) -> Option<PathBuf> {
+    let name = backup.file_name()?.to_str()?;
+    let name = name.strip_suffix(COMPRESSED_SUFFIX).unwrap_or(name);
+    Some(backup.with_file_name(name.strip_suffix(".bak")?))
+}
+
+fn is_compressed(backup: &Path) -> bool {
+    backup
+        .to_str()
+        .is_some_and(|path| path.ends_with(COMPRESSED_SUFFIX))
+}
+
+pub fn compressed_path(backup: &Path) -> PathBuf {
+    let mut path = backup.as_os_str().to_os_string();
+    path.push(COMPRESSED_SUFFIX);
+    PathBuf::from(path)
+}
+
+/// Compresses a backup in place with the `zstd` tool, replacing `X.bak` by
+/// `X.bak.zst`.
+pub async fn compress(backup: &Path) -> Result<PathBuf> {
+    crate::events::log(format!("Compressing backup {:?}", backup));
+    let output = Command::new("zstd")
+        .args(["-q", "-f", "--rm", "-T0"])
+        .arg(backup)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run zstd (is it installed?): {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "zstd failed to compress {:?}: {}",
+            backup,
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+    Ok(compressed_path(backup))
+}
+
+/// Hashes the original contents of a backup, decompressing on the fly.
+pub async fn sha256_backup(backup: &Path) -> Result<String> {
+    if !is_compressed(backup) {
+        return crate::cache::sha256_file(backup).await;
+    }
+    let mut child = Command::new("zstd")
+        .args(["-q", "-d", "-c"])
+        .arg(backup)
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run zstd (is it installed?): {}", e))?;
+    let mut stdout = child.stdout.take().ok_or("zstd has no stdout")?;
+    let mut hasher = ring::digest::Context::new(&ring::digest::SHA256);
+    let mut buffer = vec![0; 1 << 16];
+    loop {
+        let read = stdout.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    if !child.wait().await?.success() {
+        return Err(format!("zstd failed to decompress {:?}", backup).into());
+    }
+    Ok(crate::provenance::hex(hasher.finish().as_ref()))
+}
+
+/// Copies a backup's original contents to `dest`, decompressing compressed
+/// backups so callers such as rollback don't need to care how it was stored.
+/// With `link`, an uncompressed backup is linked rather than copied where
+/// the filesystem allows; see `fsutil::link_or_copy`. Copies are synced to
+/// disk before this returns.
+pub async fn restore_file(backup: &Path, dest: &Path, link: bool) -> Result<()> {
+    if !is_compressed(backup) {
+        if link {
+            let placed = crate::fsutil::link_or_copy(backup, dest).await?;
+            crate::events::log(format!("{} {:?} to {:?}", placed.verb(), backup, dest));
+            return Ok(());
+        }
+        fs::copy(backup, dest).await?;
+        fs::File::open(dest).await?.sync_all().await?;
+        return Ok(());
+    }
+    // zstd writes `dest` itself, so the AppImage never sits in memory.
+    let output = Command::new("zstd")
+        .args(["-q", "-d", "-f", "-o"])
+        .arg(dest)
+        .arg(backup)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run zstd (is it installed?): {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "zstd failed to decompress {:?}: {}",
+            backup,
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+    fs::File::open(dest).await?.sync_all().await?;
+    Ok(())
+}
+
+async fn recorded_checksum(backup: &Path) -> Result<Option<RecordedChecksum>> {
+    let sidecar = checksum_sidecar(backup).ok_or("Backup has no .bak suffix")?;
+    crate::provenance::read_checksum(&sidecar).await
+}
+
+/// The backed-up `.sha256` sidecar that belongs to a backed-up AppImage.
+pub fn checksum_sidecar(backup: &Path) -> Option<PathBuf> {
+    let original = original_name(backup)?;
+    let mut sidecar = crate::provenance::checksum_path(&original).into_os_string();
+    sidecar.push(".bak");
+    Some(PathBuf::from(sidecar))
+}
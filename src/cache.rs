@@ -0,0 +1,256 @@
+use crate::cleanup::CleanupReport;
+use crate::{Platform, ReleaseTrack, Resp, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::AsyncReadExt;
+
+/// Directory for downloaded artifacts, honouring `XDG_CACHE_HOME`.
+pub fn dir() -> Result<PathBuf> {
+    let base = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => crate::home_dir()?.join(".cache"),
+    };
+    Ok(base.join("cursorup"))
+}
+
+fn artifacts_dir() -> Result<PathBuf> {
+    Ok(dir()?.join("artifacts"))
+}
+
+/// Where the artifact for `version` is (or will be) cached.
+pub fn artifact_path(platform: Platform, version: &str, file_name: &str) -> Result<PathBuf> {
+    Ok(artifacts_dir()?
+        .join(crate::urls::cache_key(platform, version))
+        .join(file_name))
+}
+
+/// Release metadata as last fetched, so prompt integrations can answer
+/// without network access.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CachedMetadata {
+    /// Seconds since the Unix epoch.
+    pub fetched_at: u64,
+    pub metadata: Resp,
+}
+
+fn metadata_path(platform: Platform, release_track: ReleaseTrack) -> Result<PathBuf> {
+    Ok(dir()?
+        .join("metadata")
+        .join(format!("{}-{}.json", platform, release_track)))
+}
+
+pub async fn store_metadata(
+    platform: Platform,
+    release_track: ReleaseTrack,
+    metadata: &Resp,
+) -> Result<()> {
+    let path = metadata_path(platform, release_track)?;
+    let contents = serde_json::to_string(&CachedMetadata {
+        fetched_at: crate::state::now(),
+        metadata: metadata.clone(),
+    })?;
+    crate::fsutil::write_atomic(&path, contents).await
+}
+
+/// The last fetched metadata of the track, if any. An unreadable cache
+/// counts as missing.
+pub async fn load_metadata(
+    platform: Platform,
+    release_track: ReleaseTrack,
+) -> Result<Option<CachedMetadata>> {
+    match fs::read_to_string(metadata_path(platform, release_track)?).await {
+        Ok(contents) => Ok(serde_json::from_str(&contents).ok()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Hashes a file on disk, returning the lowercase hex SHA-256.
+pub async fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path).await?;
+    let mut hasher = ring::digest::Context::new(&ring::digest::SHA256);
+    let mut buffer = vec![0; 1 << 16];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(crate::provenance::hex(hasher.finish().as_ref()))
+}
+
+/// Checks a cached artifact against the checksum recorded when it was
+/// downloaded. The sidecar is only written once a download completes, so a
+/// missing sidecar means the artifact is absent or partial.
+pub async fn verify(artifact: &Path) -> Result<String> {
+    let checksum_path = crate::provenance::checksum_path(artifact);
+    let Some(recorded) = crate::provenance::read_checksum(&checksum_path).await? else {
+        return Err(format!("No complete cached download at {:?}", artifact).into());
+    };
+    let recorded = recorded.sha256;
+
+    let actual = sha256_file(artifact).await?;
+    if actual != recorded {
+        return Err(format!(
+            "Cached artifact {:?} is corrupt: expected sha256 {}, found {}",
+            artifact, recorded, actual
+        )
+        .into());
+    }
+    Ok(actual)
+}
+
+/// Removes cached artifacts of every `platform` version except
+/// `keep_version`, along with version dirs left at the top level by the
+/// layout used before artifacts were keyed by platform.
+pub async fn prune_except(platform: Platform, keep_version: Option<&str>) -> Result<CleanupReport> {
+    let mut report = CleanupReport::default();
+    for path in prunable(platform, keep_version).await? {
+        crate::events::log(format!("Removing cached download {:?}", path));
+        report.remove(&path).await;
+    }
+    Ok(report)
+}
+
+/// The dirs `prune_except` removes.
+async fn prunable(platform: Platform, keep_version: Option<&str>) -> Result<Vec<PathBuf>> {
+    let artifacts_dir = artifacts_dir()?;
+    let name = |path: &Path| {
+        path.file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    };
+    let mut paths: Vec<PathBuf> = subdirs(&artifacts_dir)
+        .await?
+        .into_iter()
+        .filter(|path| name(path).parse::<Platform>().is_err())
+        .collect();
+    for path in subdirs(&artifacts_dir.join(platform.to_string())).await? {
+        if Some(name(&path).as_str()) != keep_version {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+/// Bytes `prune_except` would free.
+pub(crate) async fn prunable_bytes(platform: Platform, keep_version: Option<&str>) -> Result<u64> {
+    let mut bytes = 0;
+    for path in prunable(platform, keep_version).await? {
+        bytes += dir_usage(&path).await?.0;
+    }
+    Ok(bytes)
+}
+
+/// Something `enforce_budget` may delete, with when it was last used.
+struct Prunable {
+    path: PathBuf,
+    /// Other files that belong to it, such as a backup's checksum.
+    companions: Vec<PathBuf>,
+    size: u64,
+    /// Seconds since the Unix epoch.
+    used: u64,
+}
+
+/// Deletes cached downloads (one version dir at a time) and compressed
+/// backups in `install_dir`, least recently used first, until together they
+/// take up at most `budget` bytes.
+pub async fn enforce_budget(install_dir: &Path, budget: u64) -> Result<CleanupReport> {
+    let mut candidates = Vec::new();
+    let artifacts_dir = artifacts_dir()?;
+    for platform_dir in subdirs(&artifacts_dir).await? {
+        for version_dir in subdirs(&platform_dir).await? {
+            let (size, used) = dir_usage(&version_dir).await?;
+            candidates.push(Prunable {
+                path: version_dir,
+                companions: Vec::new(),
+                size,
+                used,
+            });
+        }
+    }
+    for backup in crate::backup::list(install_dir).await?.0 {
+        if !backup.compressed {
+            continue;
+        }
+        let used = last_used(&fs::metadata(&backup.file).await?);
+        candidates.push(Prunable {
+            companions: crate::backup::checksum_sidecar(&backup.file)
+                .into_iter()
+                .collect(),
+            path: backup.file,
+            size: backup.size,
+            used,
+        });
+    }
+
+    let mut total: u64 = candidates.iter().map(|candidate| candidate.size).sum();
+    candidates.sort_by_key(|candidate| candidate.used);
+    let mut report = CleanupReport::default();
+    for candidate in candidates {
+        if total <= budget {
+            break;
+        }
+        crate::events::log(format!(
+            "Removing {:?} ({:.2} MB) to stay within the cache budget",
+            candidate.path,
+            candidate.size as f64 / 1_048_576.0
+        ));
+        report.remove(&candidate.path).await;
+        for companion in &candidate.companions {
+            report.remove(companion).await;
+        }
+        total -= candidate.size;
+    }
+    Ok(report)
+}
+
+async fn subdirs(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = match fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut dirs = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_type().await?.is_dir() {
+            dirs.push(entry.path());
+        }
+    }
+    Ok(dirs)
+}
+
+/// Total size of the files in `dir` and when the most recently used one was
+/// last used.
+pub(crate) async fn dir_usage(dir: &Path) -> Result<(u64, u64)> {
+    let mut pending = vec![dir.to_path_buf()];
+    let (mut size, mut used) = (0, 0);
+    while let Some(dir) = pending.pop() {
+        let mut entries = fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else {
+                size += metadata.len();
+                used = used.max(last_used(&metadata));
+            }
+        }
+    }
+    Ok((size, used))
+}
+
+/// Access time where the filesystem keeps it, else modification time.
+fn last_used(metadata: &std::fs::Metadata) -> u64 {
+    let modified = metadata.modified().ok();
+    metadata
+        .accessed()
+        .ok()
+        .max(modified)
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,78 @@
+//! `cursorup changelog` and `status --open`: where a release's notes are,
+//! opened in the default browser with `xdg-open` when asked. Without a
+//! graphical session, or if `xdg-open` fails, the URL is printed instead.
+
+use crate::config::Config;
+use crate::{Result, urls};
+use serde::Serialize;
+use std::fmt;
+use std::process::Stdio;
+use tokio::process::Command;
+
+#[derive(Serialize, Debug)]
+pub struct Changelog {
+    pub version: String,
+    pub url: String,
+}
+
+impl fmt::Display for Changelog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Cursor {} release notes: {}", self.version, self.url)
+    }
+}
+
+impl Changelog {
+    fn new(version: String) -> Changelog {
+        Changelog {
+            url: urls::changelog_url(&version),
+            version,
+        }
+    }
+}
+
+/// Release notes of the latest release of the configured track.
+pub async fn latest(config: &Config) -> Result<Changelog> {
+    Ok(Changelog::new(crate::latest_version(config).await?.version))
+}
+
+/// Release notes of the installed version.
+pub async fn installed(config: &Config) -> Result<Changelog> {
+    let version = match crate::state::State::load().await? {
+        Some(state) => Some(state.version),
+        None => crate::status::installed_appimage(&config.install_dir)
+            .await?
+            .and_then(|path| crate::status::version_from_file_name(&path)),
+    };
+    let version = version.ok_or("Cursor is not installed")?;
+    Ok(Changelog::new(version))
+}
+
+/// Opens `url` in the default browser. Returns false when there is no
+/// graphical session or `xdg-open` is missing or fails, so the caller can
+/// print the URL instead.
+pub async fn open_in_browser(url: &str) -> bool {
+    let graphical = ["DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|var| std::env::var_os(var).is_some_and(|value| !value.is_empty()));
+    if !graphical {
+        return false;
+    }
+    let status = Command::new("xdg-open")
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await;
+    match status {
+        Ok(status) if status.success() => true,
+        Ok(status) => {
+            tracing::debug!("xdg-open {} exited with {}", url, status);
+            false
+        }
+        Err(e) => {
+            tracing::debug!("could not run xdg-open: {}", e);
+            false
+        }
+    }
+}
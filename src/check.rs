@@ -0,0 +1,300 @@
+use crate::config::Config;
+use crate::{ReleaseTrack, Resp, Result};
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// Exit status of `cursorup check` when an update is available; 0 means up
+/// to date and anything else an error.
+pub const EXIT_UPDATE_AVAILABLE: i32 = 10;
+
+/// Versions of the `--porcelain` output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Porcelain {
+    /// `cursor <current|-> <latest> <true|false>`.
+    V1,
+    /// One line of compact JSON, answered from cached metadata.
+    V2,
+}
+
+impl FromStr for Porcelain {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "v1" => Ok(Porcelain::V1),
+            "v2" => Ok(Porcelain::V2),
+            _ => Err(format!("unknown porcelain version `{}`", s)),
+        }
+    }
+}
+
+/// Installed vs. latest version for the configured release track.
+#[derive(Serialize, Debug)]
+pub struct Check {
+    pub current: Option<String>,
+    pub latest: String,
+    pub update_available: bool,
+}
+
+impl Check {
+    /// Stable single-line form for meta-updaters such as topgrade:
+    /// `cursor <current|-> <latest> <true|false>`, space separated.
+    pub fn porcelain(&self) -> String {
+        format!(
+            "cursor {} {} {}",
+            self.current.as_deref().unwrap_or("-"),
+            self.latest,
+            self.update_available
+        )
+    }
+}
+
+/// A `Check` answered from cached metadata, for `--porcelain=v2`.
+#[derive(Serialize, Debug)]
+pub struct CachedCheck {
+    #[serde(flatten)]
+    pub check: Check,
+    /// Seconds since the metadata was fetched.
+    pub age: u64,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Installed: {}",
+            self.current.as_deref().unwrap_or("not installed")
+        )?;
+        writeln!(f, "Latest:    {}", self.latest)?;
+        let ahead = self.current.as_deref().and_then(|current| {
+            crate::version::compare(current, &self.latest).filter(|ordering| ordering.is_gt())
+        });
+        if self.update_available {
+            write!(f, "An update is available.")
+        } else if ahead.is_some() {
+            write!(f, "The installed version is newer than the latest release.")
+        } else {
+            write!(f, "Cursor is up to date.")
+        }
+    }
+}
+
+pub async fn check(config: &Config, metadata: &Resp) -> Result<Check> {
+    let state = crate::state::State::load_or_recover(config).await?;
+    // The recorded version wins while it still describes the installed
+    // file, which may be named without one, e.g. after `adopt`.
+    let current = crate::status::installed_appimage(&config.install_dir)
+        .await?
+        .and_then(|path| match state.filter(|state| state.appimage == path) {
+            Some(state) => Some(state.version),
+            None => crate::status::version_from_file_name(&path),
+        });
+    let update_available = match current.as_deref() {
+        None => true,
+        Some(current) => match crate::version::compare(current, &metadata.version) {
+            Some(ordering) => ordering.is_lt(),
+            // Unrecognised version strings can only be told apart.
+            None => current != metadata.version,
+        },
+    };
+    Ok(Check {
+        current,
+        latest: metadata.version.clone(),
+        update_available,
+    })
+}
+
+/// `check` for shell prompts and other frequent callers: uses the metadata
+/// cached by the last fetch while it is younger than `check_interval`, and
+/// only goes to the network (without the connectivity probe) when it is
+/// stale or missing.
+pub async fn cached(config: &Config) -> Result<CachedCheck> {
+    let now = crate::state::now();
+    let cached = crate::cache::load_metadata(config.platform, config.release_track)
+        .await?
+        .filter(|cached| now.saturating_sub(cached.fetched_at) < config.check_interval);
+    let (metadata, fetched_at) = match cached {
+        Some(cached) => (cached.metadata, cached.fetched_at),
+        None => {
+            let client = crate::http::client(config)?;
+            let metadata = crate::fetch_metadata(
+                &client,
+                &config.api_url,
+                config.platform,
+                config.release_track,
+            )
+            .await?;
+            (metadata, now)
+        }
+    };
+    Ok(CachedCheck {
+        check: check(config, &metadata).await?,
+        age: now.saturating_sub(fetched_at),
+    })
+}
+
+/// The latest build of every release track side by side, for
+/// `check --all-tracks`.
+#[derive(Serialize, Debug)]
+pub struct TrackComparison {
+    pub current: Option<String>,
+    /// The track the config follows.
+    pub following: ReleaseTrack,
+    pub tracks: Vec<TrackRelease>,
+    /// The track with the newer version; `None` when they are on the same
+    /// version or it cannot be told.
+    pub ahead: Option<ReleaseTrack>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TrackRelease {
+    pub track: ReleaseTrack,
+    pub version: String,
+    pub commit_sha: String,
+    pub update_available: bool,
+}
+
+impl TrackComparison {
+    /// Whether the followed track has an update, which decides the exit
+    /// status as for a plain `check`.
+    pub fn update_available(&self) -> bool {
+        self.tracks
+            .iter()
+            .any(|release| release.track == self.following && release.update_available)
+    }
+}
+
+impl fmt::Display for TrackComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Installed: {} (following {})",
+            self.current.as_deref().unwrap_or("not installed"),
+            self.following
+        )?;
+        for release in &self.tracks {
+            writeln!(
+                f,
+                "{:<8} {:<12} {}  {}",
+                release.track.to_string(),
+                release.version,
+                release.commit_sha,
+                if release.update_available {
+                    "update available"
+                } else {
+                    "up to date"
+                }
+            )?;
+        }
+        let [first, second] = &self.tracks[..] else {
+            return Ok(());
+        };
+        match self.ahead {
+            Some(track) => {
+                let (ahead, behind) = if track == first.track {
+                    (first, second)
+                } else {
+                    (second, first)
+                };
+                write!(
+                    f,
+                    "{} is ahead of {}: {} vs {}",
+                    ahead.track, behind.track, ahead.version, behind.version
+                )
+            }
+            None if first.commit_sha == second.commit_sha => {
+                write!(f, "Both tracks are on the same build.")
+            }
+            None => write!(
+                f,
+                "Both tracks are on {}, built from different commits.",
+                first.version
+            ),
+        }
+    }
+}
+
+/// Fetches the latest build of every track concurrently and compares each
+/// with the installed version and with each other.
+pub async fn all_tracks(config: &Config) -> Result<TrackComparison> {
+    let client = crate::http::client(config)?;
+    let fetch = |track| crate::fetch_metadata(&client, &config.api_url, config.platform, track);
+    let (stable, latest) =
+        tokio::try_join!(fetch(ReleaseTrack::Stable), fetch(ReleaseTrack::Latest))?;
+    let mut current = None;
+    let mut tracks = Vec::new();
+    for (track, metadata) in [
+        (ReleaseTrack::Stable, stable),
+        (ReleaseTrack::Latest, latest),
+    ] {
+        let check = check(config, &metadata).await?;
+        current = check.current;
+        tracks.push(TrackRelease {
+            track,
+            version: metadata.version,
+            commit_sha: metadata.commit_sha,
+            update_available: check.update_available,
+        });
+    }
+    let ahead = match crate::version::compare(&tracks[0].version, &tracks[1].version) {
+        Some(std::cmp::Ordering::Greater) => Some(tracks[0].track),
+        Some(std::cmp::Ordering::Less) => Some(tracks[1].track),
+        _ => None,
+    };
+    Ok(TrackComparison {
+        current,
+        following: config.release_track,
+        tracks,
+        ahead,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(current: Option<&str>, latest: &str, update_available: bool) -> Check {
+        Check {
+            current: current.map(str::to_string),
+            latest: latest.to_string(),
+            update_available,
+        }
+    }
+
+    #[test]
+    fn porcelain_v1() {
+        assert_eq!(
+            check(Some("1.2.3"), "1.3.0", true).porcelain(),
+            "cursor 1.2.3 1.3.0 true"
+        );
+        assert_eq!(
+            check(None, "1.3.0", true).porcelain(),
+            "cursor - 1.3.0 true"
+        );
+        assert_eq!(
+            check(Some("1.3.0"), "1.3.0", false).porcelain(),
+            "cursor 1.3.0 1.3.0 false"
+        );
+    }
+
+    #[test]
+    fn porcelain_v2_is_one_json_line() {
+        let cached = CachedCheck {
+            check: check(Some("1.2.3"), "1.3.0", true),
+            age: 60,
+        };
+        assert_eq!(
+            serde_json::to_string(&cached).unwrap(),
+            r#"{"current":"1.2.3","latest":"1.3.0","update_available":true,"age":60}"#
+        );
+    }
+
+    #[test]
+    fn porcelain_versions() {
+        assert_eq!("v1".parse(), Ok(Porcelain::V1));
+        assert_eq!("v2".parse(), Ok(Porcelain::V2));
+        assert!("v3".parse::<Porcelain>().is_err());
+        assert!("V2".parse::<Porcelain>().is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use crate::config::Config;
+use crate::{Resp, Result};
+
+/// A checksum published by Cursor for a release, and where it came from.
+#[derive(Debug, Clone)]
+pub struct Published {
+    pub sha256: String,
+    pub source: String,
+}
+
+/// The SHA-256 the release's AppImage must have, from the first of:
+/// `pinned` (`--sha256`), a `sha256` field in the metadata, the configured
+/// `checksums_url` list, a `<download_url>.sha256` file on the CDN, and the
+/// recorded hash of the same build if it is the one installed. `None` means
+/// none of these exist and only the locally recorded hash is available.
+pub async fn published(
+    client: &reqwest::Client,
+    config: &Config,
+    pinned: Option<&str>,
+    metadata: &Resp,
+) -> Result<Option<Published>> {
+    if let Some(pinned) = pinned {
+        let sha256 = parse_sha256(pinned)
+            .ok_or_else(|| format!("--sha256 {} is not a SHA-256 checksum", pinned))?;
+        return Ok(Some(Published {
+            sha256,
+            source: "--sha256".to_string(),
+        }));
+    }
+    if let Some(sha256) = metadata.sha256.as_deref().and_then(parse_sha256) {
+        return Ok(Some(Published {
+            sha256,
+            source: "the release metadata".to_string(),
+        }));
+    }
+
+    let file_name = crate::urls::file_name_from_url(&metadata.download_url);
+    if let Some(url) = &config.checksums_url
+        && let Some(contents) = fetch(client, url).await?
+    {
+        return match parse_listed(&contents, file_name) {
+            Some(sha256) => Ok(Some(Published {
+                sha256,
+                source: url.clone(),
+            })),
+            None => Err(format!("{} lists no checksum for {}", url, file_name).into()),
+        };
+    }
+
+    let url = format!("{}.sha256", metadata.download_url);
+    if let Some(sha256) = fetch(client, &url).await?.as_deref().and_then(parse_sha256) {
+        return Ok(Some(Published {
+            sha256,
+            source: url,
+        }));
+    }
+    Ok(installed(config, metadata).await)
+}
+
+/// The body of `url`, or `None` if it cannot be fetched.
+async fn fetch(client: &reqwest::Client, url: &str) -> Result<Option<String>> {
+    let response = match client.get(url).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            crate::events::log(format!("Could not fetch {}: {}", url, e));
+            return Ok(None);
+        }
+    };
+    if !response.status().is_success() {
+        return Ok(None);
+    }
+    Ok(Some(response.text().await?))
+}
+
+/// The hash recorded in `provenance.json` when this very build was
+/// installed before, so reinstalling it must produce the same file.
+async fn installed(config: &Config, metadata: &Resp) -> Option<Published> {
+    let path = config.install_dir.join(crate::provenance::PROVENANCE_FILE);
+    let contents = tokio::fs::read_to_string(path).await.ok()?;
+    let provenance: crate::provenance::Provenance = serde_json::from_str(&contents).ok()?;
+    (provenance.version == metadata.version && provenance.commit_sha == metadata.commit_sha).then(
+        || Published {
+            sha256: provenance.sha256,
+            source: format!("the previous install of {}", metadata.version),
+        },
+    )
+}
+
+/// The hash of `file_name` in `sha256sum` output listing several files. A
+/// file holding a single hash without a name is taken as is.
+pub fn parse_listed(contents: &str, file_name: &str) -> Option<String> {
+    let lines: Vec<&str> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    if let [line] = lines.as_slice()
+        && line.split_whitespace().nth(1).is_none()
+    {
+        return parse_sha256(line);
+    }
+    lines.iter().find_map(|line| {
+        let (hash, name) = line.split_once(char::is_whitespace)?;
+        // `sha256sum -b` marks names with `*`; lists may carry paths.
+        let name = name.trim().trim_start_matches('*');
+        (name.rsplit('/').next() == Some(file_name))
+            .then(|| parse_sha256(hash))
+            .flatten()
+    })
+}
+
+/// The hash in `sha256sum` output or a bare hex digest, lowercased.
+/// Comment lines starting with `#` are skipped.
+pub fn parse_sha256(contents: &str) -> Option<String> {
+    let hash = contents
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))?
+        .split_whitespace()
+        .next()?;
+    (hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| hash.to_ascii_lowercase())
+}
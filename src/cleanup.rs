@@ -0,0 +1,214 @@
+//! Removing what cursorup wrote. Each operation returns a `CleanupReport`
+//! listing the paths removed, the bytes freed and what was left in place and
+//! why, so front-ends can show exactly what was cleaned:
+//!
+//! - `gc` drops cached downloads the installed version doesn't need and
+//!   enforces `cache_budget`;
+//! - `uninstall` removes the installed app, its launchers and the state
+//!   file, keeping backups, history and the download cache;
+//! - `purge` is `uninstall` plus backups, the state dir and the cache.
+//!
+//! Files cursorup didn't write, such as foreign files in the install dir or
+//! a desktop entry pointing elsewhere, are never removed. Nothing here
+//! touches the config file or Cursor's own user data.
+
+use crate::config::Config;
+use crate::state::State;
+use crate::{Result, events};
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+#[derive(Serialize, Debug, Default)]
+pub struct CleanupReport {
+    pub removed: Vec<Removed>,
+    pub skipped: Vec<Skipped>,
+    pub bytes_freed: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Removed {
+    pub path: PathBuf,
+    /// Size of the file, or of everything in the dir.
+    pub bytes: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Skipped {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+impl fmt::Display for CleanupReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.removed.is_empty() && self.skipped.is_empty() {
+            return write!(f, "Nothing to clean up.");
+        }
+        for removed in &self.removed {
+            writeln!(
+                f,
+                "Removed {:>9.2} MB  {}",
+                removed.bytes as f64 / 1_048_576.0,
+                removed.path.display()
+            )?;
+        }
+        for skipped in &self.skipped {
+            writeln!(f, "Kept     {}: {}", skipped.path.display(), skipped.reason)?;
+        }
+        write!(f, "Freed {:.2} MB.", self.bytes_freed as f64 / 1_048_576.0)
+    }
+}
+
+impl CleanupReport {
+    /// Removes the file, link or dir at `path`, recording it. A missing path
+    /// is not an error; any other failure is recorded as skipped so the rest
+    /// of the cleanup still happens.
+    pub async fn remove(&mut self, path: &Path) {
+        let metadata = match fs::symlink_metadata(path).await {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+            Err(e) => return self.skip(path, e.to_string()),
+        };
+        let removed = if metadata.is_dir() {
+            let bytes = crate::cache::dir_usage(path)
+                .await
+                .map(|(size, _)| size)
+                .unwrap_or_default();
+            fs::remove_dir_all(path).await.map(|()| bytes)
+        } else {
+            let bytes = if metadata.is_symlink() {
+                0
+            } else {
+                metadata.len()
+            };
+            fs::remove_file(path).await.map(|()| bytes)
+        };
+        match removed {
+            Ok(bytes) => {
+                self.bytes_freed += bytes;
+                self.removed.push(Removed {
+                    path: path.to_path_buf(),
+                    bytes,
+                });
+            }
+            Err(e) => self.skip(path, e.to_string()),
+        }
+    }
+
+    pub fn skip(&mut self, path: &Path, reason: impl Into<String>) {
+        self.skipped.push(Skipped {
+            path: path.to_path_buf(),
+            reason: reason.into(),
+        });
+    }
+
+    pub fn merge(&mut self, other: CleanupReport) {
+        self.removed.extend(other.removed);
+        self.skipped.extend(other.skipped);
+        self.bytes_freed += other.bytes_freed;
+    }
+}
+
+/// Removes cached downloads of versions other than the installed one and,
+/// with `cache_budget` set, whatever else exceeds it.
+pub async fn gc(config: &Config) -> Result<CleanupReport> {
+    let installed = State::load().await?.map(|state| state.version);
+    let mut report = crate::cache::prune_except(config.platform, installed.as_deref()).await?;
+    if let Some(budget) = config.cache_budget {
+        report.merge(crate::cache::enforce_budget(&config.install_dir, budget).await?);
+    }
+    Ok(report)
+}
+
+/// Removes the installed AppImage and icon, the `current` link, a staged
+/// update, the CLI wrapper, desktop entries, profile launchers and the
+/// state file. The install dir itself goes too once nothing else is in it.
+pub async fn uninstall(config: &Config) -> Result<CleanupReport> {
+    let mut report = CleanupReport::default();
+    remove_install(config, &mut report).await?;
+    remove_empty_dir(&config.install_dir, &mut report).await;
+    events::log(format!("Uninstalled Cursor from {:?}", config.install_dir));
+    Ok(report)
+}
+
+/// `uninstall`, then also the backups, the state dir with the history and
+/// the download cache.
+pub async fn purge(config: &Config) -> Result<CleanupReport> {
+    let mut report = CleanupReport::default();
+    remove_install(config, &mut report).await?;
+    report
+        .remove(&config.install_dir.join(crate::backup::BACKUP_DIR))
+        .await;
+    remove_empty_dir(&config.install_dir, &mut report).await;
+    report.remove(&State::dir()?).await;
+    report.remove(&crate::cache::dir()?).await;
+    Ok(report)
+}
+
+async fn remove_install(config: &Config, report: &mut CleanupReport) -> Result<()> {
+    let install_dir = &config.install_dir;
+    let classified = crate::manifest::classify(install_dir).await?;
+    match crate::manifest::Manifest::load(install_dir).await? {
+        Some(manifest) => {
+            for name in &manifest.files {
+                report.remove(&install_dir.join(name)).await;
+            }
+        }
+        None => {
+            for path in &classified.tracked {
+                report.remove(path).await;
+            }
+        }
+    }
+    for path in &classified.foreign {
+        report.skip(path, "not installed by cursorup");
+    }
+    report
+        .remove(&install_dir.join(crate::wrapper::CURRENT_LINK))
+        .await;
+    report
+        .remove(&install_dir.join(crate::manifest::MANIFEST_FILE))
+        .await;
+    report.remove(&crate::stage::dir(config)).await;
+
+    // Launchers outside the install dir are only removed when they start
+    // this install.
+    let target = install_dir.to_string_lossy().into_owned();
+    for path in [
+        crate::wrapper::wrapper_path()?,
+        crate::desktop_entry_path(config.system_install)?,
+    ] {
+        match fs::read_to_string(&path).await {
+            Ok(contents) if contents.contains(&target) => report.remove(&path).await,
+            Ok(_) => report.skip(&path, format!("does not start {}", target)),
+            Err(_) => {}
+        }
+    }
+    let shim = crate::shim::path()?;
+    if crate::shim::is_shim(&shim).await {
+        report.remove(&shim).await;
+    }
+    for profile in crate::profiles::Profiles::load().await?.0 {
+        report.remove(&profile.desktop_entry).await;
+        report.remove(&profile.wrapper).await;
+    }
+
+    report.remove(&crate::profiles::Profiles::path()?).await;
+    report.remove(&crate::daemon::PendingRetry::path()?).await;
+    report.remove(&crate::journal::root()?).await;
+    report.remove(&State::path()?).await;
+    Ok(())
+}
+
+/// Removes `dir` if it is empty, else records what kept it.
+async fn remove_empty_dir(dir: &Path, report: &mut CleanupReport) {
+    match fs::remove_dir(dir).await {
+        Ok(()) => report.removed.push(Removed {
+            path: dir.to_path_buf(),
+            bytes: 0,
+        }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(_) => report.skip(dir, "still holds backups or files cursorup didn't write"),
+    }
+}
@@ -0,0 +1,652 @@
+use cursorup::check::Porcelain;
+use cursorup::events::Interaction;
+use cursorup::export::ExportOptions;
+use cursorup::http::{IpVersion, Resolve};
+use cursorup::output::Format;
+use cursorup::{InstallOptions, Platform, ReleaseTrack};
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: cursorup [OPTIONS] [COMMAND]
+
+Commands:
+  update   Download and install the latest Cursor unless it is installed (default)
+  install  Download and install the latest Cursor, even if it is installed
+  commit   Install the update prepared with `update --stage`, offline
+  download Download and verify the release into --output-dir without installing
+  resume [<id>]   Finish a failed update from the step where it stopped
+  check    Compare the installed version with the latest release; exits with 10
+           if an update is available, 0 if up to date
+  adopt    Take over a hand-installed AppImage in the install dir
+  rollback Restore the most recently backed-up version
+  gc       Remove cached downloads the installed version doesn't need
+  uninstall       Remove the app, launchers and state; keeps backups and history
+  du       Show the disk space of the app, backups, cache and state, and what
+           the cleanup commands would free
+  status   Show what is currently installed (--open: its release notes in the browser)
+  changelog       Show the latest release's notes (--open: in the browser)
+  info     Show the latest release metadata
+  plan     Show what an update would do
+  env      Show effective paths and settings
+  doctor   Probe DNS, TCP, TLS and HTTP reachability of the Cursor API
+  history export  Print recorded update attempts (use with --since, --format csv)
+  verify   Re-hash the installed AppImage against the checksum recorded at install
+  backups ls      List backed-up versions with size and date
+  backups verify  Re-hash backups against their recorded checksums
+  daemon   Check for updates periodically (installing them if auto_update is set)
+  watch    Take over AppImages that other tools put in the install dir, fixing
+           the state file and launchers
+  rpc      Speak JSON-RPC 2.0 over stdin/stdout (one message per line)
+  self update     Replace cursorup with its latest verified release
+  lock export     Print a lock file pinning the installed build, for `install --locked`
+  telemetry show  Show whether the anonymous usage ping is on and what it sends
+  profile add <name>  Add a launcher with its own --user-data-dir and --extensions-dir
+  profile ls          List the launcher profiles
+  profile rm <name>   Remove a profile's launchers, keeping its data
+  shim code           Add a `code` command running Cursor next to `cursor`
+                      (refuses if VS Code's `code` is on PATH, unless --force)
+  shim rm code        Remove the `code` command again
+  help     Print this message
+
+Options:
+  --format <text|json|yaml|csv>  Output format for reporting commands
+  --json                     Same as --format json
+  --output <text|json>       With json, print progress as one JSON event per line
+                             (metadata-fetched, download-progress, ..., error)
+  --commit <sha>             Install the build with this full commit SHA
+  --version <version>        Install this version, e.g. 1.2.3 or 1.2.x; with --commit,
+                             the version of that build
+  --skip-download            Install the cached download of the resolved version
+  --no-cache                 Download again even if cached, and drop the download after installing
+  --keep-download            Keep cached downloads of other versions after this install
+  --connections <n>          Download over up to n connections at once (1-16)
+  --limit-rate <size>        Download at most this much per second, e.g. 2M
+  --sha256 <hex>             Refuse to install unless the download has this SHA-256
+  --locked <file>            Make `install` install exactly the build in this lock file
+  --force                    Make `update` reinstall even if the latest version is installed,
+                             and `shim code` shadow another `code` command
+  --dry-run                  Make `update` or `install` only print what they would download,
+                             back up and write, changing nothing
+  --force-metered            Make `daemon` install updates on a metered connection too
+  --stage                    Make `update` only download and prepare; see `commit`
+  --no-backup                Delete the installed version instead of backing it up
+                             (asks first in a terminal; leaves nothing to roll back to)
+  --max-duration <age>       Cancel the install or update if it takes longer, e.g. 10m
+                             (what it got done is kept for the next run or `resume`)
+  --targets <list>           Install these components of one build together, e.g.
+                             desktop,reh,cli (all or nothing)
+  --output-dir <dir>         Where `download` puts the AppImage (default: current dir)
+  --layout <flat|versioned>  Put downloads directly in --output-dir or in a <version>/ subdir
+  --file-name <template>     Name downloads e.g. cursor-{version}-{arch}.AppImage
+                             (also {platform}, {track}, {commit})
+  --install-dir <dir>        Install into this dir for this run instead of install_dir
+  --system                   Install for all users into /opt/cursor, asking for root
+                             via pkexec only to set it up
+  --user-data-dir <dir>      Cursor data dir for `profile add`
+  --extensions-dir <dir>     Extensions dir for `profile add` (default: <user-data-dir>/extensions)
+  --profile <name>           Apply the settings of [profile.<name>] from the config
+  --set <key=value>          Override a config key for this run (repeatable)
+  --name <name>              Launcher name for the desktop entry, e.g. \"Cursor (Work)\"
+  --icon <file>              Use this icon instead of Cursor's from now on (saved in the config)
+  --proxy <url>              HTTP(S) or SOCKS5 proxy for this run, e.g. http://proxy:3128
+  --api-url <url>            Release API to query for this run, e.g. an internal mirror
+                             (also CURSORUP_API_URL)
+  --ca-cert <file>           Also trust the root certificates in this PEM file
+  --insecure                 Do not check TLS certificates at all (dangerous)
+  --platform <linux-x64|linux-arm64>  Build to fetch for this run (default: this machine's)
+  --channel <stable|latest>  Follow this release track from now on (saved in the config)
+  --ipv4, --ipv6             Only connect over IPv4 or IPv6
+  --resolve <host:port:addr> Resolve host to addr, like curl (repeatable)
+  --porcelain                Print `check` as one stable line: cursor <current|-> <latest> <true|false>
+  --porcelain=v2             Print `check` as one line of JSON from cached metadata, for prompts
+  --all-platforms            Make `info` show every platform and release track
+  --all-tracks               Make `check` compare the stable and latest tracks
+  --allow-unsigned           Let `self update` install a release without a checksum or signature check
+  --open                     Open release notes from `changelog` or `status` with xdg-open
+  --purge                    Make `uninstall` also delete backups, history and the download cache
+  --since <age>              Limit `history export` to e.g. the last 30d (s, m, h, d, w)
+  --verbose                  Include download throughput history in `status` and
+                             show desktop entry changes in `update` and `commit`
+  -v, -vv                    Also log debug (-v) or trace (-vv) details to stderr
+  -q, --quiet                Print only warnings, errors and command results
+  -y, --yes                  Answer yes to every confirmation, e.g. replacing the install
+  --non-interactive          Never ask; every step does what it does without a terminal
+  --defaults                 Write the default config instead of running the setup wizard
+  --                         Treat the remaining arguments as names, e.g. a profile named -x";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Update,
+    Install,
+    Commit,
+    Download,
+    Resume,
+    Check,
+    Adopt,
+    Rollback,
+    Gc,
+    Du,
+    Uninstall,
+    Changelog,
+    TelemetryShow,
+    Status,
+    Info,
+    Plan,
+    Env,
+    Doctor,
+    HistoryExport,
+    BackupsList,
+    BackupsVerify,
+    Verify,
+    SelfUpdate,
+    LockExport,
+    ProfileAdd,
+    ProfileList,
+    ProfileRemove,
+    ShimCode,
+    ShimRemove,
+    Daemon,
+    Watch,
+    Rpc,
+    Help,
+}
+
+#[derive(Debug)]
+pub struct Cli {
+    pub command: Command,
+    pub format: Format,
+    pub defaults: bool,
+    pub porcelain: Option<Porcelain>,
+    pub install: InstallOptions,
+    pub export: ExportOptions,
+    pub name: Option<String>,
+    pub proxy: Option<String>,
+    /// `--api-url`: release API for this run.
+    pub api_url: Option<String>,
+    /// `--ca-cert`: extra root certificates for this run.
+    pub ca_cert: Option<PathBuf>,
+    /// `--insecure`: skip TLS certificate checks for this run.
+    pub insecure: bool,
+    /// Custom icon from `--icon`, saved in the config.
+    pub icon: Option<PathBuf>,
+    /// `--install-dir`: install dir for this run.
+    pub install_dir: Option<PathBuf>,
+    pub overrides: Vec<(String, String)>,
+    pub ip_version: Option<IpVersion>,
+    pub channel: Option<ReleaseTrack>,
+    pub platform: Option<Platform>,
+    pub resolve: Vec<Resolve>,
+    pub allow_unsigned: bool,
+    pub all_platforms: bool,
+    pub all_tracks: bool,
+    /// `--keep-download`: skip pruning the cache after this install.
+    pub keep_download: bool,
+    /// `--locked`: lock file for `install`.
+    pub locked: Option<PathBuf>,
+    /// `--force`: `update` installs even when up to date, like `install`.
+    pub force: bool,
+    /// `--force-metered`: `daemon` installs even on a metered connection.
+    pub force_metered: bool,
+    /// `--dry-run`: print the plan of `update` or `install` instead of running it.
+    pub dry_run: bool,
+    /// `--connections`: parallel connections per download for this run.
+    pub connections: Option<String>,
+    /// `--limit-rate`: download speed cap for this run.
+    pub limit_rate: Option<String>,
+    pub purge: bool,
+    pub open: bool,
+    pub verbose: bool,
+    /// How many `-v`: 1 logs debug details, 2 and more trace details.
+    pub log_verbosity: u8,
+    pub quiet: bool,
+    /// `--yes` or `--non-interactive`: how confirmations are answered.
+    pub interaction: Interaction,
+    /// `--output json`: events as newline-delimited JSON on stdout.
+    pub json_events: bool,
+    /// Maximum age in seconds, from `--since`.
+    pub since: Option<u64>,
+    pub profile: Option<String>,
+    pub system: bool,
+    /// Journal id given to `resume`; the latest one if absent.
+    pub resume_id: Option<String>,
+    /// Name given to `profile add` or `profile rm`.
+    pub profile_name: Option<String>,
+    pub user_data_dir: Option<PathBuf>,
+    pub extensions_dir: Option<PathBuf>,
+}
+
+impl Cli {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Cli, String> {
+        let mut command = None;
+        let mut format = Format::default();
+        let mut defaults = false;
+        let mut porcelain = None;
+        let mut install = InstallOptions::default();
+        let mut export = ExportOptions::default();
+        let mut name = None;
+        let mut proxy = None;
+        let mut api_url = None;
+        let mut ca_cert = None;
+        let mut insecure = false;
+        let mut icon = None;
+        let mut install_dir = None;
+        let mut overrides = Vec::new();
+        let mut ip_version = None;
+        let mut channel = None;
+        let mut platform = None;
+        let mut resolve = Vec::new();
+        let mut allow_unsigned = false;
+        let mut all_platforms = false;
+        let mut all_tracks = false;
+        let mut keep_download = false;
+        let mut locked = None;
+        let mut force = false;
+        let mut dry_run = false;
+        let mut force_metered = false;
+        let mut connections = None;
+        let mut limit_rate = None;
+        let mut purge = false;
+        let mut open = false;
+        let mut verbose = false;
+        let mut log_verbosity = 0;
+        let mut quiet = false;
+        let mut assume_yes = false;
+        let mut non_interactive = false;
+        let mut json_events = false;
+        let mut since = None;
+        let mut profile = None;
+        let mut system = false;
+        let mut user_data_dir = None;
+        let mut extensions_dir = None;
+
+        let mut positionals = Vec::new();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                positionals.extend(args.by_ref());
+                break;
+            }
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => {
+                    (flag.to_string(), Some(value.to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("`{}` expects a value", flag))
+            };
+            match flag.as_str() {
+                "--format" => format = value()?.parse()?,
+                "--json" => format = Format::Json,
+                "--output" => {
+                    json_events = match value()?.as_str() {
+                        "json" => true,
+                        "text" => false,
+                        other => {
+                            return Err(format!(
+                                "unknown output `{}` (expected text or json)",
+                                other
+                            ));
+                        }
+                    }
+                }
+                "--defaults" => defaults = true,
+                "--commit" => install.commit = Some(value()?),
+                "--version" => install.version = Some(value()?),
+                "--skip-download" => install.skip_download = true,
+                "--no-cache" => install.no_cache = true,
+                "--keep-download" => keep_download = true,
+                "--connections" => connections = Some(value()?),
+                "--limit-rate" => limit_rate = Some(value()?),
+                "--sha256" => install.sha256 = Some(value()?),
+                "--locked" => locked = Some(value()?.into()),
+                "--force" => force = true,
+                "--dry-run" => dry_run = true,
+                "--force-metered" => force_metered = true,
+                "--stage" => install.stage = true,
+                "--no-backup" => install.no_backup = true,
+                "--max-duration" => {
+                    install.max_duration = Some(cursorup::history::parse_age(&value()?)?)
+                }
+                "--targets" => install.targets = cursorup::targets::parse_list(&value()?)?,
+                "--output-dir" => export.output_dir = value()?.into(),
+                "--layout" => export.layout = value()?.parse()?,
+                "--file-name" => export.file_name = Some(value()?),
+                "--name" => name = Some(value()?),
+                "--proxy" => proxy = Some(value()?),
+                "--api-url" => api_url = Some(value()?),
+                "--ca-cert" => ca_cert = Some(value()?.into()),
+                "--insecure" => insecure = true,
+                "--icon" => icon = Some(value()?.into()),
+                "--install-dir" => install_dir = Some(value()?.into()),
+                "--channel" => channel = Some(value()?.parse()?),
+                "--platform" => platform = Some(value()?.parse()?),
+                "--ipv4" => ip_version = Some(IpVersion::V4),
+                "--ipv6" => ip_version = Some(IpVersion::V6),
+                "--resolve" => resolve.push(value()?.parse()?),
+                "--allow-unsigned" => allow_unsigned = true,
+                "--all-platforms" => all_platforms = true,
+                "--all-tracks" => all_tracks = true,
+                "--purge" => purge = true,
+                "--open" => open = true,
+                "-v" => log_verbosity += 1,
+                "-vv" => log_verbosity += 2,
+                "-q" | "--quiet" => quiet = true,
+                "-y" | "--yes" => assume_yes = true,
+                "--non-interactive" => non_interactive = true,
+                "--verbose" => {
+                    verbose = true;
+                    install.verbose = true;
+                }
+                "--since" => since = Some(cursorup::history::parse_age(&value()?)?),
+                "--profile" => profile = Some(value()?),
+                "--system" => system = true,
+                "--user-data-dir" => user_data_dir = Some(value()?.into()),
+                "--extensions-dir" => extensions_dir = Some(value()?.into()),
+                "--set" => {
+                    let assignment = value()?;
+                    let (key, value) = assignment
+                        .split_once('=')
+                        .ok_or_else(|| format!("`--set {}` expects key=value", assignment))?;
+                    overrides.push((key.trim().to_string(), value.to_string()));
+                }
+                "--porcelain" => {
+                    porcelain = Some(match inline_value.as_deref() {
+                        None => Porcelain::V1,
+                        Some(version) => version.parse()?,
+                    })
+                }
+                "-h" | "--help" => command = Some(Command::Help),
+                _ if flag.starts_with('-') => return Err(format!("unknown option `{}`", flag)),
+                _ => positionals.push(arg),
+            }
+        }
+
+        let positionals: Vec<&str> = positionals.iter().map(String::as_str).collect();
+        let mut resume_id = None;
+        let mut profile_name = None;
+        let parsed = match positionals.as_slice() {
+            [] => None,
+            ["update"] => Some(Command::Update),
+            ["install"] => Some(Command::Install),
+            ["commit"] => Some(Command::Commit),
+            ["download"] => Some(Command::Download),
+            ["resume"] => Some(Command::Resume),
+            ["resume", id] => {
+                resume_id = Some(id.to_string());
+                Some(Command::Resume)
+            }
+            ["check"] => Some(Command::Check),
+            ["adopt"] => Some(Command::Adopt),
+            ["rollback"] => Some(Command::Rollback),
+            ["gc"] => Some(Command::Gc),
+            ["du"] => Some(Command::Du),
+            ["uninstall"] => Some(Command::Uninstall),
+            ["status"] => Some(Command::Status),
+            ["changelog"] => Some(Command::Changelog),
+            ["info"] => Some(Command::Info),
+            ["plan"] => Some(Command::Plan),
+            ["env"] => Some(Command::Env),
+            ["doctor"] => Some(Command::Doctor),
+            ["history", "export"] => Some(Command::HistoryExport),
+            ["backups"] | ["backups", "ls"] => Some(Command::BackupsList),
+            ["backups", "verify"] => Some(Command::BackupsVerify),
+            ["verify"] => Some(Command::Verify),
+            ["self", "update"] => Some(Command::SelfUpdate),
+            ["lock", "export"] => Some(Command::LockExport),
+            ["telemetry"] | ["telemetry", "show"] => Some(Command::TelemetryShow),
+            ["profile", "add", name] => {
+                if user_data_dir.is_none() {
+                    return Err("`profile add` expects --user-data-dir".to_string());
+                }
+                profile_name = Some(name.to_string());
+                Some(Command::ProfileAdd)
+            }
+            ["profile"] | ["profile", "ls"] => Some(Command::ProfileList),
+            ["profile", "rm", name] => {
+                profile_name = Some(name.to_string());
+                Some(Command::ProfileRemove)
+            }
+            ["shim", "code"] => Some(Command::ShimCode),
+            ["shim", "rm", "code"] => Some(Command::ShimRemove),
+            ["daemon"] => Some(Command::Daemon),
+            ["watch"] => Some(Command::Watch),
+            ["rpc"] => Some(Command::Rpc),
+            ["help"] => Some(Command::Help),
+            [command, ..] if is_command(command) => {
+                return Err(format!("unexpected arguments for `{}`", command));
+            }
+            [command, ..] => return Err(format!("unknown command `{}`", command)),
+        };
+        // `--help` wins over any command given alongside it.
+        let command = command.or(parsed);
+        if quiet && log_verbosity > 0 {
+            return Err("--quiet cannot be combined with -v".to_string());
+        }
+        if assume_yes && non_interactive {
+            return Err("--yes cannot be combined with --non-interactive".to_string());
+        }
+        if locked.is_some() && !matches!(command, Some(Command::Install | Command::Help)) {
+            return Err("--locked only applies to `install`".to_string());
+        }
+        if force
+            && !matches!(
+                command,
+                None | Some(Command::Update | Command::Install | Command::ShimCode | Command::Help)
+            )
+        {
+            return Err("--force only applies to `update` and `shim code`".to_string());
+        }
+        if dry_run
+            && !matches!(
+                command,
+                None | Some(Command::Update | Command::Install | Command::Help)
+            )
+        {
+            return Err("--dry-run only applies to `update` and `install`".to_string());
+        }
+        if force_metered && !matches!(command, Some(Command::Daemon | Command::Help)) {
+            return Err("--force-metered only applies to `daemon`".to_string());
+        }
+        if dry_run && locked.is_some() {
+            return Err("--dry-run cannot be combined with --locked".to_string());
+        }
+
+        Ok(Cli {
+            command: command.unwrap_or(Command::Update),
+            format,
+            defaults,
+            porcelain,
+            install,
+            export,
+            name,
+            proxy,
+            api_url,
+            ca_cert,
+            insecure,
+            icon,
+            install_dir,
+            overrides,
+            ip_version,
+            channel,
+            platform,
+            resolve,
+            allow_unsigned,
+            all_platforms,
+            all_tracks,
+            keep_download,
+            locked,
+            force,
+            dry_run,
+            force_metered,
+            connections,
+            limit_rate,
+            purge,
+            open,
+            verbose,
+            log_verbosity,
+            quiet,
+            interaction: if assume_yes {
+                Interaction::AssumeYes
+            } else if non_interactive {
+                Interaction::Never
+            } else {
+                Interaction::Ask
+            },
+            json_events,
+            since,
+            profile,
+            system,
+            resume_id,
+            profile_name,
+            user_data_dir,
+            extensions_dir,
+        })
+    }
+}
+
+fn is_command(word: &str) -> bool {
+    matches!(
+        word,
+        "update"
+            | "install"
+            | "commit"
+            | "download"
+            | "resume"
+            | "check"
+            | "adopt"
+            | "rollback"
+            | "gc"
+            | "du"
+            | "uninstall"
+            | "status"
+            | "verify"
+            | "changelog"
+            | "info"
+            | "plan"
+            | "env"
+            | "doctor"
+            | "history"
+            | "backups"
+            | "self"
+            | "lock"
+            | "telemetry"
+            | "profile"
+            | "shim"
+            | "daemon"
+            | "watch"
+            | "rpc"
+            | "help"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, String> {
+        Cli::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn command(args: &[&str]) -> Command {
+        parse(args).unwrap().command
+    }
+
+    #[test]
+    fn dispatches_subcommands() {
+        assert_eq!(command(&[]), Command::Update);
+        assert_eq!(command(&["check"]), Command::Check);
+        assert_eq!(command(&["self", "update"]), Command::SelfUpdate);
+        assert_eq!(command(&["backups"]), Command::BackupsList);
+        assert_eq!(command(&["backups", "ls"]), Command::BackupsList);
+        assert_eq!(command(&["shim", "rm", "code"]), Command::ShimRemove);
+        assert_eq!(command(&["check", "--help"]), Command::Help);
+
+        let resume = parse(&["resume", "1700000000"]).unwrap();
+        assert_eq!(resume.command, Command::Resume);
+        assert_eq!(resume.resume_id.as_deref(), Some("1700000000"));
+
+        let err = parse(&["status", "now"]).unwrap_err();
+        assert_eq!(err, "unexpected arguments for `status`");
+        let err = parse(&["upgrade"]).unwrap_err();
+        assert_eq!(err, "unknown command `upgrade`");
+        assert!(parse(&["profile", "add", "work"]).is_err());
+    }
+
+    #[test]
+    fn global_flags_go_anywhere() {
+        let before = parse(&["--json", "-q", "-y", "status"]).unwrap();
+        let after = parse(&["status", "--json", "-q", "-y"]).unwrap();
+        for cli in [before, after] {
+            assert_eq!(cli.command, Command::Status);
+            assert_eq!(cli.format, Format::Json);
+            assert!(cli.quiet);
+            assert_eq!(cli.interaction, Interaction::AssumeYes);
+        }
+
+        let cli = parse(&["-v", "-v", "--format=yaml", "--output", "json"]).unwrap();
+        assert_eq!(cli.log_verbosity, 2);
+        assert_eq!(cli.format, Format::Yaml);
+        assert!(cli.json_events);
+
+        let cli = parse(&["check", "--porcelain=v2", "--proxy=socks5://h:1080"]).unwrap();
+        assert_eq!(cli.porcelain, Some(Porcelain::V2));
+        assert_eq!(cli.proxy.as_deref(), Some("socks5://h:1080"));
+    }
+
+    #[test]
+    fn rejects_bad_flag_values_and_combinations() {
+        assert_eq!(
+            parse(&["--proxy"]).unwrap_err(),
+            "`--proxy` expects a value"
+        );
+        assert!(parse(&["--output", "xml"]).is_err());
+        assert!(parse(&["-q", "-v"]).is_err());
+        assert!(parse(&["--yes", "--non-interactive"]).is_err());
+        assert!(parse(&["status", "--dry-run"]).is_err());
+        assert!(parse(&["check", "--force-metered"]).is_err());
+    }
+
+    #[test]
+    fn set_overrides() {
+        let cli = parse(&["--set", " connections =4", "--set=proxy=http://h:3128"]).unwrap();
+        assert_eq!(
+            cli.overrides,
+            [
+                ("connections".to_string(), "4".to_string()),
+                ("proxy".to_string(), "http://h:3128".to_string())
+            ]
+        );
+        assert_eq!(
+            parse(&["--set", "connections"]).unwrap_err(),
+            "`--set connections` expects key=value"
+        );
+        assert!(parse(&["--set"]).is_err());
+    }
+
+    #[test]
+    fn double_dash_ends_options() {
+        let cli = parse(&["-q", "profile", "rm", "--", "-work"]).unwrap();
+        assert_eq!(cli.command, Command::ProfileRemove);
+        assert_eq!(cli.profile_name.as_deref(), Some("-work"));
+        assert!(cli.quiet);
+        assert_eq!(
+            parse(&["--", "--help"]).unwrap_err(),
+            "unknown command `--help`"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_flags() {
+        assert_eq!(parse(&["--nope"]).unwrap_err(), "unknown option `--nope`");
+        assert_eq!(
+            parse(&["update", "--nope=1"]).unwrap_err(),
+            "unknown option `--nope`"
+        );
+        assert_eq!(parse(&["-x"]).unwrap_err(), "unknown option `-x`");
+    }
+}
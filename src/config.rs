@@ -0,0 +1,892 @@
+use crate::Result;
+use crate::execution::ExecutionMode;
+use crate::http::{IpVersion, Resolve};
+use crate::metered::MeteredPolicy;
+use crate::restart::RestartStrategy;
+use crate::system::Elevation;
+use crate::{Platform, ReleaseTrack};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// User settings persisted at `~/.config/cursorup/config.toml`.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub install_dir: PathBuf,
+    /// Install for all users: the desktop entry goes to
+    /// `/usr/share/applications` and the install dir is set up with root
+    /// privileges once.
+    pub system_install: bool,
+    /// How to get those privileges when not running as root.
+    pub elevate: Elevation,
+    /// Defaults to the build for this machine's architecture.
+    pub platform: Platform,
+    pub release_track: ReleaseTrack,
+    pub auto_update: bool,
+    /// What the daemon does with an update on a metered connection.
+    pub metered_updates: MeteredPolicy,
+    /// Seconds between update checks in daemon mode.
+    pub check_interval: u64,
+    /// Address (`host:port`) to serve Prometheus metrics on in daemon mode.
+    pub metrics_listen: Option<String>,
+    pub desktop_entry: bool,
+    /// `Name=` of the desktop entry, e.g. "Cursor (Work)".
+    pub desktop_name: String,
+    /// Icon to install instead of the one shipped in the AppImage, e.g. to
+    /// tell a nightly apart. It is copied in again on every update.
+    pub icon: Option<PathBuf>,
+    pub cli_wrapper: bool,
+    /// Compress backed-up AppImages with zstd.
+    pub compress_backups: bool,
+    /// Install AppImages as hardlinks (or reflinks) of the cached download,
+    /// and restore backups the same way, so a generation kept in the cache,
+    /// the install dir and `back/` is stored once.
+    pub hardlinks: bool,
+    /// Bytes that cached downloads and compressed backups may take up
+    /// together; the least recently used are pruned after each install.
+    pub cache_budget: Option<u64>,
+    /// Shell command run on a copy of each verified download before it is
+    /// installed, with the copy's path as `$1`; see `transform`.
+    pub transform: Option<String>,
+    /// Keep the downloads of other versions in the cache after an install,
+    /// e.g. to switch back and forth or copy them to other machines;
+    /// `cache_budget` still applies.
+    pub keep_downloads: bool,
+    /// Connections a download may use at once, 1 to 16; see `connections`.
+    pub connections: u64,
+    /// Download speed cap in bytes per second, e.g. for unattended updates
+    /// on a shared link; `None` downloads as fast as possible.
+    pub limit_rate: Option<u64>,
+    /// Permissions for installed executables, e.g. 0o750 on shared
+    /// machines; `None` follows the umask.
+    pub file_mode: Option<u32>,
+    /// How to run AppImages: directly via FUSE or extracted first.
+    pub execution_mode: ExecutionMode,
+    /// Start Cursor with `--no-sandbox`, for systems where Chromium's
+    /// sandbox cannot work.
+    pub no_sandbox: bool,
+    /// Run the new AppImage with `--version` after each install, rolling
+    /// back to the previous version if that fails.
+    pub smoke_test: bool,
+    /// What to do with a Cursor that is running during an update.
+    pub restart_strategy: RestartStrategy,
+    /// Extra arguments for the Cursor a graceful restart starts, e.g. a
+    /// session-restore flag or a folder to reopen.
+    pub restart_args: Vec<String>,
+    pub ip_version: IpVersion,
+    /// Minisign public key that self-updates must be signed with; empty
+    /// means [`crate::selfupdate::RELEASE_PUBKEY`].
+    pub self_update_pubkey: String,
+    /// Hint at `cursorup self update` when `check` or `update` finds a
+    /// newer cursorup release.
+    pub self_update_notice: bool,
+    /// A `sha256sum`-style list of checksums, e.g. an internal mirror's
+    /// `SHA256SUMS`, that downloads are checked against by file name.
+    pub checksums_url: Option<String>,
+    /// Where to send the anonymous usage ping after each install; `None`,
+    /// the default, sends nothing.
+    pub telemetry_url: Option<String>,
+    /// curl-style `host:port:addr` DNS overrides.
+    pub resolve: Vec<Resolve>,
+    /// HTTP(S) or SOCKS5 proxy for all requests, e.g. `http://proxy:3128`;
+    /// `None` follows `HTTPS_PROXY`/`NO_PROXY` and friends.
+    pub proxy: Option<String>,
+    /// Release API to ask for metadata, e.g. an internal mirror's;
+    /// `CURSORUP_API_URL` and `--api-url` override it.
+    pub api_url: String,
+    /// Base URL downloads are fetched from instead of Cursor's CDN, keeping
+    /// the CDN's paths; `None` downloads from the URLs the API names.
+    pub download_base: Option<String>,
+    /// PEM file of extra root certificates to trust, e.g. the CA of a
+    /// TLS-intercepting proxy.
+    pub ca_cert: Option<PathBuf>,
+    /// Accept any TLS certificate. Only for diagnosing, never by default.
+    pub insecure: bool,
+    /// Named bundles of settings from `[profile.<name>]` sections, kept as
+    /// `(key, literal)` pairs and applied on top of the top-level settings.
+    pub profiles: BTreeMap<String, Vec<(String, String)>>,
+    /// The profile selected with `--profile`, if any.
+    pub active_profile: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let install_dir = crate::home_dir()
+            .map(|home| home.join("Applications").join("cursor"))
+            .unwrap_or_else(|_| PathBuf::from("Applications/cursor"));
+        Self {
+            install_dir,
+            system_install: false,
+            elevate: Elevation::Pkexec,
+            platform: Platform::host().unwrap_or_default(),
+            release_track: ReleaseTrack::default(),
+            auto_update: false,
+            metered_updates: MeteredPolicy::Notify,
+            check_interval: 6 * 60 * 60,
+            metrics_listen: None,
+            desktop_entry: true,
+            desktop_name: "Cursor".to_string(),
+            icon: None,
+            cli_wrapper: true,
+            compress_backups: false,
+            hardlinks: true,
+            cache_budget: None,
+            keep_downloads: false,
+            connections: 1,
+            limit_rate: None,
+            transform: None,
+            file_mode: None,
+            execution_mode: ExecutionMode::Auto,
+            no_sandbox: false,
+            smoke_test: true,
+            restart_strategy: RestartStrategy::Never,
+            restart_args: Vec::new(),
+            ip_version: IpVersion::Any,
+            resolve: Vec::new(),
+            proxy: None,
+            api_url: crate::API_URL.to_string(),
+            download_base: None,
+            ca_cert: None,
+            insecure: false,
+            self_update_pubkey: crate::selfupdate::RELEASE_PUBKEY.to_string(),
+            self_update_notice: true,
+            checksums_url: None,
+            telemetry_url: None,
+            profiles: BTreeMap::new(),
+            active_profile: None,
+        }
+    }
+}
+
+impl Config {
+    /// Location of the config file, honouring `XDG_CONFIG_HOME`.
+    pub fn path() -> Result<PathBuf> {
+        let base = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => crate::home_dir()?.join(".config"),
+        };
+        Ok(base.join("cursorup").join("config.toml"))
+    }
+
+    /// Loads the config file, returning `None` when it does not exist yet.
+    pub async fn load() -> Result<Option<Config>> {
+        Self::load_from(&Self::path()?).await
+    }
+
+    pub async fn load_from(path: &Path) -> Result<Option<Config>> {
+        let contents = match fs::read_to_string(path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let config = Self::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Some(config))
+    }
+
+    pub async fn save(&self) -> Result<PathBuf> {
+        let path = Self::path()?;
+        crate::fsutil::write_atomic(&path, self.to_toml()).await?;
+        Ok(path)
+    }
+
+    /// Writes `release_track` to the config file so later runs follow that
+    /// channel, leaving the other saved settings alone. Returns whether the
+    /// file changed.
+    pub async fn save_release_track(release_track: ReleaseTrack) -> Result<bool> {
+        Self::save_setting("release_track", &release_track.to_string()).await
+    }
+
+    /// Writes one setting, given as for `--set`, to the config file, leaving
+    /// the others as saved. Only that key's line is rewritten, so comments
+    /// and unexpanded `$VAR`s in the other lines survive. Returns whether the
+    /// file changed.
+    pub async fn save_setting(key: &str, value: &str) -> Result<bool> {
+        let path = Self::path()?;
+        let contents = match fs::read_to_string(&path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let mut config = Config::default();
+                config.set(key, value)?;
+                config.save().await?;
+                return Ok(true);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let mut saved = Self::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let before = saved.to_toml();
+        saved.set(key, value)?;
+        let after = saved.to_toml();
+        if after == before {
+            return Ok(false);
+        }
+        let prefix = format!("{} = ", key);
+        let line = after
+            .lines()
+            .find(|line| line.starts_with(&prefix))
+            .ok_or_else(|| format!("cannot save `{}`", key))?;
+
+        let mut lines: Vec<&str> = contents.lines().collect();
+        let top_level = lines
+            .iter()
+            .position(|line| line.trim_start().starts_with('['))
+            .unwrap_or(lines.len());
+        let existing = lines[..top_level].iter().rposition(|l| {
+            l.split_once('=')
+                .is_some_and(|(k, _)| k.trim() == key && !l.trim_start().starts_with('#'))
+        });
+        match existing {
+            Some(index) => lines[index] = line,
+            None => {
+                // After the last setting, not after the blank lines that
+                // separate it from the first profile.
+                let mut at = top_level;
+                while at > 0 && lines[at - 1].trim().is_empty() {
+                    at -= 1;
+                }
+                lines.insert(at, line);
+            }
+        }
+        let mut updated = lines.join("\n");
+        updated.push('\n');
+        crate::fsutil::write_atomic(&path, updated).await?;
+        Ok(true)
+    }
+
+    /// Arguments launchers put before the user's when starting Cursor.
+    pub fn launch_args(&self) -> Vec<&'static str> {
+        let mut args = self.execution_mode.launch_args().to_vec();
+        if self.no_sandbox {
+            args.push(crate::smoke::NO_SANDBOX_FLAG);
+        }
+        args
+    }
+
+    fn parse(contents: &str) -> Result<Config> {
+        let mut config = Config::default();
+        let mut profile: Option<String> = None;
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let name = section
+                    .trim()
+                    .strip_prefix("profile.")
+                    .filter(|name| !name.is_empty())
+                    .ok_or_else(|| {
+                        format!(
+                            "line {}: unknown section `[{}]` (expected [profile.<name>])",
+                            index + 1,
+                            section
+                        )
+                    })?;
+                config.profiles.entry(name.to_string()).or_default();
+                profile = Some(name.to_string());
+                continue;
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected `key = value`", index + 1))?;
+            let (key, value) = (key.trim(), value.trim());
+            match &profile {
+                // Validated now so a typo surfaces before the profile is used.
+                Some(name) => {
+                    Config::default()
+                        .apply(key, value)
+                        .map_err(|e| format!("line {}: {}", index + 1, e))?;
+                    if let Some(settings) = config.profiles.get_mut(name) {
+                        settings.push((key.to_string(), value.to_string()));
+                    }
+                }
+                None => config
+                    .apply(key, value)
+                    .map_err(|e| format!("line {}: {}", index + 1, e))?,
+            }
+        }
+        Ok(config)
+    }
+
+    /// Applies the settings of `[profile.<name>]` on top of the current ones.
+    pub fn apply_profile(&mut self, name: &str) -> std::result::Result<(), String> {
+        let settings = self.profiles.get(name).cloned().ok_or_else(|| {
+            let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            match known.as_slice() {
+                [] => format!("unknown profile `{}` (the config defines none)", name),
+                known => format!(
+                    "unknown profile `{}` (expected one of {})",
+                    name,
+                    known.join(", ")
+                ),
+            }
+        })?;
+        for (key, literal) in &settings {
+            self.apply(key, literal)
+                .map_err(|e| format!("profile {}: {}", name, e))?;
+        }
+        self.active_profile = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Overrides a single key for this run, as given to `--set key=value`.
+    /// Values are taken literally; booleans may be written bare.
+    pub fn set(&mut self, key: &str, value: &str) -> std::result::Result<(), String> {
+        let literal = match value {
+            "true" | "false" => value.to_string(),
+            _ if !value.is_empty() && value.chars().all(|c| c.is_ascii_digit()) => {
+                value.to_string()
+            }
+            _ if value.starts_with('"') => value.to_string(),
+            _ => quote(value),
+        };
+        self.apply(key, &literal)
+            .map_err(|e| format!("--set {}: {}", key, e))
+    }
+
+    /// Overrides keys from `CURSORUP_<KEY>` environment variables, e.g.
+    /// `CURSORUP_INSTALL_DIR` or `CURSORUP_PROXY`, with values written as for
+    /// `--set`. Empty variables are ignored.
+    pub fn apply_env(&mut self) -> std::result::Result<(), String> {
+        let toml = self.to_toml();
+        let keys = toml
+            .lines()
+            .take_while(|line| !line.starts_with('['))
+            .filter_map(|line| line.split_once(" = ").map(|(key, _)| key));
+        for key in keys {
+            let name = format!("CURSORUP_{}", key.to_uppercase());
+            if let Ok(value) = std::env::var(&name)
+                && !value.is_empty()
+            {
+                self.set(key, &value)
+                    .map_err(|e| e.replace(&format!("--set {}", key), &name))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Assigns a key from its TOML literal.
+    fn apply(&mut self, key: &str, literal: &str) -> std::result::Result<(), String> {
+        match key {
+            "install_dir" => parse_path(literal).and_then(|v| {
+                self.install_dir = v.ok_or("must not be empty")?;
+                Ok(())
+            }),
+            "system_install" => parse_bool(literal).map(|v| self.system_install = v),
+            "elevate" => parse_string(literal).and_then(|v| v.parse().map(|v| self.elevate = v)),
+            "platform" => parse_string(literal).and_then(|v| v.parse().map(|v| self.platform = v)),
+            "release_track" => {
+                parse_string(literal).and_then(|v| v.parse().map(|v| self.release_track = v))
+            }
+            "auto_update" => parse_bool(literal).map(|v| self.auto_update = v),
+            "metered_updates" => {
+                parse_string(literal).and_then(|v| v.parse().map(|v| self.metered_updates = v))
+            }
+            "check_interval" => parse_integer(literal).map(|v| self.check_interval = v),
+            "metrics_listen" => parse_string(literal)
+                .map(|v| self.metrics_listen = Some(v).filter(|v| !v.is_empty())),
+            "desktop_entry" => parse_bool(literal).map(|v| self.desktop_entry = v),
+            "desktop_name" => parse_string(literal).map(|v| self.desktop_name = v),
+            "icon" => parse_path(literal).map(|v| self.icon = v),
+            "cli_wrapper" => parse_bool(literal).map(|v| self.cli_wrapper = v),
+            "compress_backups" => parse_bool(literal).map(|v| self.compress_backups = v),
+            "hardlinks" => parse_bool(literal).map(|v| self.hardlinks = v),
+            "no_sandbox" => parse_bool(literal).map(|v| self.no_sandbox = v),
+            "smoke_test" => parse_bool(literal).map(|v| self.smoke_test = v),
+            "restart_strategy" => {
+                parse_string(literal).and_then(|v| v.parse().map(|v| self.restart_strategy = v))
+            }
+            "restart_args" => parse_string(literal)
+                .map(|v| self.restart_args = v.split_whitespace().map(String::from).collect()),
+            "keep_downloads" => parse_bool(literal).map(|v| self.keep_downloads = v),
+            "connections" => parse_integer(literal).and_then(|v| {
+                if !(1..=crate::segmented::MAX_CONNECTIONS).contains(&v) {
+                    return Err(format!(
+                        "must be between 1 and {}",
+                        crate::segmented::MAX_CONNECTIONS
+                    ));
+                }
+                self.connections = v;
+                Ok(())
+            }),
+            "limit_rate" => parse_string(literal).and_then(|v| {
+                self.limit_rate = match v.as_str() {
+                    "" => None,
+                    rate => Some(parse_size(rate.trim_end_matches("/s"))?).filter(|v| *v > 0),
+                };
+                Ok(())
+            }),
+            "transform" => {
+                parse_string(literal).map(|v| self.transform = Some(v).filter(|v| !v.is_empty()))
+            }
+            "cache_budget" => parse_string(literal).and_then(|v| {
+                self.cache_budget = match v.as_str() {
+                    "" => None,
+                    size => Some(parse_size(size)?),
+                };
+                Ok(())
+            }),
+            // Also accepted bare, as `--set file_mode=0750` passes it.
+            "file_mode" => parse_string(literal)
+                .or_else(|e| {
+                    literal
+                        .chars()
+                        .all(|c| c.is_ascii_digit())
+                        .then(|| literal.to_string())
+                        .ok_or(e)
+                })
+                .and_then(|v| {
+                    self.file_mode = match v.as_str() {
+                        "" => None,
+                        mode => Some(parse_file_mode(mode)?),
+                    };
+                    Ok(())
+                }),
+            "execution_mode" => {
+                parse_string(literal).and_then(|v| v.parse().map(|v| self.execution_mode = v))
+            }
+            "ip_version" => {
+                parse_string(literal).and_then(|v| v.parse().map(|v| self.ip_version = v))
+            }
+            "self_update_pubkey" => parse_string(literal).map(|v| {
+                self.self_update_pubkey = if v.is_empty() {
+                    crate::selfupdate::RELEASE_PUBKEY.to_string()
+                } else {
+                    v
+                }
+            }),
+            "self_update_notice" => parse_bool(literal).map(|v| self.self_update_notice = v),
+            "checksums_url" => parse_string(literal)
+                .map(|v| self.checksums_url = Some(v).filter(|v| !v.is_empty())),
+            "telemetry_url" => parse_string(literal)
+                .map(|v| self.telemetry_url = Some(v).filter(|v| !v.is_empty())),
+            "resolve" => parse_string(literal).and_then(|v| {
+                self.resolve = v
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|entry| !entry.is_empty())
+                    .map(str::parse)
+                    .collect::<std::result::Result<_, _>>()?;
+                Ok(())
+            }),
+            "proxy" => {
+                parse_string(literal).map(|v| self.proxy = Some(v).filter(|v| !v.is_empty()))
+            }
+            "api_url" => parse_string(literal).map(|v| {
+                self.api_url = if v.is_empty() {
+                    crate::API_URL.to_string()
+                } else {
+                    v
+                }
+            }),
+            "download_base" => parse_string(literal)
+                .map(|v| self.download_base = Some(v).filter(|v| !v.is_empty())),
+            "ca_cert" => parse_path(literal).map(|v| self.ca_cert = v),
+            "insecure" => parse_bool(literal).map(|v| self.insecure = v),
+            _ => Err(format!("unknown key `{}`", key)),
+        }
+    }
+
+    fn to_toml(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "install_dir = {}", quote_path(&self.install_dir));
+        let _ = writeln!(out, "system_install = {}", self.system_install);
+        let _ = writeln!(out, "elevate = {}", quote(&self.elevate.to_string()));
+        let _ = writeln!(out, "platform = {}", quote(&self.platform.to_string()));
+        let _ = writeln!(
+            out,
+            "release_track = {}",
+            quote(&self.release_track.to_string())
+        );
+        let _ = writeln!(out, "auto_update = {}", self.auto_update);
+        let _ = writeln!(
+            out,
+            "metered_updates = {}",
+            quote(&self.metered_updates.to_string())
+        );
+        let _ = writeln!(out, "check_interval = {}", self.check_interval);
+        let _ = writeln!(
+            out,
+            "metrics_listen = {}",
+            quote(self.metrics_listen.as_deref().unwrap_or_default())
+        );
+        let _ = writeln!(out, "desktop_entry = {}", self.desktop_entry);
+        let _ = writeln!(out, "desktop_name = {}", quote(&self.desktop_name));
+        let _ = writeln!(
+            out,
+            "icon = {}",
+            self.icon.as_deref().map(quote_path).unwrap_or(quote(""))
+        );
+        let _ = writeln!(out, "cli_wrapper = {}", self.cli_wrapper);
+        let _ = writeln!(out, "compress_backups = {}", self.compress_backups);
+        let _ = writeln!(out, "hardlinks = {}", self.hardlinks);
+        let _ = writeln!(
+            out,
+            "cache_budget = {}",
+            quote(&self.cache_budget.map(format_size).unwrap_or_default())
+        );
+        let _ = writeln!(out, "keep_downloads = {}", self.keep_downloads);
+        let _ = writeln!(out, "connections = {}", self.connections);
+        let _ = writeln!(
+            out,
+            "limit_rate = {}",
+            quote(&self.limit_rate.map(format_size).unwrap_or_default())
+        );
+        let _ = writeln!(
+            out,
+            "transform = {}",
+            quote(self.transform.as_deref().unwrap_or_default())
+        );
+        let _ = writeln!(
+            out,
+            "file_mode = {}",
+            quote(
+                &self
+                    .file_mode
+                    .map(|mode| format!("{:04o}", mode))
+                    .unwrap_or_default()
+            )
+        );
+        let _ = writeln!(
+            out,
+            "execution_mode = {}",
+            quote(&self.execution_mode.to_string())
+        );
+        let _ = writeln!(out, "no_sandbox = {}", self.no_sandbox);
+        let _ = writeln!(out, "smoke_test = {}", self.smoke_test);
+        let _ = writeln!(
+            out,
+            "restart_strategy = {}",
+            quote(&self.restart_strategy.to_string())
+        );
+        let _ = writeln!(
+            out,
+            "restart_args = {}",
+            quote(&self.restart_args.join(" "))
+        );
+        let _ = writeln!(out, "ip_version = {}", quote(&self.ip_version.to_string()));
+        let resolve: Vec<String> = self.resolve.iter().map(Resolve::to_string).collect();
+        let _ = writeln!(out, "resolve = {}", quote(&resolve.join(", ")));
+        let _ = writeln!(
+            out,
+            "proxy = {}",
+            quote(self.proxy.as_deref().unwrap_or_default())
+        );
+        let _ = writeln!(out, "api_url = {}", quote(&self.api_url));
+        let _ = writeln!(
+            out,
+            "download_base = {}",
+            quote(self.download_base.as_deref().unwrap_or_default())
+        );
+        let _ = writeln!(
+            out,
+            "ca_cert = {}",
+            self.ca_cert.as_deref().map(quote_path).unwrap_or(quote(""))
+        );
+        let _ = writeln!(out, "insecure = {}", self.insecure);
+        let _ = writeln!(
+            out,
+            "self_update_pubkey = {}",
+            quote(&self.self_update_pubkey)
+        );
+        let _ = writeln!(out, "self_update_notice = {}", self.self_update_notice);
+        let _ = writeln!(
+            out,
+            "checksums_url = {}",
+            quote(self.checksums_url.as_deref().unwrap_or_default())
+        );
+        let _ = writeln!(
+            out,
+            "telemetry_url = {}",
+            quote(self.telemetry_url.as_deref().unwrap_or_default())
+        );
+        for (name, settings) in &self.profiles {
+            let _ = writeln!(out, "\n[profile.{}]", name);
+            for (key, literal) in settings {
+                let _ = writeln!(out, "{} = {}", key, literal);
+            }
+        }
+        out
+    }
+}
+
+fn parse_string(value: &str) -> std::result::Result<String, String> {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .map(|v| v.replace("\\\"", "\"").replace("\\\\", "\\"))
+        .ok_or_else(|| format!("expected a quoted string, found `{}`", value))
+}
+
+/// A path setting: a quoted string in which `~/`, `$VAR`, `${VAR}` and
+/// `${VAR:-default}` are expanded, so one config works on machines with
+/// different home and XDG dirs. `$$` is a literal `$`. After expansion the
+/// path must be absolute; an empty string is `None`.
+fn parse_path(value: &str) -> std::result::Result<Option<PathBuf>, String> {
+    let raw = parse_string(value)?;
+    if raw.is_empty() {
+        return Ok(None);
+    }
+    expand_path(&raw).map(Some)
+}
+
+/// Expands `raw` like a path setting's value and checks it is absolute.
+pub(crate) fn expand_path(raw: &str) -> std::result::Result<PathBuf, String> {
+    let expanded = expand(raw)?;
+    let path = PathBuf::from(&expanded);
+    if !path.is_absolute() {
+        return Err(if expanded == raw {
+            format!("expected an absolute path, found `{}`", raw)
+        } else {
+            format!(
+                "expected an absolute path, but `{}` expands to `{}`",
+                raw, expanded
+            )
+        });
+    }
+    Ok(path)
+}
+
+/// Expands environment variables and a leading `~` as described at
+/// `parse_path`. A default may use `~` and `$VAR` itself, but not braces.
+/// Unset and empty variables without a default are errors rather than
+/// silently becoming empty.
+fn expand(value: &str) -> std::result::Result<String, String> {
+    let lookup = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+    let mut out = String::new();
+    let mut rest = value;
+    if rest == "~" || rest.starts_with("~/") {
+        let home = lookup("HOME").ok_or("`~` cannot be expanded: $HOME is not set")?;
+        out.push_str(&home);
+        rest = &rest[1..];
+    }
+    while let Some(index) = rest.find('$') {
+        out.push_str(&rest[..index]);
+        rest = &rest[index + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            out.push('$');
+            rest = after;
+            continue;
+        }
+        let (name, default) = if let Some(braced) = rest.strip_prefix('{') {
+            let end = braced
+                .find('}')
+                .ok_or_else(|| format!("unterminated `${{` in `{}`", value))?;
+            rest = &braced[end + 1..];
+            match braced[..end].split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (&braced[..end], None),
+            }
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let name = &rest[..end];
+            rest = &rest[end..];
+            (name, None)
+        };
+        let valid = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(format!(
+                "invalid variable name `{}` in `{}` (write `$$` for a literal `$`)",
+                name, value
+            ));
+        }
+        match (lookup(name), default) {
+            (Some(v), _) => out.push_str(&v),
+            (None, Some(default)) => out.push_str(&expand(default)?),
+            (None, None) => {
+                return Err(format!(
+                    "${} in `{}` is not set; set it or write ${{{}:-<default>}}",
+                    name, value, name
+                ));
+            }
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn parse_bool(value: &str) -> std::result::Result<bool, String> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format!("expected `true` or `false`, found `{}`", value)),
+    }
+}
+
+fn parse_integer(value: &str) -> std::result::Result<u64, String> {
+    value
+        .replace('_', "")
+        .parse()
+        .map_err(|_| format!("expected a non-negative integer, found `{}`", value))
+}
+
+/// Octal permissions such as `0750`. The owner must keep read and execute
+/// access, or the installed AppImage could not be started.
+fn parse_file_mode(value: &str) -> std::result::Result<u32, String> {
+    let mode = u32::from_str_radix(value.trim_start_matches("0o"), 8)
+        .ok()
+        .filter(|mode| *mode <= 0o777)
+        .ok_or_else(|| format!("expected an octal mode such as \"0755\", found `{}`", value))?;
+    if mode & 0o500 != 0o500 {
+        return Err(format!(
+            "file mode {:04o} would not let the owner run the AppImage",
+            mode
+        ));
+    }
+    Ok(mode)
+}
+
+const SIZE_UNITS: [(&str, u64); 4] = [
+    ("TB", 1 << 40),
+    ("GB", 1 << 30),
+    ("MB", 1 << 20),
+    ("KB", 1 << 10),
+];
+
+/// Sizes such as `2GB`, `1.5 GB`, `500M` or a plain number of bytes. Units
+/// are binary, as everywhere else in cursorup: 1 GB is 1024 MB.
+fn parse_size(value: &str) -> std::result::Result<u64, String> {
+    let invalid = || {
+        format!(
+            "expected a size such as \"2GB\" or \"500MB\", found `{}`",
+            value
+        )
+    };
+    let upper = value.trim().to_ascii_uppercase();
+    let split = upper
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(upper.len());
+    let (number, unit) = upper.split_at(split);
+    let number: f64 = number.parse().map_err(|_| invalid())?;
+    let unit = unit.trim().trim_end_matches('B').trim_end_matches('I');
+    let multiplier = match unit {
+        "" => 1,
+        unit => SIZE_UNITS
+            .iter()
+            .find(|(name, _)| name.starts_with(unit))
+            .map(|(_, multiplier)| *multiplier)
+            .ok_or_else(invalid)?,
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// The inverse of `parse_size`, in the largest unit that is exact.
+fn format_size(bytes: u64) -> String {
+    SIZE_UNITS
+        .iter()
+        .find(|(_, multiplier)| bytes >= *multiplier && bytes.is_multiple_of(*multiplier))
+        .map(|(name, multiplier)| format!("{}{}", bytes / multiplier, name))
+        .unwrap_or_else(|| bytes.to_string())
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Quotes a path setting, escaping `$` so it is read back unexpanded.
+fn quote_path(path: &Path) -> String {
+    quote(&path.to_string_lossy().replace('$', "$$"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn home() -> String {
+        std::env::var("HOME").unwrap()
+    }
+
+    #[test]
+    fn expand_home_and_variables() {
+        assert_eq!(expand("~").unwrap(), home());
+        assert_eq!(expand("~/Apps").unwrap(), format!("{}/Apps", home()));
+        assert_eq!(expand("$HOME/a").unwrap(), format!("{}/a", home()));
+        assert_eq!(expand("${HOME}a").unwrap(), format!("{}a", home()));
+        // Only a leading `~` is expanded.
+        assert_eq!(expand("/a/~/b").unwrap(), "/a/~/b");
+    }
+
+    #[test]
+    fn expand_defaults_and_escapes() {
+        assert_eq!(
+            expand("${CURSORUP_TEST_UNSET:-/opt/cursor}").unwrap(),
+            "/opt/cursor"
+        );
+        assert_eq!(
+            expand("${CURSORUP_TEST_UNSET:-~/x}").unwrap(),
+            format!("{}/x", home())
+        );
+        assert_eq!(expand("/a/$$b").unwrap(), "/a/$b");
+        assert_eq!(expand("/a/$$$$").unwrap(), "/a/$$");
+    }
+
+    #[test]
+    fn expand_rejects_unset_and_invalid_variables() {
+        assert!(expand("$CURSORUP_TEST_UNSET/a").is_err());
+        assert!(expand("${CURSORUP_TEST_UNSET").is_err());
+        assert!(expand("/a/$").is_err());
+        assert!(expand("/a/$1").is_err());
+    }
+
+    #[test]
+    fn paths_must_be_absolute() {
+        assert_eq!(
+            parse_path("\"~/Apps\"").unwrap(),
+            Some(PathBuf::from(home()).join("Apps"))
+        );
+        assert_eq!(parse_path("\"\"").unwrap(), None);
+        assert!(parse_path("\"Apps\"").is_err());
+        assert!(parse_path("/no/quotes").is_err());
+        assert!(expand_path("rel/dir").is_err());
+    }
+
+    #[test]
+    fn saved_paths_read_back_unchanged() {
+        let path = Path::new("/opt/cu$r/$HOME");
+        assert_eq!(
+            parse_path(&quote_path(path)).unwrap().as_deref(),
+            Some(path)
+        );
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert_eq!(parse_size("500M").unwrap(), 500 << 20);
+        assert_eq!(parse_size("500MB").unwrap(), 500 << 20);
+        assert_eq!(parse_size("2gb").unwrap(), 2 << 30);
+        assert_eq!(parse_size("2GiB").unwrap(), 2 << 30);
+        assert_eq!(parse_size("1.5 GB").unwrap(), 3 << 29);
+        assert_eq!(parse_size("1TB").unwrap(), 1 << 40);
+        assert!(parse_size("").is_err());
+        assert!(parse_size("GB").is_err());
+        assert!(parse_size("2XB").is_err());
+        assert!(parse_size("-1GB").is_err());
+    }
+
+    #[test]
+    fn format_size_round_trips() {
+        assert_eq!(format_size(2 << 30), "2GB");
+        assert_eq!(format_size(1536 << 20), "1536MB");
+        assert_eq!(format_size(1000), "1000");
+        for bytes in [0, 1000, 3 << 29, 5 << 40] {
+            assert_eq!(parse_size(&format_size(bytes)).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn empty_pubkey_means_the_release_key() {
+        let mut config = Config {
+            self_update_pubkey: "RWQother".to_string(),
+            ..Config::default()
+        };
+        config.apply("self_update_pubkey", "\"\"").unwrap();
+        assert_eq!(config.self_update_pubkey, crate::selfupdate::RELEASE_PUBKEY);
+    }
+}
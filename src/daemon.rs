@@ -0,0 +1,213 @@
+use crate::config::Config;
+use crate::events;
+use crate::metered::MeteredPolicy;
+use crate::metrics::{Metrics, SharedMetrics};
+use crate::state::State;
+use crate::{InstallOptions, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::fs;
+
+/// First retry delay after a failed scheduled check or update; it doubles
+/// with every further failure.
+const RETRY_BASE: u64 = 60;
+
+/// Longest retry delay, unless `check_interval` is shorter still.
+const RETRY_MAX: u64 = 60 * 60;
+
+/// A failed scheduled check or update waiting to be retried, persisted at
+/// `~/.local/share/cursorup/retry.json` so a restarted daemon keeps backing
+/// off and `status` can show it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PendingRetry {
+    /// Consecutive failures so far.
+    pub attempts: u32,
+    /// When the next attempt is due, in seconds since the Unix epoch.
+    pub next_at: u64,
+    pub error: String,
+}
+
+impl PendingRetry {
+    pub(crate) fn path() -> Result<PathBuf> {
+        Ok(State::dir()?.join("retry.json"))
+    }
+
+    pub async fn load() -> Result<Option<PendingRetry>> {
+        match fs::read_to_string(Self::path()?).await {
+            Ok(contents) => Ok(serde_json::from_str(&contents).ok()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn save(&self) -> Result<()> {
+        crate::fsutil::write_atomic(&Self::path()?, serde_json::to_string_pretty(self)?).await
+    }
+
+    async fn clear() -> Result<()> {
+        match fs::remove_file(Self::path()?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Delay before retry number `attempts` (1-based): `RETRY_BASE` doubled per
+/// earlier failure, capped at `RETRY_MAX` and the regular interval.
+fn retry_delay(attempts: u32, check_interval: u64) -> u64 {
+    let cap = RETRY_MAX.min(check_interval).max(1);
+    RETRY_BASE
+        .saturating_mul(1 << attempts.saturating_sub(1).min(20))
+        .min(cap)
+}
+
+/// Checks for updates every `check_interval` seconds, installing them when
+/// `auto_update` is enabled, and optionally serves Prometheus metrics.
+pub async fn run(config: &Config) -> Result<()> {
+    let metrics: SharedMetrics = Arc::new(Mutex::new(Metrics::default()));
+    if let Some(addr) = config.metrics_listen.clone() {
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            if let Err(e) = crate::metrics::serve(&addr, metrics).await {
+                tracing::warn!("metrics endpoint on {} failed: {}", addr, e);
+            }
+        });
+    }
+
+    events::log(format!(
+        "cursorup daemon started, checking every {} seconds",
+        config.check_interval
+    ));
+    let mut pending = PendingRetry::load().await?;
+    if let Some(retry) = &pending {
+        let wait = retry.next_at.saturating_sub(crate::state::now());
+        events::log(format!(
+            "Resuming retry {} of a failed update in {} seconds",
+            retry.attempts + 1,
+            wait
+        ));
+        tokio::time::sleep(Duration::from_secs(wait)).await;
+    }
+    loop {
+        let delay = match tick(config, &metrics).await {
+            Ok(()) => {
+                if pending.take().is_some() {
+                    PendingRetry::clear().await?;
+                }
+                config.check_interval
+            }
+            Err(error) => {
+                let attempts = pending.as_ref().map_or(1, |retry| retry.attempts + 1);
+                let delay = retry_delay(attempts, config.check_interval);
+                events::log(format!(
+                    "Retrying in {} seconds (attempt {})",
+                    delay,
+                    attempts + 1
+                ));
+                let retry = PendingRetry {
+                    attempts,
+                    next_at: crate::state::now() + delay,
+                    error,
+                };
+                retry.save().await?;
+                pending = Some(retry);
+                delay
+            }
+        };
+        tokio::time::sleep(Duration::from_secs(delay)).await;
+    }
+}
+
+/// One scheduled check, and update if enabled. Failures are reported and
+/// counted here; the message is returned so the caller can retry.
+async fn tick(config: &Config, metrics: &SharedMetrics) -> std::result::Result<(), String> {
+    let update = |f: &dyn Fn(&mut Metrics)| {
+        if let Ok(mut metrics) = metrics.lock() {
+            f(&mut metrics);
+        }
+    };
+
+    let check = async {
+        let client = crate::http::client(config)?;
+        let metadata = crate::fetch_metadata(
+            &client,
+            &config.api_url,
+            config.platform,
+            config.release_track,
+        )
+        .await?;
+        crate::check::check(config, &metadata).await
+    };
+    let check = match check.await {
+        Ok(check) => check,
+        Err(e) => {
+            tracing::warn!("update check failed: {}", e);
+            update(&|m| m.check_failures += 1);
+            return Err(format!("update check failed: {}", e));
+        }
+    };
+    update(&|m| {
+        m.last_check = Some(crate::state::now());
+        m.latest_version = Some(check.latest.clone());
+        m.installed_version = check.current.clone();
+        m.update_available = check.update_available;
+    });
+    record_installed(config, metrics).await;
+
+    if !(check.update_available && config.auto_update) {
+        return Ok(());
+    }
+    let mut options = InstallOptions::default();
+    if config.metered_updates != MeteredPolicy::Install && crate::metered::is_metered().await {
+        if config.metered_updates == MeteredPolicy::Notify {
+            events::log(format!(
+                "Cursor {} is available; not downloading it over a metered connection (metered_updates = \"notify\")",
+                check.latest
+            ));
+            return Ok(());
+        }
+        let staged = crate::stage::load(config).await.ok().flatten();
+        if staged.is_some_and(|staged| staged.metadata.version == check.latest) {
+            return Ok(());
+        }
+        events::log(format!(
+            "Staging Cursor {} on a metered connection; run `cursorup commit` to install it",
+            check.latest
+        ));
+        options.stage = true;
+    }
+    let _lock = match crate::instance::acquire() {
+        Ok(lock) => lock,
+        Err(e) => {
+            tracing::warn!("scheduled update postponed: {}", e);
+            return Err(format!("scheduled update postponed: {}", e));
+        }
+    };
+    match crate::install(config, &options).await {
+        Ok(()) if options.stage => Ok(()),
+        Ok(()) => {
+            update(&|m| {
+                m.last_update = Some(crate::state::now());
+                m.update_available = false;
+            });
+            record_installed(config, metrics).await;
+            Ok(())
+        }
+        Err(e) => {
+            tracing::warn!("scheduled update failed: {}", e);
+            update(&|m| m.update_failures += 1);
+            Err(format!("scheduled update failed: {}", e))
+        }
+    }
+}
+
+async fn record_installed(config: &Config, metrics: &SharedMetrics) {
+    if let Ok(Some(state)) = crate::state::State::load_or_recover(config).await
+        && let Ok(mut metrics) = metrics.lock()
+    {
+        metrics.installed_version = Some(state.version);
+        metrics.installed_commit = Some(state.commit_sha).filter(|sha| !sha.is_empty());
+    }
+}
@@ -0,0 +1,185 @@
+//! Line diffs for showing what cursorup is about to change in files users
+//! may have edited, such as the desktop entry.
+
+/// Lines of context around each change in `unified`, as in `diff -u`.
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+impl Line<'_> {
+    pub fn is_change(&self) -> bool {
+        !matches!(self, Line::Same(_))
+    }
+}
+
+/// Diffs `old` against `new` line by line via their longest common
+/// subsequence. Meant for small files; the table is quadratic.
+pub fn lines<'a>(old: &'a str, new: &'a str) -> Vec<Line<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(Line::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(Line::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(Line::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+/// Renders a diff in `diff -u` format, or `None` if nothing changed.
+pub fn unified(lines: &[Line], old_label: &str, new_label: &str) -> Option<String> {
+    let changes: Vec<usize> = (0..lines.len())
+        .filter(|&index| lines[index].is_change())
+        .collect();
+    let first = *changes.first()?;
+
+    // Group changes whose context would touch into hunks of line indices.
+    let mut hunks = vec![(first.saturating_sub(CONTEXT), first)];
+    for &index in &changes[1..] {
+        let last = hunks.last_mut().expect("hunks start non-empty");
+        if index <= last.1 + 2 * CONTEXT + 1 {
+            last.1 = index;
+        } else {
+            hunks.push((index.saturating_sub(CONTEXT), index));
+        }
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_label, new_label);
+    for (start, last_change) in hunks {
+        let end = (last_change + CONTEXT + 1).min(lines.len());
+        // 1-based line numbers of the hunk start in the old and new file.
+        let before = &lines[..start];
+        let old_start = before
+            .iter()
+            .filter(|l| !matches!(l, Line::Added(_)))
+            .count()
+            + 1;
+        let new_start = before
+            .iter()
+            .filter(|l| !matches!(l, Line::Removed(_)))
+            .count()
+            + 1;
+        let hunk = &lines[start..end];
+        let old_len = hunk.iter().filter(|l| !matches!(l, Line::Added(_))).count();
+        let new_len = hunk
+            .iter()
+            .filter(|l| !matches!(l, Line::Removed(_)))
+            .count();
+        // An empty range is numbered by the line before it, like `diff -u`.
+        let old_start = if old_len == 0 {
+            old_start - 1
+        } else {
+            old_start
+        };
+        let new_start = if new_len == 0 {
+            new_start - 1
+        } else {
+            new_start
+        };
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start, old_len, new_start, new_len
+        ));
+        for line in hunk {
+            let (sign, text) = match line {
+                Line::Same(text) => (' ', text),
+                Line::Removed(text) => ('-', text),
+                Line::Added(text) => ('+', text),
+            };
+            out.push(sign);
+            out.push_str(text);
+            out.push('\n');
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `unified` without the `---`/`+++` header lines.
+    fn hunks(old: &str, new: &str) -> Option<String> {
+        let out = unified(&lines(old, new), "old", "new")?;
+        let body = out.strip_prefix("--- old\n+++ new\n").unwrap();
+        Some(body.to_string())
+    }
+
+    fn numbered(replace: &[(usize, &str)], remove: &[usize]) -> String {
+        (1..=20)
+            .filter(|n| !remove.contains(n))
+            .map(|n| match replace.iter().find(|(at, _)| *at == n) {
+                Some((_, text)) => format!("{}\n", text),
+                None => format!("{}\n", n),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pairs_changes_with_common_lines() {
+        assert_eq!(
+            lines("a\nb\nc\n", "a\nB\nc\n"),
+            [
+                Line::Same("a"),
+                Line::Removed("b"),
+                Line::Added("B"),
+                Line::Same("c")
+            ]
+        );
+        assert!(lines("a\nb\n", "a\nb\n").iter().all(|l| !l.is_change()));
+    }
+
+    #[test]
+    fn unchanged_files_have_no_diff() {
+        assert_eq!(hunks("a\nb\n", "a\nb\n"), None);
+        assert_eq!(hunks("", ""), None);
+    }
+
+    #[test]
+    fn matches_diff_u() {
+        assert_eq!(
+            hunks("a\nb\nc\n", "a\nB\nc\n").unwrap(),
+            "@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n"
+        );
+        assert_eq!(hunks("", "x\ny\n").unwrap(), "@@ -0,0 +1,2 @@\n+x\n+y\n");
+        assert_eq!(
+            hunks(&numbered(&[], &[]), &numbered(&[], &[5])).unwrap(),
+            "@@ -2,7 +2,6 @@\n 2\n 3\n 4\n-5\n 6\n 7\n 8\n"
+        );
+    }
+
+    #[test]
+    fn distant_changes_get_separate_hunks() {
+        let new = numbered(&[(2, "two"), (18, "eighteen")], &[]);
+        assert_eq!(
+            hunks(&numbered(&[], &[]), &new).unwrap(),
+            "@@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n 4\n 5\n\
+             @@ -15,6 +15,6 @@\n 15\n 16\n 17\n-18\n+eighteen\n 19\n 20\n"
+        );
+    }
+}
@@ -0,0 +1,122 @@
+//! `cursorup du`: how much space each kind of file cursorup manages takes,
+//! and how much of it the cleanup commands in `cleanup` would free.
+
+use crate::config::Config;
+use crate::state::State;
+use crate::{Result, backup, cache, stage};
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+#[derive(Serialize, Debug)]
+pub struct DiskUsage {
+    pub categories: Vec<Usage>,
+    pub total_bytes: u64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Usage {
+    /// `app`, `staged`, `backups`, `cache` or `state`.
+    pub name: &'static str,
+    pub path: PathBuf,
+    pub bytes: u64,
+    /// The command that frees space here, and how much it would free.
+    pub reclaim_with: &'static str,
+    pub reclaimable_bytes: u64,
+}
+
+impl fmt::Display for DiskUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for usage in &self.categories {
+            writeln!(
+                f,
+                "{:<8} {:>9.2} MB  {}",
+                usage.name,
+                megabytes(usage.bytes),
+                usage.path.display()
+            )?;
+            if usage.reclaimable_bytes > 0 {
+                writeln!(
+                    f,
+                    "{:<8} {:>9.2} MB  freed by `{}`",
+                    "",
+                    megabytes(usage.reclaimable_bytes),
+                    usage.reclaim_with
+                )?;
+            }
+        }
+        write!(f, "{:<8} {:>9.2} MB", "total", megabytes(self.total_bytes))
+    }
+}
+
+fn megabytes(bytes: u64) -> f64 {
+    bytes as f64 / 1_048_576.0
+}
+
+pub async fn disk_usage(config: &Config) -> Result<DiskUsage> {
+    let install_dir = &config.install_dir;
+    let backups_dir = install_dir.join(backup::BACKUP_DIR);
+    let stage_dir = stage::dir(config);
+    let backups = size(&backups_dir).await?;
+    let staged = size(&stage_dir).await?;
+    // The backups and the staged update live inside the install dir.
+    let app = size(install_dir)
+        .await?
+        .saturating_sub(backups)
+        .saturating_sub(staged);
+    let cache_dir = cache::dir()?;
+    let installed = State::load().await?.map(|state| state.version);
+    let state_dir = State::dir()?;
+    let state = size(&state_dir).await?;
+
+    let categories = vec![
+        Usage {
+            name: "app",
+            path: install_dir.clone(),
+            bytes: app,
+            reclaim_with: "cursorup uninstall",
+            reclaimable_bytes: app,
+        },
+        Usage {
+            name: "staged",
+            path: stage_dir,
+            bytes: staged,
+            reclaim_with: "cursorup uninstall",
+            reclaimable_bytes: staged,
+        },
+        Usage {
+            name: "backups",
+            path: backups_dir,
+            bytes: backups,
+            reclaim_with: "cursorup uninstall --purge",
+            reclaimable_bytes: backups,
+        },
+        Usage {
+            name: "cache",
+            path: cache_dir.clone(),
+            bytes: size(&cache_dir).await?,
+            reclaim_with: "cursorup gc",
+            reclaimable_bytes: cache::prunable_bytes(config.platform, installed.as_deref()).await?,
+        },
+        Usage {
+            name: "state",
+            path: state_dir,
+            bytes: state,
+            reclaim_with: "cursorup uninstall --purge",
+            reclaimable_bytes: state,
+        },
+    ];
+    Ok(DiskUsage {
+        total_bytes: categories.iter().map(|usage| usage.bytes).sum(),
+        categories,
+    })
+}
+
+/// Total size of the files under `path`; nothing when it doesn't exist.
+async fn size(path: &Path) -> Result<u64> {
+    if !fs::try_exists(path).await? {
+        return Ok(0);
+    }
+    Ok(cache::dir_usage(path).await?.0)
+}
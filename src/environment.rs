@@ -0,0 +1,146 @@
+use crate::Result;
+use crate::config::Config;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Effective paths and settings after config, environment and flags are merged.
+#[derive(Serialize, Debug)]
+pub struct Environment {
+    pub config_file: PathBuf,
+    pub config_file_exists: bool,
+    pub profile: Option<String>,
+    pub install_dir: PathBuf,
+    pub cache_dir: PathBuf,
+    pub state_dir: PathBuf,
+    pub desktop_entry: Option<PathBuf>,
+    pub cli_wrapper: Option<PathBuf>,
+    pub api_url: String,
+    /// Mirror downloads are fetched from, if not Cursor's CDN.
+    pub download_base: Option<String>,
+    pub release_track: crate::ReleaseTrack,
+    /// Configured mode, with what `auto` resolves to on this machine.
+    pub execution_mode: String,
+    pub ip_version: String,
+    pub resolve: Vec<String>,
+    /// Proxy-related environment variables picked up by the HTTP client, and
+    /// the `proxy` setting, which takes precedence over them.
+    pub proxy: BTreeMap<String, String>,
+    pub ca_cert: Option<PathBuf>,
+    pub insecure: bool,
+}
+
+const PROXY_VARIABLES: [&str; 8] = [
+    "HTTPS_PROXY",
+    "https_proxy",
+    "HTTP_PROXY",
+    "http_proxy",
+    "ALL_PROXY",
+    "all_proxy",
+    "NO_PROXY",
+    "no_proxy",
+];
+
+impl fmt::Display for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let disabled = |path: &Option<PathBuf>| match path {
+            Some(path) => path.display().to_string(),
+            None => "(disabled)".to_string(),
+        };
+        writeln!(
+            f,
+            "Config file:   {}{}",
+            self.config_file.display(),
+            if self.config_file_exists {
+                ""
+            } else {
+                " (not found, using defaults)"
+            }
+        )?;
+        if let Some(profile) = &self.profile {
+            writeln!(f, "Profile:       {}", profile)?;
+        }
+        writeln!(f, "Install dir:   {}", self.install_dir.display())?;
+        writeln!(f, "Cache dir:     {}", self.cache_dir.display())?;
+        writeln!(f, "State dir:     {}", self.state_dir.display())?;
+        writeln!(f, "Desktop entry: {}", disabled(&self.desktop_entry))?;
+        writeln!(f, "CLI wrapper:   {}", disabled(&self.cli_wrapper))?;
+        writeln!(f, "API endpoint:  {}", self.api_url)?;
+        if let Some(download_base) = &self.download_base {
+            writeln!(f, "Downloads:     {}", download_base)?;
+        }
+        writeln!(f, "Release track: {}", self.release_track)?;
+        writeln!(f, "Execution:     {}", self.execution_mode)?;
+        writeln!(f, "IP version:    {}", self.ip_version)?;
+        if !self.resolve.is_empty() {
+            writeln!(f, "Resolve:       {}", self.resolve.join(", "))?;
+        }
+        if self.proxy.is_empty() {
+            writeln!(f, "Proxy:         (none)")?;
+        } else {
+            let proxy: Vec<String> = self
+                .proxy
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect();
+            writeln!(f, "Proxy:         {}", proxy.join(" "))?;
+        }
+        match &self.ca_cert {
+            Some(path) => write!(f, "CA certs:      system + {}", path.display())?,
+            None => write!(f, "CA certs:      system")?,
+        }
+        if self.insecure {
+            write!(f, " (NOT CHECKED: insecure)")?;
+        }
+        Ok(())
+    }
+}
+
+pub async fn environment(config: &Config) -> Result<Environment> {
+    let config_file = Config::path()?;
+    Ok(Environment {
+        config_file_exists: tokio::fs::try_exists(&config_file).await?,
+        config_file,
+        profile: config.active_profile.clone(),
+        install_dir: config.install_dir.clone(),
+        cache_dir: crate::cache::dir()?,
+        state_dir: crate::state::State::dir()?,
+        desktop_entry: config
+            .desktop_entry
+            .then(|| crate::desktop_entry_path(config.system_install))
+            .transpose()?,
+        cli_wrapper: config
+            .cli_wrapper
+            .then(crate::wrapper::wrapper_path)
+            .transpose()?,
+        api_url: config.api_url.clone(),
+        download_base: config.download_base.clone(),
+        release_track: config.release_track,
+        execution_mode: match config.execution_mode {
+            crate::execution::ExecutionMode::Auto => {
+                format!("auto ({})", config.execution_mode.resolve())
+            }
+            mode => mode.to_string(),
+        },
+        ip_version: config.ip_version.to_string(),
+        resolve: config.resolve.iter().map(ToString::to_string).collect(),
+        proxy: PROXY_VARIABLES
+            .iter()
+            .filter_map(|name| {
+                std::env::var(name)
+                    .ok()
+                    .filter(|value| !value.is_empty())
+                    .map(|value| (name.to_string(), value))
+            })
+            .chain(
+                config
+                    .proxy
+                    .clone()
+                    .map(|proxy| ("proxy".to_string(), proxy)),
+            )
+            .collect(),
+        ca_cert: config.ca_cert.clone(),
+        insecure: config.insecure,
+    })
+}
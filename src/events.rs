@@ -0,0 +1,454 @@
+//! Progress events emitted by the install pipeline. Without a registered
+//! handler they are printed to stdout as human-readable lines (unless
+//! `set_printing(false)`); front-ends (and `cursorup rpc`) install their own
+//! handler to receive them instead. Every event is also a `tracing` event
+//! with target `cursorup::events`, inside the `fetch`, `download`, `extract`
+//! and `install` spans, for programs that log through a subscriber.
+//! Either way each event comes with the same timing and byte counters, so
+//! front-ends can compute speed and ETA like the terminal output does.
+//!
+//! Decisions the install needs from the user are typed `Prompt`s. A
+//! registered prompt handler answers them through a `Responder`, so a GUI
+//! can show its own dialog; otherwise they are asked in the terminal, and
+//! without one each caller falls back to its unattended behaviour.
+
+use crate::i18n::tr;
+use crate::progress;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum InstallEvent {
+    MetadataFetched {
+        version: String,
+        commit_sha: String,
+        download_url: String,
+    },
+    DownloadStarted {
+        url: String,
+        total_bytes: u64,
+    },
+    DownloadProgress {
+        downloaded_bytes: u64,
+        total_bytes: u64,
+    },
+    DownloadFinished {
+        path: PathBuf,
+        bytes: u64,
+    },
+    Extracting {
+        path: PathBuf,
+    },
+    /// Copying the AppImage into the install dir.
+    Copying {
+        path: PathBuf,
+    },
+    BackedUp {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    Installed {
+        version: String,
+        path: PathBuf,
+    },
+    /// Informational message without further structure.
+    Log {
+        message: String,
+    },
+    /// The command failed; the last event of a failed run.
+    Error {
+        message: String,
+    },
+}
+
+/// An `InstallEvent` with measurements taken when it was emitted. It
+/// serializes flat: the event's own fields sit next to these.
+#[derive(Serialize, Debug, Clone)]
+pub struct Event {
+    /// Milliseconds since this process emitted its first event, from a
+    /// monotonic clock.
+    pub elapsed_ms: u64,
+    /// Milliseconds since the current phase began. Every event other than
+    /// progress and log messages ends one phase and begins the next, and
+    /// carries the duration of the phase it ends, e.g. `download-finished`
+    /// that of the download.
+    pub phase_elapsed_ms: u64,
+    /// Bytes downloaded by this process so far, over all downloads.
+    pub bytes_received: u64,
+    #[serde(flatten)]
+    pub event: InstallEvent,
+}
+
+pub type EventHandler = Arc<dyn Fn(&Event) + Send + Sync>;
+
+static HANDLER: RwLock<Option<EventHandler>> = RwLock::new(None);
+
+static PRINTING: AtomicBool = AtomicBool::new(true);
+
+struct Clock {
+    started: Instant,
+    phase_started: Instant,
+    bytes_received: u64,
+    /// `bytes_received` when the current download started.
+    download_base: u64,
+}
+
+static CLOCK: Mutex<Option<Clock>> = Mutex::new(None);
+
+/// How many events failure reports include.
+const RECENT_EVENTS: usize = 100;
+
+/// The latest events, for failure reports. Progress events
+/// are left out; they would crowd out everything else.
+static RECENT: Mutex<VecDeque<serde_json::Value>> = Mutex::new(VecDeque::new());
+
+fn remember(event: &Event) {
+    if matches!(event.event, InstallEvent::DownloadProgress { .. }) {
+        return;
+    }
+    let Ok(value) = serde_json::to_value(event) else {
+        return;
+    };
+    let mut recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+    if recent.len() == RECENT_EVENTS {
+        recent.pop_front();
+    }
+    recent.push_back(value);
+}
+
+/// The events emitted most recently, oldest first.
+pub fn recent() -> Vec<serde_json::Value> {
+    let recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+    recent.iter().cloned().collect()
+}
+
+/// Stamps `event` with the current measurements and advances them.
+fn measure(event: InstallEvent) -> Event {
+    let now = Instant::now();
+    let mut clock = CLOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let clock = clock.get_or_insert_with(|| Clock {
+        started: now,
+        phase_started: now,
+        bytes_received: 0,
+        download_base: 0,
+    });
+    match &event {
+        InstallEvent::DownloadStarted { .. } => clock.download_base = clock.bytes_received,
+        InstallEvent::DownloadProgress {
+            downloaded_bytes, ..
+        } => clock.bytes_received = clock.download_base + downloaded_bytes,
+        _ => {}
+    }
+    let measured = Event {
+        elapsed_ms: now.duration_since(clock.started).as_millis() as u64,
+        phase_elapsed_ms: now.duration_since(clock.phase_started).as_millis() as u64,
+        bytes_received: clock.bytes_received,
+        event,
+    };
+    if !matches!(
+        measured.event,
+        InstallEvent::DownloadProgress { .. } | InstallEvent::Log { .. }
+    ) {
+        clock.phase_started = now;
+    }
+    measured
+}
+
+/// A decision the install needs from the user.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "prompt", rename_all = "kebab-case")]
+pub enum Prompt {
+    /// Files cursorup did not install are in the install dir; move them
+    /// into `to` before installing?
+    MoveForeignFiles { files: Vec<PathBuf>, to: PathBuf },
+    /// The desktop entry has changes made by hand that rewriting it would
+    /// undo; `diff` shows what would change.
+    RewriteDesktopEntry { path: PathBuf, diff: String },
+    /// `dir` is not on `PATH`; append `line` to `rc_file`?
+    AddToPath {
+        dir: PathBuf,
+        rc_file: PathBuf,
+        line: String,
+    },
+    /// Cursor's sandbox cannot start on this system; launch Cursor with
+    /// `--no-sandbox` from now on?
+    DisableSandbox,
+    /// `--no-backup`: delete the installed Cursor `version` without keeping
+    /// a backup to roll back to?
+    DeleteWithoutBackup { version: String },
+    /// `restart_strategy = "prompt"`: Cursor is running the previous
+    /// version; quit it and start `version`?
+    RestartCursor { version: String },
+    /// Replace the installed Cursor `from` with `to`, moving its files into
+    /// `back/`?
+    ReplaceInstall { from: String, to: String },
+}
+
+impl Prompt {
+    pub fn question(&self) -> String {
+        match self {
+            Prompt::MoveForeignFiles { to, .. } => format!("Move them to {:?}?", to),
+            Prompt::RewriteDesktopEntry { .. } => {
+                "Rewrite the desktop entry with these changes?".to_string()
+            }
+            Prompt::AddToPath { rc_file, .. } => format!("Append it to {}?", rc_file.display()),
+            Prompt::DisableSandbox => "Launch Cursor with --no-sandbox from now on?".to_string(),
+            Prompt::DeleteWithoutBackup { .. } => "Install without a backup?".to_string(),
+            Prompt::RestartCursor { version } => format!("Restart Cursor now to use {}?", version),
+            Prompt::ReplaceInstall { from, to } => format!("Replace Cursor {} with {}?", from, to),
+        }
+    }
+
+    /// The answer a plain Enter, or a dropped `Responder`, gives.
+    pub fn default_answer(&self) -> bool {
+        matches!(
+            self,
+            Prompt::RewriteDesktopEntry { .. } | Prompt::ReplaceInstall { .. }
+        )
+    }
+
+    /// What the terminal shows before the question.
+    pub fn details(&self) -> Option<String> {
+        match self {
+            Prompt::MoveForeignFiles { files, .. } => {
+                let mut details = "These files were not installed by cursorup:".to_string();
+                for file in files {
+                    details.push_str(&format!("\n  {}", file.display()));
+                }
+                Some(details)
+            }
+            // The diff is logged before asking.
+            Prompt::RewriteDesktopEntry { .. } => None,
+            Prompt::AddToPath { dir, rc_file, line } => Some(format!(
+                "{} is not on your PATH, so the `cursor` command won't be found. Add this line to {}:\n  {}",
+                dir.display(),
+                rc_file.display(),
+                line
+            )),
+            Prompt::DisableSandbox => None,
+            Prompt::DeleteWithoutBackup { version } => Some(format!(
+                "Cursor {} will be deleted without a backup; `cursorup rollback` will have nothing to return to.",
+                version
+            )),
+            Prompt::RestartCursor { .. } => Some(
+                "Cursor is still running the previous version. Restarting closes its windows and reopens them; unsaved changes are kept as on any quit.".to_string(),
+            ),
+            Prompt::ReplaceInstall { from, .. } => Some(format!(
+                "The files of Cursor {} will be moved into back/, where `cursorup rollback` can restore them.",
+                from
+            )),
+        }
+    }
+}
+
+/// Answers one `Prompt`. Dropping it unanswered gives the prompt's default
+/// answer.
+pub struct Responder(tokio::sync::oneshot::Sender<bool>);
+
+impl Responder {
+    pub fn answer(self, yes: bool) {
+        let _ = self.0.send(yes);
+    }
+}
+
+pub type PromptHandler = Arc<dyn Fn(Prompt, Responder) + Send + Sync>;
+
+static PROMPT_HANDLER: RwLock<Option<PromptHandler>> = RwLock::new(None);
+
+/// Whether prompts are asked at all, for `--yes` and `--non-interactive`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interaction {
+    /// Ask the prompt handler or the terminal.
+    #[default]
+    Ask,
+    /// Answer every prompt with yes without asking.
+    AssumeYes,
+    /// Ask nobody, as without a terminal, so each step keeps its
+    /// unattended behaviour.
+    Never,
+}
+
+static INTERACTION: RwLock<Interaction> = RwLock::new(Interaction::Ask);
+
+/// Sets how all subsequent prompts are answered.
+pub fn set_interaction(interaction: Interaction) {
+    if let Ok(mut current) = INTERACTION.write() {
+        *current = interaction;
+    }
+}
+
+/// Sends all subsequent prompts to `handler` instead of the terminal. The
+/// install waits until the `Responder` is answered or dropped.
+pub fn set_prompt_handler(handler: PromptHandler) {
+    if let Ok(mut current) = PROMPT_HANDLER.write() {
+        *current = Some(handler);
+    }
+}
+
+/// Asks in the terminal again, where there is one.
+pub fn clear_prompt_handler() {
+    if let Ok(mut current) = PROMPT_HANDLER.write() {
+        *current = None;
+    }
+}
+
+/// Asks `prompt` through the prompt handler or, without one, in the
+/// terminal. `None` means nobody could be asked.
+pub(crate) async fn ask(prompt: Prompt) -> crate::Result<Option<bool>> {
+    match INTERACTION.read().map(|interaction| *interaction) {
+        Ok(Interaction::AssumeYes) => return Ok(Some(true)),
+        Ok(Interaction::Never) => return Ok(None),
+        _ => {}
+    }
+    let handler = PROMPT_HANDLER
+        .read()
+        .ok()
+        .and_then(|handler| handler.clone());
+    if let Some(handler) = handler {
+        let default = prompt.default_answer();
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        handler(prompt, Responder(sender));
+        return Ok(Some(receiver.await.unwrap_or(default)));
+    }
+    if !std::io::stdin().is_terminal() {
+        return Ok(None);
+    }
+    progress::finish();
+    if let Some(details) = prompt.details() {
+        println!("{}", details);
+    }
+    crate::wizard::ask_bool(&prompt.question(), prompt.default_answer()).map(Some)
+}
+
+/// Routes all subsequent events to `handler` instead of stdout.
+pub fn set_handler(handler: EventHandler) {
+    if let Ok(mut current) = HANDLER.write() {
+        *current = Some(handler);
+    }
+}
+
+/// Restores the default stdout printing.
+pub fn clear_handler() {
+    if let Ok(mut current) = HANDLER.write() {
+        *current = None;
+    }
+}
+
+/// Turns the default stdout printing off or back on, e.g. for `--quiet` or
+/// a program that only wants the `tracing` events. A registered handler
+/// still gets every event.
+pub fn set_printing(enabled: bool) {
+    PRINTING.store(enabled, Ordering::Relaxed);
+}
+
+pub(crate) fn emit(event: InstallEvent) {
+    let event = measure(event);
+    remember(&event);
+    trace(&event.event);
+    let handler = HANDLER.read().ok().and_then(|handler| handler.clone());
+    match handler {
+        Some(handler) => handler(&event),
+        // Errors are printed even when nothing else is.
+        None if PRINTING.load(Ordering::Relaxed)
+            || matches!(event.event, InstallEvent::Error { .. }) =>
+        {
+            print_event(&event)
+        }
+        None => {}
+    }
+}
+
+fn trace(event: &InstallEvent) {
+    match event {
+        InstallEvent::Log { message } => tracing::info!(target: "cursorup::events", "{}", message),
+        InstallEvent::DownloadProgress {
+            downloaded_bytes,
+            total_bytes,
+        } => tracing::trace!(
+            target: "cursorup::events",
+            downloaded_bytes,
+            total_bytes,
+            "download progress"
+        ),
+        event => tracing::info!(
+            target: "cursorup::events",
+            event = %serde_json::to_string(event).unwrap_or_default()
+        ),
+    }
+}
+
+/// Reports the error a command failed with, as the last event.
+pub fn error(error: &dyn std::error::Error) {
+    emit(InstallEvent::Error {
+        message: error.to_string(),
+    });
+}
+
+pub(crate) fn log(message: impl Into<String>) {
+    emit(InstallEvent::Log {
+        message: message.into(),
+    });
+}
+
+fn print_event(measured: &Event) {
+    // Every other event begins a new phase, which replaces the bar.
+    if !matches!(
+        measured.event,
+        InstallEvent::DownloadProgress { .. } | InstallEvent::Log { .. }
+    ) {
+        progress::finish();
+    }
+    match &measured.event {
+        InstallEvent::MetadataFetched {
+            version,
+            commit_sha,
+            download_url,
+        } => println!(
+            "{}",
+            tr(
+                "metadata-fetched",
+                &[
+                    ("version", version),
+                    ("commit", commit_sha),
+                    ("url", download_url)
+                ]
+            )
+        ),
+        InstallEvent::DownloadStarted { url, total_bytes } => {
+            println!("{}", tr("download-started", &[("url", url)]));
+            progress::download(tr("downloading", &[]), *total_bytes);
+        }
+        InstallEvent::DownloadProgress {
+            downloaded_bytes, ..
+        } => progress::downloaded(*downloaded_bytes),
+        InstallEvent::DownloadFinished { path, .. } => println!(
+            "{}",
+            tr("download-finished", &[("path", &format!("{:?}", path))])
+        ),
+        InstallEvent::Extracting { .. } => progress::spinner(tr("extracting", &[])),
+        InstallEvent::Copying { path } => {
+            progress::spinner(tr("copying", &[("path", &format!("{:?}", path))]))
+        }
+        InstallEvent::BackedUp { from, to } => println!(
+            "{}",
+            tr(
+                "backed-up",
+                &[
+                    ("from", &format!("{:?}", from)),
+                    ("to", &format!("{:?}", to))
+                ]
+            )
+        ),
+        InstallEvent::Installed { .. } => println!("{}", tr("installed", &[])),
+        InstallEvent::Log { message } => progress::println(message),
+        InstallEvent::Error { message } => {
+            eprintln!("{}", tr("application-error", &[("error", message)]))
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! How AppImages are run. The AppImage runtime normally mounts its payload
+//! with FUSE, which hardened containers and minimal systems lack; there it
+//! can unpack to a temporary dir and run from there instead.
+
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Runtime flag that runs the AppImage from a temporary extraction.
+pub const EXTRACT_AND_RUN_FLAG: &str = "--appimage-extract-and-run";
+
+/// Environment variable with the same effect as `EXTRACT_AND_RUN_FLAG`,
+/// honoured for every runtime invocation including `--appimage-extract`.
+const EXTRACT_AND_RUN_ENV: &str = "APPIMAGE_EXTRACT_AND_RUN";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecutionMode {
+    /// `Direct` when FUSE is usable, `ExtractAndRun` otherwise.
+    #[default]
+    Auto,
+    Direct,
+    ExtractAndRun,
+}
+
+impl FromStr for ExecutionMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ExecutionMode::Auto),
+            "direct" => Ok(ExecutionMode::Direct),
+            "extract-and-run" => Ok(ExecutionMode::ExtractAndRun),
+            _ => Err(format!(
+                "unknown execution mode `{}` (expected auto, direct or extract-and-run)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ExecutionMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ExecutionMode::Auto => "auto",
+            ExecutionMode::Direct => "direct",
+            ExecutionMode::ExtractAndRun => "extract-and-run",
+        })
+    }
+}
+
+impl ExecutionMode {
+    /// Decides `Auto` for this machine.
+    pub fn resolve(self) -> ExecutionMode {
+        match self {
+            ExecutionMode::Auto if fuse_available() => ExecutionMode::Direct,
+            ExecutionMode::Auto => ExecutionMode::ExtractAndRun,
+            mode => mode,
+        }
+    }
+
+    /// Arguments launchers put before the user's when starting the AppImage.
+    pub fn launch_args(self) -> &'static [&'static str] {
+        match self.resolve() {
+            ExecutionMode::ExtractAndRun => &[EXTRACT_AND_RUN_FLAG],
+            _ => &[],
+        }
+    }
+
+    /// Prepares a runtime invocation such as `--appimage-extract` for this
+    /// mode.
+    pub fn configure(self, command: &mut tokio::process::Command) {
+        if self.resolve() == ExecutionMode::ExtractAndRun {
+            command.env(EXTRACT_AND_RUN_ENV, "1");
+        }
+    }
+}
+
+/// Whether the kernel offers FUSE and a `fusermount` helper is installed.
+pub fn fuse_available() -> bool {
+    Path::new("/dev/fuse").exists()
+        && ["fusermount3", "fusermount"]
+            .iter()
+            .any(|tool| on_path(tool))
+}
+
+fn on_path(tool: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(tool).is_file()))
+}
+
+/// Whether a failed runtime invocation failed for lack of FUSE, in which
+/// case `Auto` retries it in extract-and-run mode.
+pub fn is_fuse_error(stderr: &str) -> bool {
+    stderr.to_lowercase().contains("fuse")
+}
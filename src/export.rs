@@ -0,0 +1,166 @@
+//! `download`: fetch and verify a release like `update` does, then copy the
+//! AppImage into a directory of the user's choosing instead of installing it.
+
+use crate::config::Config;
+use crate::{InstallOptions, Result};
+use serde::Serialize;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use tokio::fs;
+
+/// How exported artifacts are arranged below the output dir.
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// `<output_dir>/<file>`
+    #[default]
+    Flat,
+    /// `<output_dir>/<version>/<file>`
+    Versioned,
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "flat" => Ok(Layout::Flat),
+            "versioned" => Ok(Layout::Versioned),
+            other => Err(format!(
+                "unknown layout `{}` (expected flat or versioned)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    pub output_dir: PathBuf,
+    pub layout: Layout,
+    /// File name template, e.g. `cursor-{version}-{arch}.AppImage`. `None`
+    /// keeps the upstream file name.
+    pub file_name: Option<String>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            output_dir: PathBuf::from("."),
+            layout: Layout::default(),
+            file_name: None,
+        }
+    }
+}
+
+/// What `download` wrote.
+#[derive(Serialize, Debug)]
+pub struct Exported {
+    pub file: PathBuf,
+    pub version: String,
+    pub commit_sha: String,
+    pub sha256: String,
+    pub size: u64,
+}
+
+impl fmt::Display for Exported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Exported:      {}", self.file.display())?;
+        writeln!(f, "Version:       {}", self.version)?;
+        writeln!(f, "Commit:        {}", self.commit_sha)?;
+        writeln!(f, "SHA-256:       {}", self.sha256)?;
+        write!(f, "Size:          {} bytes", self.size)
+    }
+}
+
+/// Expands `{version}`, `{arch}`, `{platform}`, `{track}` and `{commit}` in
+/// a file name template.
+pub fn expand_file_name(
+    template: &str,
+    config: &Config,
+    version: &str,
+    commit: &str,
+) -> Result<String> {
+    let mut name = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        name.push_str(&rest[..start]);
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed `{{` in file name template {:?}", template))?;
+        let value = match &rest[start + 1..start + end] {
+            "version" => version.to_string(),
+            "arch" => config.platform.appimage_arch().to_string(),
+            "platform" => config.platform.to_string(),
+            "track" => config.release_track.to_string(),
+            "commit" => commit.to_string(),
+            other => {
+                return Err(format!(
+                    "Unknown placeholder `{{{}}}` in file name template (expected version, arch, platform, track or commit)",
+                    other
+                )
+                .into());
+            }
+        };
+        name.push_str(&value);
+        rest = &rest[start + end + 1..];
+    }
+    name.push_str(rest);
+    if name.is_empty() || name.contains('/') {
+        return Err(format!(
+            "File name template {:?} must expand to a plain file name",
+            template
+        )
+        .into());
+    }
+    Ok(name)
+}
+
+/// Downloads and verifies the resolved release, then copies it and its
+/// `.sha256` sidecar into the output dir.
+pub async fn download(
+    config: &Config,
+    install: &InstallOptions,
+    options: &ExportOptions,
+) -> Result<Exported> {
+    // Reject a bad template before spending time on the download.
+    if let Some(template) = &options.file_name {
+        expand_file_name(template, config, "0", "0")?;
+    }
+    let prepared = crate::fetch_release(config, install).await?;
+    let provenance = prepared.provenance;
+
+    let file_name = match &options.file_name {
+        Some(template) => expand_file_name(
+            template,
+            config,
+            &provenance.version,
+            &provenance.commit_sha,
+        )?,
+        None => provenance.file.clone(),
+    };
+    let dir = match options.layout {
+        Layout::Flat => options.output_dir.clone(),
+        Layout::Versioned => options.output_dir.join(&provenance.version),
+    };
+    fs::create_dir_all(&dir).await?;
+    let file = dir.join(file_name);
+
+    crate::events::log(format!("Copying {:?} to {:?}", prepared.appimage, file));
+    fs::copy(&prepared.appimage, &file).await?;
+    crate::provenance::write_checksum(
+        &file,
+        provenance.installed_sha256(),
+        provenance.installed_checksum_source(),
+    )
+    .await?;
+
+    Ok(Exported {
+        file,
+        version: provenance.version.clone(),
+        commit_sha: provenance.commit_sha.clone(),
+        sha256: provenance.installed_sha256().to_string(),
+        size: provenance.installed_size(),
+    })
+}
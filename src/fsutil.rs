@@ -0,0 +1,201 @@
+use crate::Result;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// The first value of a field in `/proc/self/status`, e.g. `Umask:`.
+fn proc_status(field: &str) -> Option<String> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix(field))
+        .and_then(|value| value.split_whitespace().next())
+        .map(str::to_string)
+}
+
+/// The process umask, read from `/proc/self/status` (Linux 4.7+), falling
+/// back to the common 022 when it cannot be determined.
+pub fn umask() -> u32 {
+    proc_status("Umask:")
+        .and_then(|mask| u32::from_str_radix(&mask, 8).ok())
+        .unwrap_or(0o022)
+}
+
+pub fn is_root() -> bool {
+    proc_status("Uid:").as_deref() == Some("0")
+}
+
+/// The real group id of the process, i.e. the user's primary group.
+pub fn primary_gid() -> u32 {
+    proc_status("Gid:")
+        .and_then(|gid| gid.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Mode for installed executables: the configured `file_mode`, or what a
+/// freshly created executable would get under the current umask.
+pub fn executable_mode(file_mode: Option<u32>) -> u32 {
+    file_mode.unwrap_or(0o777 & !umask())
+}
+
+/// Mode for installed data files such as the icon: the executable mode
+/// without the execute bits.
+pub fn data_mode(file_mode: Option<u32>) -> u32 {
+    executable_mode(file_mode) & 0o666
+}
+
+/// Mode for files other programs read but nobody else may change (desktop
+/// entry, config and state): never world-writable, whatever the umask.
+pub fn protected_mode() -> u32 {
+    0o666 & !umask() & !0o002
+}
+
+/// Sets the permission bits of `path`. Windows has none to set, so there it
+/// does nothing.
+pub async fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    #[cfg(unix)]
+    fs::set_permissions(path, permissions(mode)).await?;
+    #[cfg(not(unix))]
+    let _ = (path, mode);
+    Ok(())
+}
+
+#[cfg(unix)]
+fn permissions(mode: u32) -> std::fs::Permissions {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::Permissions::from_mode(mode)
+}
+
+/// `path` with `suffix` appended to its file name, e.g. `x.AppImage.part`.
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_os_string();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Reserves `len` bytes on disk for `file`, open at `path`, with
+/// fallocate(2), so a full disk fails right away instead of part way through
+/// a long download, and the file ends up in few extents. The file's size is
+/// left alone, so a partial download still shows how far it got. Where the
+/// filesystem doesn't support it, the file is written without reservation.
+#[cfg(target_os = "linux")]
+pub fn preallocate(file: &fs::File, path: &Path, len: u64) -> Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let Ok(len) = libc::off_t::try_from(len) else {
+        return Ok(());
+    };
+    let error = loop {
+        // SAFETY: fallocate(2) only acts on the descriptor `file` owns.
+        if unsafe { libc::fallocate(file.as_raw_fd(), libc::FALLOC_FL_KEEP_SIZE, 0, len) } == 0 {
+            return Ok(());
+        }
+        let error = std::io::Error::last_os_error();
+        if error.kind() != std::io::ErrorKind::Interrupted {
+            break error;
+        }
+    };
+    match error.raw_os_error() {
+        Some(libc::ENOSPC) => Err(format!(
+            "Not enough free space for {:.2} MB at {:?}",
+            len as f64 / 1_048_576.0,
+            path
+        )
+        .into()),
+        Some(libc::EOPNOTSUPP) => {
+            tracing::debug!("{:?} does not support fallocate, not pre-allocating", path);
+            Ok(())
+        }
+        _ => {
+            tracing::debug!(%error, "fallocate failed, not pre-allocating");
+            Ok(())
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn preallocate(_file: &fs::File, _path: &Path, _len: u64) -> Result<()> {
+    Ok(())
+}
+
+/// Replaces `path` with `contents` so that readers see either the old or the
+/// new file, never a truncated one: the data is written and fsynced to a
+/// sibling temp file, renamed over the target, and the directory is synced.
+pub async fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let dir = path.parent().ok_or("Path has no parent directory")?;
+    fs::create_dir_all(dir).await?;
+
+    let mut tmp_name = path
+        .file_name()
+        .ok_or("Path has no file name")?
+        .to_os_string();
+    tmp_name.push(format!(".tmp-{}", std::process::id()));
+    let tmp_path = dir.join(tmp_name);
+
+    let result = async {
+        let mut file = fs::File::create(&tmp_path).await?;
+        #[cfg(unix)]
+        file.set_permissions(permissions(protected_mode())).await?;
+        file.write_all(contents.as_ref()).await?;
+        file.sync_all().await?;
+        drop(file);
+        fs::rename(&tmp_path, path).await?;
+        fs::File::open(dir).await?.sync_all().await?;
+        Ok::<_, std::io::Error>(())
+    }
+    .await;
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path).await;
+    }
+    Ok(result?)
+}
+
+/// How `link_or_copy` placed a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placed {
+    Hardlink,
+    Reflink,
+    Copy,
+}
+
+impl Placed {
+    /// For log lines such as "Linked AppImage to ...".
+    pub fn verb(self) -> &'static str {
+        match self {
+            Placed::Hardlink => "Linked",
+            Placed::Reflink => "Cloned",
+            Placed::Copy => "Copied",
+        }
+    }
+}
+
+/// Puts a copy of `from` at `to`, replacing it, that shares `from`'s data
+/// where the filesystem allows: a hardlink, else a reflink (`cp
+/// --reflink=always`, on btrfs or XFS), else a full copy. Only for files
+/// nobody writes to in place, such as AppImages, since a write through a
+/// hardlink changes both.
+pub async fn link_or_copy(from: &Path, to: &Path) -> Result<Placed> {
+    match fs::remove_file(to).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    if fs::hard_link(from, to).await.is_ok() {
+        return Ok(Placed::Hardlink);
+    }
+    let reflinked = Command::new("cp")
+        .arg("--reflink=always")
+        .arg("--")
+        .arg(from)
+        .arg(to)
+        .output()
+        .await
+        .is_ok_and(|output| output.status.success());
+    if reflinked {
+        return Ok(Placed::Reflink);
+    }
+    let _ = fs::remove_file(to).await;
+    fs::copy(from, to).await?;
+    Ok(Placed::Copy)
+}
@@ -0,0 +1,60 @@
+//! GitHub Actions workflow commands and step summaries, active only when
+//! running under Actions (`GITHUB_ACTIONS=true`).
+
+use std::io::Write;
+
+pub fn enabled() -> bool {
+    std::env::var("GITHUB_ACTIONS").is_ok_and(|v| v == "true")
+}
+
+pub fn notice(message: &str) {
+    if enabled() {
+        println!("::notice title=cursorup::{}", escape(message));
+    }
+}
+
+pub fn error(message: &str) {
+    if enabled() {
+        println!("::error title=cursorup::{}", escape(message));
+    }
+}
+
+/// Appends Markdown to the job's step summary file, if Actions provided one.
+pub fn append_summary(markdown: &str) {
+    if !enabled() {
+        return;
+    }
+    let Some(path) = std::env::var_os("GITHUB_STEP_SUMMARY") else {
+        return;
+    };
+    let result = std::fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{}", markdown));
+    if let Err(e) = result {
+        eprintln!("Failed to write GitHub step summary: {e}");
+    }
+}
+
+/// Summary written after a successful update.
+pub fn update_summary(old_version: Option<&str>, new_version: &str, bytes: u64) {
+    notice(&format!(
+        "Updated Cursor {} -> {}",
+        old_version.unwrap_or("(none)"),
+        new_version
+    ));
+    append_summary(&format!(
+        "### cursorup\n\n| Previous version | New version | Downloaded |\n| --- | --- | --- |\n| {} | {} | {:.2} MB |\n",
+        old_version.unwrap_or("—"),
+        new_version,
+        bytes as f64 / 1_048_576.0
+    ));
+}
+
+fn escape(message: &str) -> String {
+    message
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
@@ -0,0 +1,202 @@
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Updated,
+    Failed,
+    #[serde(rename = "rolled_back")]
+    RolledBack,
+}
+
+/// One update attempt, appended to `history.jsonl` in the state dir.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryEntry {
+    /// Seconds since the Unix epoch.
+    pub at: u64,
+    /// Machine the attempt ran on, so exports from a fleet can be merged.
+    pub host: String,
+    pub outcome: Outcome,
+    pub from_version: Option<String>,
+    pub to_version: Option<String>,
+    pub commit_sha: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(transparent)]
+pub struct History(pub Vec<HistoryEntry>);
+
+impl fmt::Display for History {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "No recorded updates.");
+        }
+        for (index, entry) in self.0.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            let from = entry.from_version.as_deref().unwrap_or("-");
+            write!(f, "{}  ", crate::output::format_timestamp(entry.at))?;
+            match entry.outcome {
+                Outcome::Updated => write!(
+                    f,
+                    "updated {} -> {}",
+                    from,
+                    entry.to_version.as_deref().unwrap_or("?")
+                )?,
+                Outcome::RolledBack => write!(
+                    f,
+                    "rolled back {} -> {}",
+                    from,
+                    entry.to_version.as_deref().unwrap_or("?")
+                )?,
+                Outcome::Failed => write!(
+                    f,
+                    "FAILED  {}: {}",
+                    from,
+                    entry.error.as_deref().unwrap_or("unknown error")
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl HistoryEntry {
+    pub fn new(outcome: Outcome, from_version: Option<String>) -> Self {
+        Self {
+            at: crate::state::now(),
+            host: hostname(),
+            outcome,
+            from_version,
+            to_version: None,
+            commit_sha: None,
+            error: None,
+        }
+    }
+}
+
+pub fn path() -> Result<PathBuf> {
+    Ok(crate::state::State::dir()?.join("history.jsonl"))
+}
+
+/// Appends one entry as a JSON line and syncs it to disk. A line cut short
+/// by a crash is ended first, so only it is lost, not the new entry too.
+pub async fn record(entry: &HistoryEntry) -> Result<()> {
+    let path = path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .read(true)
+        .create(true)
+        .append(true)
+        .open(&path)
+        .await?;
+    let mut line = String::new();
+    let len = file.metadata().await?.len();
+    if len > 0 {
+        let mut last = [0u8];
+        file.seek(std::io::SeekFrom::Start(len - 1)).await?;
+        file.read_exact(&mut last).await?;
+        if last[0] != b'\n' {
+            line.push('\n');
+        }
+    }
+    line.push_str(&serde_json::to_string(entry)?);
+    line.push('\n');
+    file.write_all(line.as_bytes()).await?;
+    file.flush().await?;
+    file.sync_data().await?;
+    Ok(())
+}
+
+/// Records a failed update. Problems writing the log are only reported, so
+/// they never hide the original error.
+pub async fn record_failure(from_version: Option<String>, error: &dyn std::error::Error) {
+    let mut entry = HistoryEntry::new(Outcome::Failed, from_version);
+    entry.error = Some(error.to_string());
+    if let Err(e) = record(&entry).await {
+        tracing::warn!("could not record the failure in the history log: {}", e);
+    }
+}
+
+/// Entries at most `max_age` seconds old, or all of them. Lines that fail to
+/// parse, e.g. one cut short by a crash, are skipped, as are invalid UTF-8
+/// bytes, so one damaged line doesn't hide the rest.
+pub async fn load(max_age: Option<u64>) -> Result<History> {
+    let since = max_age.map(|age| crate::state::now().saturating_sub(age));
+    let contents = match fs::read(path()?).await {
+        Ok(contents) => String::from_utf8_lossy(&contents).into_owned(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(History(Vec::new())),
+        Err(e) => return Err(e.into()),
+    };
+    let entries = contents
+        .lines()
+        .filter_map(|line| serde_json::from_str::<HistoryEntry>(line).ok())
+        .filter(|entry| since.is_none_or(|since| entry.at >= since))
+        .collect();
+    Ok(History(entries))
+}
+
+/// Parses an age such as `90m`, `12h`, `30d` or `2w` into seconds.
+pub fn parse_age(s: &str) -> std::result::Result<u64, String> {
+    let invalid = || format!("invalid duration `{}` (expected e.g. 12h, 30d or 2w)", s);
+    let unit_at = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let (count, unit) = s.split_at(unit_at);
+    let count: u64 = count.parse().map_err(|_| invalid())?;
+    let unit = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    count.checked_mul(unit).ok_or_else(invalid)
+}
+
+fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|name| name.trim().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ages() {
+        assert_eq!(parse_age("45s").unwrap(), 45);
+        assert_eq!(parse_age("90m").unwrap(), 90 * 60);
+        assert_eq!(parse_age("12h").unwrap(), 12 * 60 * 60);
+        assert_eq!(parse_age("30d").unwrap(), 30 * 24 * 60 * 60);
+        assert_eq!(parse_age("2w").unwrap(), 14 * 24 * 60 * 60);
+        assert_eq!(parse_age("0d").unwrap(), 0);
+    }
+
+    #[test]
+    fn rejects_bad_ages() {
+        for age in [
+            "",
+            "30",
+            "d",
+            "30x",
+            "1.5h",
+            "-1d",
+            "30 d",
+            "30dd",
+            "99999999999999999999w",
+            "9999999999999999w",
+        ] {
+            assert!(parse_age(age).is_err(), "{:?} parsed", age);
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use crate::Result;
+use crate::config::Config;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Once;
+use std::time::{Duration, Instant};
+
+/// Address family used for outgoing connections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IpVersion {
+    #[default]
+    Any,
+    V4,
+    V6,
+}
+
+impl FromStr for IpVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(IpVersion::Any),
+            "ipv4" => Ok(IpVersion::V4),
+            "ipv6" => Ok(IpVersion::V6),
+            _ => Err(format!(
+                "unknown IP version `{}` (expected any, ipv4 or ipv6)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for IpVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IpVersion::Any => "any",
+            IpVersion::V4 => "ipv4",
+            IpVersion::V6 => "ipv6",
+        })
+    }
+}
+
+/// A curl-style `host:port:addr` DNS override.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolve {
+    pub host: String,
+    pub port: u16,
+    pub addr: IpAddr,
+}
+
+impl FromStr for Resolve {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || format!("invalid resolve entry `{}` (expected host:port:addr)", s);
+        let (host, rest) = s.split_once(':').ok_or_else(invalid)?;
+        let (port, addr) = rest.split_once(':').ok_or_else(invalid)?;
+        let addr = addr.trim_start_matches('[').trim_end_matches(']');
+        Ok(Resolve {
+            host: host.to_string(),
+            port: port.parse().map_err(|_| invalid())?,
+            addr: addr.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl fmt::Display for Resolve {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.addr {
+            IpAddr::V4(addr) => write!(f, "{}:{}:{}", self.host, self.port, addr),
+            IpAddr::V6(addr) => write!(f, "{}:{}:[{}]", self.host, self.port, addr),
+        }
+    }
+}
+
+/// Builds the HTTP client shared by metadata requests and downloads. It
+/// accepts gzip and brotli responses and decompresses them transparently;
+/// downloads opt out with `get_artifact`.
+pub fn client(config: &Config) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder().gzip(true).brotli(true);
+    builder = match config.ip_version {
+        IpVersion::Any => builder,
+        // Binding to the unspecified address of one family restricts
+        // connections to that family.
+        IpVersion::V4 => builder.local_address(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+        IpVersion::V6 => builder.local_address(IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+    };
+    for entry in &config.resolve {
+        builder = builder.resolve(&entry.host, SocketAddr::new(entry.addr, entry.port));
+    }
+    // Without `proxy`, reqwest picks up HTTPS_PROXY, ALL_PROXY and friends
+    // by itself.
+    if let Some(proxy) = &config.proxy {
+        check_proxy(proxy)?;
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?.no_proxy(reqwest::NoProxy::from_env()));
+    }
+    if let Some(path) = &config.ca_cert {
+        for certificate in ca_certificates(path)? {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+    if config.insecure {
+        static WARNED: Once = Once::new();
+        WARNED.call_once(|| {
+            tracing::warn!(
+                "TLS certificates are not checked (--insecure); anyone on the \
+                 network path can tamper with downloads. Prefer --ca-cert with your proxy's CA."
+            )
+        });
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    Ok(builder.build()?)
+}
+
+/// A GET for a download. The client asks for gzip or brotli, which shrinks
+/// metadata and checksum responses, but a compressed artifact would break
+/// `Content-Length`, byte ranges and the hash of the file as published.
+pub(crate) fn get_artifact(client: &reqwest::Client, url: &str) -> reqwest::RequestBuilder {
+    client
+        .get(url)
+        .header(reqwest::header::ACCEPT_ENCODING, "identity")
+}
+
+/// Reads the certificates of a PEM bundle, which may hold several.
+fn ca_certificates(path: &Path) -> Result<Vec<reqwest::Certificate>> {
+    let pem = std::fs::read(path)
+        .map_err(|e| format!("Cannot read the CA certificates {:?}: {}", path, e))?;
+    let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+        .map_err(|e| format!("{:?} is not a PEM certificate bundle: {}", path, e))?;
+    if certificates.is_empty() {
+        return Err(format!("{:?} contains no PEM certificates", path).into());
+    }
+    Ok(certificates)
+}
+
+/// Variables reqwest reads a proxy for HTTPS requests from, in its order.
+const HTTPS_PROXY_VARIABLES: [&str; 4] = ["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"];
+
+/// The proxy requests to the API go through, for display: the configured
+/// one, else the one from the environment, with any password masked.
+/// `NO_PROXY` is not taken into account.
+pub fn proxy(config: &Config) -> Option<String> {
+    let proxy = config.proxy.clone().or_else(|| {
+        HTTPS_PROXY_VARIABLES
+            .iter()
+            .find_map(|name| std::env::var(name).ok().filter(|value| !value.is_empty()))
+    })?;
+    match reqwest::Url::parse(&proxy) {
+        Ok(mut url) if url.password().is_some() => {
+            let _ = url.set_password(Some("***"));
+            Some(url.to_string())
+        }
+        _ => Some(proxy),
+    }
+}
+
+/// Rejects schemes reqwest can't proxy through rather than failing on the
+/// first request. `socks5h` resolves host names on the proxy, `socks5`
+/// locally.
+fn check_proxy(proxy: &str) -> Result<()> {
+    let url =
+        reqwest::Url::parse(proxy).map_err(|e| format!("invalid proxy `{}`: {}", proxy, e))?;
+    match url.scheme() {
+        "http" | "https" | "socks5" | "socks5h" => Ok(()),
+        scheme => Err(format!(
+            "proxy `{}`: unknown scheme `{}` (expected http, https, socks5 or socks5h)",
+            proxy, scheme
+        )
+        .into()),
+    }
+}
+
+/// Paces a download to `limit_rate` bytes per second by sleeping after each
+/// chunk until the bytes so far fit the budget.
+pub(crate) struct Throttle {
+    rate: u64,
+    started: Instant,
+    bytes: u64,
+}
+
+impl Throttle {
+    pub(crate) fn new(rate: u64) -> Throttle {
+        Throttle {
+            rate: rate.max(1),
+            started: Instant::now(),
+            bytes: 0,
+        }
+    }
+
+    pub(crate) async fn pace(&mut self, bytes: usize) {
+        self.bytes += bytes as u64;
+        let due = Duration::from_secs_f64(self.bytes as f64 / self.rate as f64);
+        if let Some(wait) = due.checked_sub(self.started.elapsed()) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(s: &str) -> Resolve {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_curl_style_entries() {
+        assert_eq!(
+            resolve("downloads.cursor.com:443:10.0.0.5"),
+            Resolve {
+                host: "downloads.cursor.com".to_string(),
+                port: 443,
+                addr: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5)),
+            }
+        );
+        // IPv6 addresses may be bracketed or not.
+        assert_eq!(resolve("h:443:[::1]").addr, IpAddr::V6(Ipv6Addr::LOCALHOST));
+        assert_eq!(resolve("h:443:::1").addr, IpAddr::V6(Ipv6Addr::LOCALHOST));
+    }
+
+    #[test]
+    fn displays_back_to_the_same_entry() {
+        for s in ["h:443:10.0.0.5", "h:8080:[fe80::1]"] {
+            assert_eq!(resolve(s).to_string(), s);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_entries() {
+        for s in [
+            "",
+            "h",
+            "h:443",
+            "h:port:10.0.0.5",
+            "h:70000:10.0.0.5",
+            "h:443:host.example",
+            "h:443:10.0.0",
+        ] {
+            assert!(s.parse::<Resolve>().is_err(), "{}", s);
+        }
+    }
+}
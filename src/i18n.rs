@@ -0,0 +1,176 @@
+//! Translated user-facing messages. Catalogs live in `locales/<lang>.ftl`
+//! using the simple-message subset of Fluent (`id = text with { $arg }`) and
+//! are compiled into the binary. To add a language, copy `locales/en.ftl`,
+//! translate the values and register the file in `CATALOGS`.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Bundled catalogs, English first; it is the fallback for missing ids.
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+    ("es", include_str!("../locales/es.ftl")),
+];
+
+type Catalog = HashMap<&'static str, &'static str>;
+
+struct Messages {
+    locale: Catalog,
+    fallback: Catalog,
+}
+
+static MESSAGES: OnceLock<Messages> = OnceLock::new();
+
+fn messages() -> &'static Messages {
+    MESSAGES.get_or_init(|| Messages {
+        locale: detect_locale()
+            .and_then(|lang| catalog(&lang))
+            .map(parse)
+            .unwrap_or_default(),
+        fallback: parse(CATALOGS[0].1),
+    })
+}
+
+/// Looks up message `id` in the user's locale, substituting `{ $name }`
+/// placeholders from `args`. Unknown ids are returned as-is.
+pub fn tr(id: &str, args: &[(&str, &dyn std::fmt::Display)]) -> String {
+    let messages = messages();
+    let template = messages
+        .locale
+        .get(id)
+        .or_else(|| messages.fallback.get(id))
+        .copied()
+        .unwrap_or(id);
+    format_message(template, args)
+}
+
+/// Message `id` in every bundled language, for files such as desktop entries
+/// that carry their own translations (`Comment[de]=...`).
+pub fn all_translations(id: &str) -> Vec<(&'static str, String)> {
+    CATALOGS
+        .iter()
+        .filter_map(|(lang, source)| {
+            parse(source)
+                .get(id)
+                .map(|template| (*lang, format_message(template, &[])))
+        })
+        .collect()
+}
+
+/// Language from `LC_ALL`, `LC_MESSAGES` or `LANG` (first non-empty wins),
+/// e.g. `de_AT.UTF-8@euro` becomes `de_AT`.
+fn detect_locale() -> Option<String> {
+    let value = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())?;
+    let lang = value.split(['.', '@']).next().unwrap_or_default();
+    match lang {
+        "" | "C" | "POSIX" => None,
+        lang => Some(lang.to_string()),
+    }
+}
+
+/// Exact match first (`pt_BR`), then the bare language (`pt`).
+fn catalog(lang: &str) -> Option<&'static str> {
+    let base = lang.split(['_', '-']).next().unwrap_or(lang);
+    [lang, base].iter().find_map(|wanted| {
+        CATALOGS
+            .iter()
+            .find(|(name, _)| name == wanted)
+            .map(|(_, source)| *source)
+    })
+}
+
+fn parse(source: &'static str) -> Catalog {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(id, text)| (id.trim(), text.trim()))
+        .collect()
+}
+
+fn format_message(template: &str, args: &[(&str, &dyn std::fmt::Display)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{ $") {
+        out.push_str(&rest[..start]);
+        let placeholder = &rest[start + 3..];
+        let Some(end) = placeholder.find(" }") else {
+            rest = &rest[start..];
+            break;
+        };
+        let name = &placeholder[..end];
+        match args.iter().find(|(arg, _)| *arg == name) {
+            Some((_, value)) => out.push_str(&value.to_string()),
+            None => out.push_str(&rest[start..start + 3 + end + 2]),
+        }
+        rest = &placeholder[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeholders(template: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = template
+            .split("{ $")
+            .skip(1)
+            .filter_map(|rest| rest.split_once(" }").map(|(name, _)| name))
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn parses_messages_and_skips_comments() {
+        let catalog = parse("# comment\n\n  greeting =  Hello, { $name }!  \nurl = a=b\nbroken\n");
+        assert_eq!(catalog.len(), 2);
+        assert_eq!(catalog["greeting"], "Hello, { $name }!");
+        assert_eq!(catalog["url"], "a=b");
+    }
+
+    #[test]
+    fn formats_placeholders() {
+        assert_eq!(
+            format_message("{ $a } and { $b }", &[("a", &1), ("b", &"two")]),
+            "1 and two"
+        );
+        // Unknown and unterminated placeholders are left as written.
+        assert_eq!(format_message("x { $c } y", &[("a", &1)]), "x { $c } y");
+        assert_eq!(format_message("x { $a", &[("a", &1)]), "x { $a");
+    }
+
+    #[test]
+    fn finds_catalogs_by_language() {
+        assert_eq!(catalog("de"), Some(CATALOGS[1].1));
+        assert_eq!(catalog("de_AT"), Some(CATALOGS[1].1));
+        assert_eq!(catalog("es-MX"), Some(CATALOGS[2].1));
+        assert_eq!(catalog("xx"), None);
+    }
+
+    #[test]
+    fn translations_match_the_english_catalog() {
+        let english = parse(CATALOGS[0].1);
+        for (lang, source) in &CATALOGS[1..] {
+            for (id, template) in parse(source) {
+                let reference = english
+                    .get(id)
+                    .unwrap_or_else(|| panic!("{}: `{}` is not in en.ftl", lang, id));
+                assert_eq!(
+                    placeholders(template),
+                    placeholders(reference),
+                    "{}: placeholders of `{}`",
+                    lang,
+                    id
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Keeps two cursorup runs, e.g. a systemd timer and a manual `update`,
+//! from changing the install dir, cache and state at the same time. The
+//! lock is an advisory `flock` on `~/.local/share/cursorup/lock` (on
+//! Windows, the file opened without sharing), so the system releases it
+//! when the holder exits, however it exits.
+
+use crate::Result;
+use crate::state::State;
+use std::fs::File;
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+/// Name of the lock file in the state dir.
+pub const LOCK_FILE: &str = "lock";
+
+/// Holds the lock until dropped.
+#[derive(Debug)]
+pub struct InstanceLock {
+    _file: File,
+}
+
+pub fn path() -> Result<PathBuf> {
+    Ok(State::dir()?.join(LOCK_FILE))
+}
+
+/// Takes the lock without waiting, failing with "another instance is
+/// running" if another cursorup holds it.
+pub fn acquire() -> Result<InstanceLock> {
+    let path = path()?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = match open_locked(&path) {
+        Ok(Some(file)) => file,
+        Ok(None) => return Err(held_by(&path).into()),
+        Err(e) => return Err(format!("Cannot lock {:?}: {}", path, e).into()),
+    };
+    // The pid is only for the message; the lock is what counts.
+    file.set_len(0)?;
+    file.rewind()?;
+    writeln!(file, "{}", std::process::id())?;
+    Ok(InstanceLock { _file: file })
+}
+
+/// Opens the lock file locked, or `None` if another process holds it.
+#[cfg(unix)]
+fn open_locked(path: &Path) -> std::io::Result<Option<File>> {
+    use std::os::fd::AsRawFd;
+
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    // SAFETY: flock(2) only acts on the descriptor `file` owns.
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+        let error = std::io::Error::last_os_error();
+        if error.kind() == std::io::ErrorKind::WouldBlock {
+            return Ok(None);
+        }
+        return Err(error);
+    }
+    Ok(Some(file))
+}
+
+/// Opens the lock file without sharing, or `None` if another process has
+/// it open.
+#[cfg(windows)]
+fn open_locked(path: &Path) -> std::io::Result<Option<File>> {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    /// `ERROR_SHARING_VIOLATION`.
+    const SHARING_VIOLATION: i32 = 32;
+    match std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .share_mode(0)
+        .open(path)
+    {
+        Ok(file) => Ok(Some(file)),
+        Err(e) if e.raw_os_error() == Some(SHARING_VIOLATION) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// "Another instance is running", with the holder's pid where it can be
+/// read; the Windows lock keeps others from reading the file.
+fn held_by(path: &Path) -> String {
+    let mut holder = String::new();
+    if let Ok(mut file) = File::open(path) {
+        let _ = file.read_to_string(&mut holder);
+    }
+    match holder.trim() {
+        "" => "Another instance of cursorup is running; try again once it has finished".to_string(),
+        pid => format!(
+            "Another instance of cursorup is running (pid {}); try again once it has finished",
+            pid
+        ),
+    }
+}
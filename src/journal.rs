@@ -0,0 +1,238 @@
+//! Progress of an install, persisted once the release is downloaded and
+//! extracted so `cursorup resume` can finish a failed update without
+//! repeating that work.
+
+use crate::config::Config;
+use crate::provenance::Provenance;
+use crate::state::{DownloadSample, State};
+use crate::{Platform, Prepared, Resp, Result, events, fsutil};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+use tokio::fs;
+
+const JOURNAL_FILE: &str = "journal.json";
+const ICON_FILE: &str = "code.png";
+
+/// Install steps in the order they run.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Step {
+    /// Moving the current install into `back/`.
+    Backup,
+    /// Copying the icon and AppImage into the install dir.
+    Place,
+    /// The `current` link, CLI wrapper and desktop entry.
+    Integrate,
+    /// Sidecars, manifest, state and history.
+    Record,
+}
+
+impl Step {
+    pub const ALL: [Step; 4] = [Step::Backup, Step::Place, Step::Integrate, Step::Record];
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Step::Backup => "backup",
+            Step::Place => "place",
+            Step::Integrate => "desktop integration",
+            Step::Record => "record",
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Journal {
+    pub id: String,
+    /// Seconds since the Unix epoch.
+    pub started_at: u64,
+    pub previous_version: Option<String>,
+    pub platform: Platform,
+    pub metadata: Resp,
+    pub provenance: Provenance,
+    pub download: Option<DownloadSample>,
+    /// Installing with `--no-backup`: the previous version is deleted
+    /// rather than backed up.
+    #[serde(default)]
+    pub no_backup: bool,
+    pub completed: Vec<Step>,
+}
+
+pub(crate) fn root() -> Result<PathBuf> {
+    Ok(State::dir()?.join("journal"))
+}
+
+impl Journal {
+    /// Starts a journal for `prepared`, keeping a copy of its extracted icon
+    /// so a resume doesn't need to extract the AppImage again.
+    pub(crate) async fn begin(
+        config: &Config,
+        prepared: &Prepared,
+        previous_version: Option<String>,
+        no_backup: bool,
+    ) -> Result<Journal> {
+        let started_at = crate::state::now();
+        let journal = Journal {
+            id: started_at.to_string(),
+            started_at,
+            previous_version,
+            platform: config.platform,
+            metadata: prepared.metadata.clone(),
+            provenance: prepared.provenance.clone(),
+            download: prepared.download.clone(),
+            no_backup,
+            completed: Vec::new(),
+        };
+        fs::create_dir_all(journal.dir()?).await?;
+        fs::copy(&prepared.icon, journal.dir()?.join(ICON_FILE)).await?;
+        journal.save().await?;
+        Ok(journal)
+    }
+
+    pub fn dir(&self) -> Result<PathBuf> {
+        Ok(root()?.join(&self.id))
+    }
+
+    pub fn is_done(&self, step: Step) -> bool {
+        self.completed.contains(&step)
+    }
+
+    /// The step a resume would start with.
+    pub fn next_step(&self) -> Option<Step> {
+        Step::ALL.into_iter().find(|step| !self.is_done(*step))
+    }
+
+    pub async fn complete(&mut self, step: Step) -> Result<()> {
+        self.completed.push(step);
+        self.save().await
+    }
+
+    async fn save(&self) -> Result<()> {
+        fsutil::write_atomic(
+            &self.dir()?.join(JOURNAL_FILE),
+            serde_json::to_string_pretty(self)?,
+        )
+        .await
+    }
+
+    /// Adds the resume hint to the error of a failed step.
+    pub fn interrupted(&self, e: Box<dyn std::error::Error>) -> Box<dyn std::error::Error> {
+        match self.next_step() {
+            Some(step) => format!(
+                "{}\nRun `cursorup resume {}` to retry from the {} step.",
+                e, self.id, step
+            )
+            .into(),
+            None => e,
+        }
+    }
+}
+
+/// Removes all journals once an install has completed; older interrupted
+/// updates are obsolete at that point.
+pub async fn clear() -> Result<()> {
+    match fs::remove_dir_all(root()?).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// The journal with `id`, or the most recent one.
+pub async fn load(id: Option<&str>) -> Result<Option<Journal>> {
+    let id = match id {
+        Some(id) => id.to_string(),
+        None => {
+            let mut entries = match fs::read_dir(root()?).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+                Err(e) => return Err(e.into()),
+            };
+            let mut ids = Vec::new();
+            while let Some(entry) = entries.next_entry().await? {
+                if let Ok(id) = entry.file_name().to_string_lossy().parse::<u64>() {
+                    ids.push(id);
+                }
+            }
+            match ids.into_iter().max() {
+                Some(id) => id.to_string(),
+                None => return Ok(None),
+            }
+        }
+    };
+    let path = root()?.join(&id).join(JOURNAL_FILE);
+    match fs::read_to_string(&path).await {
+        Ok(contents) => {
+            Ok(Some(serde_json::from_str(&contents).map_err(|e| {
+                format!("{}: invalid journal: {}", path.display(), e)
+            })?))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Finishes the interrupted update `id` (or the latest one) from its first
+/// incomplete step, using the cached download.
+pub async fn resume(config: &Config, id: Option<&str>, verbose: bool) -> Result<()> {
+    let mut journal = load(id).await?.ok_or_else(|| match id {
+        Some(id) => format!("No interrupted update with id {}", id),
+        None => "No interrupted update to resume".to_string(),
+    })?;
+    if journal.platform != config.platform {
+        return Err(format!(
+            "Update {} was for {}, but the config now selects {}",
+            journal.id, journal.platform, config.platform
+        )
+        .into());
+    }
+    let download = crate::cache::artifact_path(
+        journal.platform,
+        &journal.metadata.version,
+        &journal.provenance.file,
+    )?;
+    let appimage = match &journal.provenance.transformed {
+        Some(transformed) => {
+            let path = crate::transform::output_path(&download);
+            crate::transform::verify(&path, transformed).await?;
+            path
+        }
+        None => {
+            crate::cache::verify(&download).await?;
+            download
+        }
+    };
+    events::log(format!(
+        "Resuming update {} to Cursor {} from the {} step",
+        journal.id,
+        journal.metadata.version,
+        journal.next_step().unwrap_or(Step::Record)
+    ));
+
+    // Once a step is done, the install was already agreed to be replaced.
+    if journal.completed.is_empty() && !journal.no_backup {
+        crate::confirm_replace(
+            journal.previous_version.as_deref(),
+            &journal.metadata.version,
+        )
+        .await?;
+    }
+    let prepared = Prepared {
+        metadata: journal.metadata.clone(),
+        provenance: journal.provenance.clone(),
+        download: journal.download.clone(),
+        appimage,
+        icon: journal.dir()?.join(ICON_FILE),
+    };
+    let appimage = match crate::activate(config, prepared, &mut journal, false, verbose).await {
+        Ok(appimage) => appimage,
+        Err(e) => {
+            crate::history::record_failure(journal.previous_version.clone(), e.as_ref()).await;
+            return Err(journal.interrupted(e));
+        }
+    };
+    clear().await?;
+    crate::stage::discard(config).await?;
+    crate::smoke::verify_or_roll_back(config, &appimage, verbose).await
+}
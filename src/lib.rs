@@ -1,203 +1,1626 @@
-use serde::Deserialize;
-use std::io::{Write, stdout};
-use std::os::unix::fs::PermissionsExt;
+use config::Config;
+use events::InstallEvent;
+pub use platform::{Platform, ReleaseTrack};
+use serde::{Deserialize, Serialize};
+use std::io::{IsTerminal, stdin};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use tokio::fs;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufWriter};
 use tokio::process::Command;
+pub use version::Version;
+
+pub mod adopt;
+pub mod backup;
+pub mod cache;
+pub mod changelog;
+pub mod check;
+pub mod checksums;
+pub mod cleanup;
+pub mod config;
+pub mod daemon;
+pub mod diff;
+pub mod du;
+pub mod environment;
+pub mod events;
+pub mod execution;
+pub mod export;
+pub mod fsutil;
+pub mod github;
+pub mod history;
+pub mod http;
+pub mod i18n;
+pub mod instance;
+pub mod journal;
+pub mod lock;
+pub mod manifest;
+pub mod matrix;
+pub mod metered;
+pub mod metrics;
+pub mod minisign;
+pub mod output;
+pub mod plan;
+pub mod platform;
+pub mod preflight;
+pub mod profiles;
+mod progress;
+pub mod provenance;
+pub mod report;
+pub mod restart;
+pub mod rollback;
+pub mod rpc;
+mod segmented;
+pub mod selfupdate;
+pub mod shim;
+pub mod smoke;
+pub mod stage;
+pub mod state;
+pub mod status;
+pub mod system;
+pub mod targets;
+pub mod telemetry;
+pub mod transform;
+pub mod urls;
+pub mod version;
+pub mod watch;
+pub mod wizard;
+pub mod wrapper;
 
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
+/// Cursor's release API; `api_url` in the config replaces it.
+pub const API_URL: &str = "https://cursor.com/api/download";
+
+pub(crate) fn home_dir() -> Result<PathBuf> {
+    Ok(PathBuf::from(std::env::var("HOME")?))
+}
+
 pub mod tmpdir {
     use std::path::PathBuf;
+    use std::sync::atomic::{AtomicU32, Ordering};
 
+    /// A private scratch directory, removed when dropped. Each one gets a
+    /// fresh name, so nested users don't delete each other's files and other
+    /// users can neither create it in advance nor look inside.
     pub struct TmpDir {
         pub path: PathBuf,
     }
 
-    impl Default for TmpDir {
-        fn default() -> Self {
-            let path = std::env::temp_dir().join("cursorup_temp");
-            Self { path }
+    impl TmpDir {
+        pub fn new() -> std::io::Result<Self> {
+            static COUNTER: AtomicU32 = AtomicU32::new(0);
+            loop {
+                let nanos = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.subsec_nanos())
+                    .unwrap_or_default();
+                let path = std::env::temp_dir().join(format!(
+                    "cursorup-{}-{}-{}",
+                    std::process::id(),
+                    COUNTER.fetch_add(1, Ordering::Relaxed),
+                    nanos
+                ));
+                // Fails rather than reusing a directory someone else made.
+                match create_private(&path) {
+                    Ok(()) => return Ok(Self { path }),
+                    Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    }
+
+    /// Creates `path`, readable only by this user where modes exist.
+    fn create_private(path: &std::path::Path) -> std::io::Result<()> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::DirBuilderExt;
+            std::fs::DirBuilder::new().mode(0o700).create(path)
         }
+        #[cfg(not(unix))]
+        std::fs::DirBuilder::new().create(path)
     }
 
     impl Drop for TmpDir {
         fn drop(&mut self) {
-            println!("Cleaning up temporary directory: {:?}", self.path);
+            crate::events::log(format!("Cleaning up temporary directory: {:?}", self.path));
             let _ = std::fs::remove_dir_all(&self.path);
         }
     }
 }
 
-#[derive(Deserialize, Debug)]
+/// Release metadata returned by the Cursor download API. Unknown fields are
+/// ignored so additions on the API side don't break parsing.
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Resp {
     pub version: String,
     #[serde(rename = "downloadUrl")]
     pub download_url: String,
     #[serde(rename = "commitSha")]
     pub commit_sha: String,
-    #[serde(rename = "rehUrl")]
+    #[serde(rename = "rehUrl", default)]
     pub reh_url: String,
+    /// SHA-256 of the AppImage, should the API start publishing it.
+    #[serde(default, alias = "sha256Hash", skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// Failure to obtain release metadata, separating connectivity problems from
+/// responses whose shape we don't understand.
+#[derive(Debug)]
+pub enum ApiError {
+    Network {
+        url: String,
+        source: reqwest::Error,
+    },
+    Status {
+        url: String,
+        status: reqwest::StatusCode,
+    },
+    UnexpectedResponse {
+        url: String,
+        source: serde_json::Error,
+    },
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::Network { url, source } => {
+                write!(f, "Could not reach the Cursor API at {}: {}", url, source)
+            }
+            ApiError::Status { url, status } => {
+                write!(f, "Cursor API at {} responded with {}", url, status)
+            }
+            ApiError::UnexpectedResponse { url, source } => write!(
+                f,
+                "Cursor API returned an unexpected response from {}: {}",
+                url, source
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ApiError::Network { source, .. } => Some(source),
+            ApiError::Status { .. } => None,
+            ApiError::UnexpectedResponse { source, .. } => Some(source),
+        }
+    }
+}
+
+/// Files every extracted Cursor AppImage contains, checked before anything
+/// is copied out of the extracted tree.
+const EXTRACTED_FILES: [&str; 2] = ["code.png", "usr/share/cursor/resources/app/package.json"];
+
+/// Failure to extract the downloaded AppImage, after one retry.
+#[derive(Debug)]
+pub enum ExtractionError {
+    /// The AppImage runtime exited with an error.
+    Failed { status: String, stderr: String },
+    /// The runtime reported success, but expected files are missing or
+    /// empty, e.g. because the disk filled up or the run was interrupted.
+    Incomplete { dir: PathBuf, missing: Vec<String> },
+}
+
+impl std::fmt::Display for ExtractionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtractionError::Failed { status, stderr } => {
+                write!(f, "AppImage extraction failed ({}): {}", status, stderr)
+            }
+            ExtractionError::Incomplete { dir, missing } => write!(
+                f,
+                "AppImage extraction into {:?} is incomplete, missing or empty: {} (is the disk full?)",
+                dir,
+                missing.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ExtractionError {}
+
+impl std::fmt::Display for Resp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Version:  {}", self.version)?;
+        writeln!(f, "Commit:   {}", self.commit_sha)?;
+        writeln!(f, "Download: {}", self.download_url)?;
+        write!(f, "REH:      {}", self.reh_url)
+    }
+}
+
+/// Per-run choices that are not part of the persistent config.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct InstallOptions {
+    /// Install the exact build with this commit SHA instead of the latest release.
+    pub commit: Option<String>,
+    /// Version of the build named by `commit`, needed for its file name.
+    /// Without `commit`, the version to install, e.g. `1.2.3` or `1.2.x`;
+    /// see `resolve_version`.
+    pub version: Option<String>,
+    /// Install the already cached artifact for the resolved version instead
+    /// of downloading it again.
+    pub skip_download: bool,
+    /// Download and prepare the release in the install dir's staging area
+    /// instead of installing it; `cursorup commit` finishes the job.
+    pub stage: bool,
+    /// Show a diff of the desktop entry before rewriting it.
+    pub verbose: bool,
+    /// SHA-256 the download must have, taking precedence over any
+    /// published checksum.
+    pub sha256: Option<String>,
+    /// Components to install together; empty installs the desktop app as
+    /// configured. See `targets`.
+    pub targets: Vec<targets::Target>,
+    /// Download afresh even when the cache has the build, and remove the
+    /// download from the cache once it is installed.
+    pub no_cache: bool,
+    /// Delete the previous version once the new one is in place instead of
+    /// keeping it in `back/`; nothing is left to roll back to.
+    pub no_backup: bool,
+    /// Seconds the install or update may take before it is cancelled; what
+    /// it got done is kept for the next run or `cursorup resume`.
+    pub max_duration: Option<u64>,
+}
+
+/// Metadata for the build to install: the latest release of the configured
+/// track, the build pinned with `--commit`, or the one matching `--version`.
+pub async fn resolve_metadata(
+    client: &reqwest::Client,
+    config: &Config,
+    options: &InstallOptions,
+) -> Result<Resp> {
+    let Some(commit_sha) = &options.commit else {
+        return match &options.version {
+            Some(pattern) => resolve_version(client, config, pattern).await,
+            None => {
+                fetch_metadata(
+                    client,
+                    &config.api_url,
+                    config.platform,
+                    config.release_track,
+                )
+                .await
+            }
+        };
+    };
+    if commit_sha.len() != 40 || !commit_sha.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("`{}` is not a full 40-character commit SHA", commit_sha).into());
+    }
+    let commit_sha = commit_sha.to_ascii_lowercase();
+
+    let version = match &options.version {
+        Some(version) => version.clone(),
+        None => {
+            let latest = fetch_metadata(
+                client,
+                &config.api_url,
+                config.platform,
+                config.release_track,
+            )
+            .await?;
+            if latest.commit_sha != commit_sha {
+                return Err(format!(
+                    "Commit {} is not the latest {} build; pass --version to name its version",
+                    commit_sha, config.release_track
+                )
+                .into());
+            }
+            latest.version
+        }
+    };
+
+    Ok(Resp {
+        download_url: urls::appimage_url(&commit_sha, &version, config.platform),
+        reh_url: urls::reh_url(&commit_sha, config.platform),
+        sha256: None,
+        commit_sha,
+        version,
+    })
+}
+
+/// The newest known build whose version matches `pattern` (see
+/// `version::matches`). Cursor publishes no index of old releases and its
+/// download URLs need the commit, so the builds known are the latest of
+/// each release track plus those recorded in this machine's state and
+/// update history. Other versions need `--commit`.
+pub async fn resolve_version(
+    client: &reqwest::Client,
+    config: &Config,
+    pattern: &str,
+) -> Result<Resp> {
+    let mut known = Vec::new();
+    for track in ReleaseTrack::ALL {
+        match fetch_metadata(client, &config.api_url, config.platform, track).await {
+            Ok(metadata) => known.push(metadata),
+            Err(e) => events::log(format!(
+                "Could not fetch the latest build of the {} track: {}",
+                track, e
+            )),
+        }
+    }
+    let mut recorded: Vec<(String, String)> = history::load(None)
+        .await?
+        .0
+        .into_iter()
+        .filter_map(|entry| Some((entry.to_version?, entry.commit_sha?)))
+        .collect();
+    if let Some(state) = state::State::load().await?
+        && state.platform == config.platform
+    {
+        recorded.push((state.version, state.commit_sha));
+    }
+    for (version, commit_sha) in recorded {
+        if commit_sha.len() == 40 {
+            known.push(Resp {
+                download_url: urls::appimage_url(&commit_sha, &version, config.platform),
+                reh_url: urls::reh_url(&commit_sha, config.platform),
+                sha256: None,
+                commit_sha,
+                version,
+            });
+        }
+    }
+
+    known
+        .into_iter()
+        .filter(|metadata| version::matches(pattern, &metadata.version))
+        .max_by(|a, b| {
+            version::compare(&a.version, &b.version).unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .ok_or_else(|| {
+            format!(
+                "No known build matches version {}: it is not the latest of a release track or in this machine's history; pass --commit <sha> with the exact version",
+                pattern
+            )
+            .into()
+        })
 }
 
-async fn fetch_metadata() -> Result<Resp> {
-    let url = "https://cursor.com/api/download?platform=linux-x64&releaseTrack=stable";
-    let resp = reqwest::get(url).await?.json::<Resp>().await?;
+/// Asks the API at `api_url` (`config.api_url`; Cursor's or a mirror's) for
+/// the latest build of `platform` on `release_track`.
+#[tracing::instrument(name = "fetch", skip_all, fields(%platform, %release_track))]
+pub async fn fetch_metadata(
+    client: &reqwest::Client,
+    api_url: &str,
+    platform: Platform,
+    release_track: ReleaseTrack,
+) -> Result<Resp> {
+    let url = urls::metadata_url(api_url, platform, release_track);
+    let network = |source| ApiError::Network {
+        url: url.clone(),
+        source,
+    };
+    let started = Instant::now();
+    let response = client.get(&url).send().await.map_err(network)?;
+    tracing::debug!(
+        %url,
+        status = %response.status(),
+        headers_ms = started.elapsed().as_millis() as u64,
+        "metadata response"
+    );
+    if !response.status().is_success() {
+        return Err(ApiError::Status {
+            url,
+            status: response.status(),
+        }
+        .into());
+    }
+    let body = response.text().await.map_err(network)?;
+    tracing::debug!(
+        // After decompression; reqwest drops `Content-Encoding` once it
+        // has undone it.
+        bytes = body.len(),
+        total_ms = started.elapsed().as_millis() as u64,
+        "metadata received"
+    );
+    let resp = serde_json::from_str::<Resp>(&body).map_err(|source| {
+        tracing::debug!(%url, %body, "unparseable metadata response");
+        ApiError::UnexpectedResponse {
+            url: url.clone(),
+            source,
+        }
+    })?;
+    if let Err(e) = cache::store_metadata(platform, release_track, &resp).await {
+        tracing::debug!(%e, "could not cache metadata");
+    }
     Ok(resp)
 }
 
-async fn download_file(url: &str, dest_path: &Path) -> Result<()> {
-    println!("Downloading from {}", url);
-    let mut response = reqwest::get(url).await?;
+/// Release metadata as returned by `latest_version`.
+pub type ReleaseMetadata = Resp;
+
+/// Minimum seconds between two fetches by `latest_version`, however short
+/// `check_interval` is.
+const LATEST_VERSION_MIN_INTERVAL: u64 = 60;
+
+/// The latest release of the configured platform and track, for prompts and
+/// editors that only need to know what is current. The metadata cached by
+/// the last fetch is used while it is younger than `check_interval` (and
+/// at least a minute); should a refetch fail, a stale cache is returned
+/// instead of the error.
+pub async fn latest_version(config: &Config) -> Result<ReleaseMetadata> {
+    let cached = cache::load_metadata(config.platform, config.release_track).await?;
+    let max_age = config.check_interval.max(LATEST_VERSION_MIN_INTERVAL);
+    let age = |cached: &cache::CachedMetadata| state::now().saturating_sub(cached.fetched_at);
+    if let Some(cached) = &cached
+        && age(cached) < max_age
+    {
+        return Ok(cached.metadata.clone());
+    }
+    let client = http::client(config)?;
+    let fetched = fetch_metadata(
+        &client,
+        &config.api_url,
+        config.platform,
+        config.release_track,
+    )
+    .await;
+    match (fetched, cached) {
+        (Ok(metadata), _) => Ok(metadata),
+        (Err(e), Some(cached)) => {
+            events::log(format!(
+                "{}; using the release metadata fetched {}s ago",
+                e,
+                age(&cached)
+            ));
+            Ok(cached.metadata)
+        }
+        (Err(e), None) => Err(e),
+    }
+}
+
+/// `latest_version` for callers without an async runtime. It starts its own,
+/// so it must not be called from async code.
+pub fn latest_version_blocking(config: &Config) -> Result<ReleaseMetadata> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(latest_version(config))
+}
+
+/// Outcome of a finished download.
+#[derive(Debug)]
+pub struct Download {
+    pub bytes: u64,
+    pub expected_bytes: u64,
+    pub sha256: String,
+    pub elapsed: Duration,
+}
+
+/// Write buffer for downloads: about a quarter second of the median
+/// throughput seen before, so fast links make fewer, larger writes.
+pub(crate) fn download_buffer_size(median_bytes_per_second: Option<u64>) -> usize {
+    const MIN: u64 = 64 * 1024;
+    const MAX: u64 = 8 * 1024 * 1024;
+    median_bytes_per_second.map_or(MIN, |speed| (speed / 4).clamp(MIN, MAX)) as usize
+}
+
+/// Downloads `url` to `dest_path` by way of `<dest_path>.part`, which is
+/// kept when the transfer breaks off. The next attempt resumes it with a
+/// Range request, provided the server still has the same file (its ETag or
+/// Last-Modified, saved next to the part, is sent as `If-Range`); otherwise
+/// it starts over. The file only takes its final name once it has the
+/// announced size.
+///
+/// With `connections` above 1 and nothing to resume, the file is fetched in
+/// that many ranges at once (see [`segmented`]) when the server allows it.
+/// `limit_rate` caps the speed either way.
+#[tracing::instrument(name = "download", skip_all, fields(%url))]
+pub(crate) async fn download_file(
+    client: &reqwest::Client,
+    url: &str,
+    dest_path: &Path,
+    buffer_size: usize,
+    config: &Config,
+) -> Result<Download> {
+    let started = Instant::now();
+    let part_path = fsutil::with_suffix(dest_path, ".part");
+    let validator_path = fsutil::with_suffix(dest_path, ".part.validator");
+    let mut resume = resume_point(&part_path, &validator_path).await;
+    if resume.is_none()
+        && config.connections > 1
+        && let Some(download) = segmented::download(
+            client,
+            url,
+            dest_path,
+            config.connections,
+            buffer_size,
+            config.limit_rate,
+        )
+        .await?
+    {
+        return Ok(download);
+    }
+    let mut response = download_request(client, url, resume.as_ref()).await?;
+    if resume.is_some() && response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+        resume = None;
+        response = download_request(client, url, None).await?;
+    }
 
     if !response.status().is_success() {
         return Err(format!("Download failed with status: {}", response.status()).into());
     }
 
-    let total_size = response
-        .content_length()
-        .ok_or("Failed to get content length")?;
+    let resumed_at = match (&resume, response.status()) {
+        (Some((offset, _)), reqwest::StatusCode::PARTIAL_CONTENT) => *offset,
+        _ => 0,
+    };
+    let total_size = if resumed_at > 0 {
+        match content_range(&response) {
+            Some((start, total)) if start == resumed_at => total,
+            _ => return Err(format!("Unexpected Content-Range resuming {}", url).into()),
+        }
+    } else {
+        response
+            .content_length()
+            .ok_or("Failed to get content length")?
+    };
+    events::emit(InstallEvent::DownloadStarted {
+        url: url.to_string(),
+        total_bytes: total_size,
+    });
 
-    let mut file = fs::File::create(dest_path).await?;
-    let mut downloaded: u64 = 0;
+    let mut hasher = ring::digest::Context::new(&ring::digest::SHA256);
+    let file = if resumed_at > 0 {
+        events::log(format!(
+            "Resuming the download at {} of {} bytes",
+            resumed_at, total_size
+        ));
+        let mut part = fs::File::open(&part_path).await?;
+        let mut buffer = vec![0; buffer_size];
+        loop {
+            let read = part.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        fs::OpenOptions::new().append(true).open(&part_path).await?
+    } else {
+        let file = fs::File::create(&part_path).await?;
+        let validator = [reqwest::header::ETAG, reqwest::header::LAST_MODIFIED]
+            .iter()
+            .find_map(|name| response.headers().get(name)?.to_str().ok());
+        match validator {
+            Some(validator) => fsutil::write_atomic(&validator_path, validator).await?,
+            None => match fs::remove_file(&validator_path).await {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            },
+        }
+        file
+    };
+    fsutil::preallocate(&file, &part_path, total_size)?;
+    let mut file = BufWriter::with_capacity(buffer_size, file);
+    let mut downloaded: u64 = resumed_at;
+    let mut throttle = config.limit_rate.map(http::Throttle::new);
 
-    while let Some(chunk) = response.chunk().await? {
+    let mut interrupted = None;
+    loop {
+        let chunk = match response.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) => {
+                interrupted = Some(e);
+                break;
+            }
+        };
         file.write_all(&chunk).await?;
+        hasher.update(&chunk);
         downloaded += chunk.len() as u64;
+        events::emit(InstallEvent::DownloadProgress {
+            downloaded_bytes: downloaded,
+            total_bytes: total_size,
+        });
+        if let Some(throttle) = &mut throttle {
+            throttle.pace(chunk.len()).await;
+        }
+    }
+    file.flush().await?;
+    if let Some(e) = interrupted {
+        return Err(format!(
+            "Download interrupted at {} of {} bytes ({}); run again to resume",
+            downloaded, total_size, e
+        )
+        .into());
+    }
+    if downloaded != total_size {
+        if downloaded > total_size {
+            let _ = fs::remove_file(&part_path).await;
+        }
+        return Err(format!(
+            "Download of {} ended at {} of {} bytes",
+            url, downloaded, total_size
+        )
+        .into());
+    }
+    drop(file);
+    fs::rename(&part_path, dest_path).await?;
+    let _ = fs::remove_file(&validator_path).await;
 
-        let percentage = (downloaded as f64 / total_size as f64) * 100.0;
+    events::emit(InstallEvent::DownloadFinished {
+        path: dest_path.to_path_buf(),
+        bytes: downloaded,
+    });
 
-        print!(
-            "\rDownloading... {:.2}% ({:.2}MB / {:.2}MB)",
-            percentage,
-            downloaded as f64 / 1_048_576.0,
-            total_size as f64 / 1_048_576.0
-        );
-        stdout().flush()?;
+    Ok(Download {
+        bytes: downloaded,
+        expected_bytes: total_size,
+        sha256: provenance::hex(hasher.finish().as_ref()),
+        elapsed: started.elapsed(),
+    })
+}
+
+/// Where a kept partial download can be resumed from: its size and the
+/// validator of the file it is part of. Parts without one start over.
+async fn resume_point(part_path: &Path, validator_path: &Path) -> Option<(u64, String)> {
+    let len = fs::metadata(part_path).await.ok()?.len();
+    let validator = fs::read_to_string(validator_path).await.ok()?;
+    (len > 0).then_some((len, validator))
+}
+
+async fn download_request(
+    client: &reqwest::Client,
+    url: &str,
+    resume: Option<&(u64, String)>,
+) -> reqwest::Result<reqwest::Response> {
+    let mut request = http::get_artifact(client, url);
+    if let Some((offset, validator)) = resume {
+        request = request
+            .header(reqwest::header::RANGE, format!("bytes={}-", offset))
+            .header(reqwest::header::IF_RANGE, validator);
     }
+    request.send().await
+}
 
-    println!();
-    println!("Download completed successfully to {:?}", dest_path);
+/// Start and total size from a `Content-Range: bytes <start>-<end>/<total>`
+/// header.
+fn content_range(response: &reqwest::Response) -> Option<(u64, u64)> {
+    parse_content_range(
+        response
+            .headers()
+            .get(reqwest::header::CONTENT_RANGE)?
+            .to_str()
+            .ok()?,
+    )
+}
 
-    Ok(())
+fn parse_content_range(value: &str) -> Option<(u64, u64)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, _) = range.split_once('-')?;
+    Some((start.parse().ok()?, total.parse().ok()?))
+}
+
+/// A verified release whose files are ready to be placed into the install
+/// dir, either straight after downloading or from a staged update.
+pub(crate) struct Prepared {
+    pub metadata: Resp,
+    pub provenance: provenance::Provenance,
+    /// Throughput of the download, if one happened.
+    pub download: Option<state::DownloadSample>,
+    pub appimage: PathBuf,
+    pub icon: PathBuf,
 }
 
-async fn install(
+/// Makes the AppImage executable and extracts it into `tmp_dir`, returning
+/// the extracted tree. A failed or incomplete extraction is retried once
+/// before an `ExtractionError` is returned.
+#[tracing::instrument(name = "extract", skip_all, fields(path = ?appimage_path))]
+pub(crate) async fn extract(
     appimage_path: &Path,
-    _version: &str, // version is not used for the destination path anymore
     tmp_dir: &Path,
-) -> Result<()> {
-    println!("Starting installation...");
-
-    let mut perms = fs::metadata(appimage_path).await?.permissions();
-    perms.set_mode(0o755); // rwxr-xr-x
-    fs::set_permissions(appimage_path, perms).await?;
-    println!("Granted execute permissions to {:?}", appimage_path);
+    mode: u32,
+    execution: execution::ExecutionMode,
+) -> Result<PathBuf> {
+    fsutil::set_mode(appimage_path, mode).await?;
+    events::log(format!(
+        "Granted execute permissions to {:?}",
+        appimage_path
+    ));
 
     // --appimage-extract
-    println!("Extracting AppImage...");
-    let output = Command::new(appimage_path)
-        .arg("--appimage-extract")
-        .current_dir(tmp_dir)
-        .output()
-        .await?;
+    events::emit(InstallEvent::Extracting {
+        path: appimage_path.to_path_buf(),
+    });
+    match extract_once(appimage_path, tmp_dir, execution).await? {
+        Ok(extracted_dir) => Ok(extracted_dir),
+        Err(e) => {
+            events::log(format!("{}; retrying once", e));
+            Ok(extract_once(appimage_path, tmp_dir, execution).await??)
+        }
+    }
+}
+
+/// One `--appimage-extract` run into a clean `squashfs-root`, checked for
+/// `EXTRACTED_FILES`. An incomplete tree is removed again.
+async fn extract_once(
+    appimage_path: &Path,
+    tmp_dir: &Path,
+    execution: execution::ExecutionMode,
+) -> Result<std::result::Result<PathBuf, ExtractionError>> {
+    let extracted_dir = tmp_dir.join("squashfs-root");
+    match fs::remove_dir_all(&extracted_dir).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+
+    let run = |execution: execution::ExecutionMode| {
+        let mut command = Command::new(appimage_path);
+        command.arg("--appimage-extract").current_dir(tmp_dir);
+        execution.configure(&mut command);
+        command.output()
+    };
+    let mut output = run(execution).await?;
+    if !output.status.success()
+        && execution == execution::ExecutionMode::Auto
+        && execution.resolve() == execution::ExecutionMode::Direct
+        && execution::is_fuse_error(&String::from_utf8_lossy(&output.stderr))
+    {
+        events::log("AppImage runtime could not use FUSE; retrying in extract-and-run mode");
+        output = run(execution::ExecutionMode::ExtractAndRun).await?;
+    }
 
     if !output.status.success() {
-        return Err(format!(
-            "AppImage extraction failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        )
-        .into());
+        return Ok(Err(ExtractionError::Failed {
+            status: output.status.to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        }));
     }
 
-    let extracted_dir = tmp_dir.join("squashfs-root");
-    println!("Extracted to {:?}", extracted_dir);
+    let mut missing = Vec::new();
+    for file in EXTRACTED_FILES {
+        let complete = fs::metadata(extracted_dir.join(file))
+            .await
+            .is_ok_and(|metadata| metadata.len() > 0);
+        if !complete {
+            missing.push(file.to_string());
+        }
+    }
+    if !missing.is_empty() {
+        let _ = fs::remove_dir_all(&extracted_dir).await;
+        return Ok(Err(ExtractionError::Incomplete {
+            dir: extracted_dir,
+            missing,
+        }));
+    }
+    events::log(format!("Extracted to {:?}", extracted_dir));
+    Ok(Ok(extracted_dir))
+}
+
+/// Backs up the current install and puts the prepared files in its place,
+/// then records the result. With `relocate` the prepared files are moved
+/// rather than copied, which is what makes committing a staged update fast.
+/// Steps already completed in `journal` are skipped.
+#[tracing::instrument(name = "install", skip_all, fields(version = %prepared.metadata.version))]
+pub(crate) async fn activate(
+    config: &Config,
+    prepared: Prepared,
+    journal: &mut journal::Journal,
+    relocate: bool,
+    verbose: bool,
+) -> Result<PathBuf> {
+    use journal::Step;
+
+    events::log("Starting installation...");
+    let Prepared {
+        metadata,
+        provenance,
+        download,
+        appimage,
+        icon,
+    } = prepared;
+    // Returns the verb for the log line.
+    let place = |from: PathBuf, to: PathBuf| async move {
+        if relocate {
+            fs::rename(&from, &to).await?;
+            Ok("Moved")
+        } else if config.hardlinks {
+            fsutil::link_or_copy(&from, &to)
+                .await
+                .map(fsutil::Placed::verb)
+        } else {
+            fs::copy(&from, &to).await?;
+            Ok::<_, Box<dyn std::error::Error>>("Copied")
+        }
+    };
 
-    let home_dir = PathBuf::from(std::env::var("HOME")?);
-    let dest_dir = home_dir.join("Applications").join("cursor");
+    let dest_dir = config.install_dir.clone();
+    if config.system_install {
+        system::prepare(config).await?;
+    }
     fs::create_dir_all(&dest_dir).await?;
-    println!("Ensured destination directory exists: {:?}", dest_dir);
+    events::log(format!(
+        "Ensured destination directory exists: {:?}",
+        dest_dir
+    ));
 
-    back_file(dest_dir.clone()).await?;
+    if !journal.is_done(Step::Backup) {
+        if journal.no_backup {
+            events::log("Skipping the backup (--no-backup)");
+        } else {
+            if let Some(installed) = status::installed_appimage(&dest_dir).await?
+                && manifest::modified_externally(&dest_dir, &installed).await?
+            {
+                tracing::warn!("{}", manifest::modified_warning(&installed));
+            }
+            back_file(dest_dir.clone(), config.compress_backups).await?;
+        }
+        journal.complete(Step::Backup).await?;
+    }
 
+    let mode = fsutil::executable_mode(config.file_mode);
     let icon_dest_path = dest_dir.join("code.png");
-    let icon_source_path = extracted_dir.join("code.png");
-    fs::copy(&icon_source_path, &icon_dest_path).await?;
-    println!("Copied icon to {:?}", icon_dest_path);
-
-    let appimage_dest_path = dest_dir.join(appimage_path.file_name().unwrap());
-    fs::copy(appimage_path, &appimage_dest_path).await?;
-    println!("Copied AppImage to {:?}", appimage_dest_path);
-    echo_2_desktop(&appimage_dest_path, &icon_dest_path).await?;
-    println!("Installation complete!");
+    let appimage_dest_path = dest_dir.join(&provenance.file);
+    if !journal.is_done(Step::Place) {
+        match &config.icon {
+            Some(custom) => {
+                fs::copy(custom, &icon_dest_path).await?;
+                events::log(format!("Copied {:?} to {:?}", custom, icon_dest_path));
+            }
+            None => {
+                let verb = place(icon, icon_dest_path.clone()).await?;
+                events::log(format!("{} icon to {:?}", verb, icon_dest_path));
+            }
+        }
+        fsutil::set_mode(&icon_dest_path, fsutil::data_mode(config.file_mode)).await?;
+
+        events::emit(InstallEvent::Copying {
+            path: appimage_dest_path.clone(),
+        });
+        let verb = if journal.no_backup {
+            // The previous install is still in place and in use; the new
+            // AppImage replaces it in one rename or not at all.
+            let part = fsutil::with_suffix(&appimage_dest_path, ".part");
+            let verb = place(appimage, part.clone()).await?;
+            fsutil::set_mode(&part, mode).await?;
+            fs::rename(&part, &appimage_dest_path).await?;
+            verb
+        } else {
+            let verb = place(appimage, appimage_dest_path.clone()).await?;
+            fsutil::set_mode(&appimage_dest_path, mode).await?;
+            verb
+        };
+        events::log(format!("{} AppImage to {:?}", verb, appimage_dest_path));
+        journal.complete(Step::Place).await?;
+    }
+
+    if !journal.is_done(Step::Integrate) {
+        wrapper::link_current(&dest_dir, &appimage_dest_path).await?;
+        if config.cli_wrapper {
+            wrapper::write_wrapper(&dest_dir, mode, &config.launch_args()).await?;
+            wrapper::ensure_on_path().await?;
+        }
+        // The system entry points at the `current` link; `system::prepare`
+        // keeps it up to date.
+        if config.desktop_entry && !config.system_install {
+            echo_2_desktop(
+                &config.desktop_name,
+                &appimage_dest_path,
+                &icon_dest_path,
+                &config.launch_args(),
+                verbose,
+            )
+            .await?;
+        }
+        profiles::refresh(config).await?;
+        journal.complete(Step::Integrate).await?;
+    }
+    events::emit(InstallEvent::Installed {
+        version: metadata.version.clone(),
+        path: appimage_dest_path.clone(),
+    });
+
+    if journal.no_backup {
+        let keep = [
+            appimage_dest_path.clone(),
+            provenance::checksum_path(&appimage_dest_path),
+            icon_dest_path.clone(),
+        ];
+        remove_replaced(&dest_dir, &keep).await?;
+    }
+    provenance::write_sidecars(&appimage_dest_path, &provenance).await?;
+    let mut manifest = manifest::Manifest::default();
+    manifest.add(&appimage_dest_path);
+    manifest.add(&provenance::checksum_path(&appimage_dest_path));
+    manifest.add(Path::new(provenance::PROVENANCE_FILE));
+    manifest.add(&icon_dest_path);
+    manifest.appimage =
+        Some(manifest::FileRecord::new(&appimage_dest_path, provenance.installed_sha256()).await?);
+    manifest.save(&dest_dir).await?;
+
+    let previous_state = state::State::load().await.ok().flatten();
+    let mut state = state::State::new(
+        &config.desktop_name,
+        &metadata.version,
+        &metadata.commit_sha,
+        &appimage_dest_path,
+        config.platform,
+        config.release_track,
+    );
+    state.sha256 = Some(provenance.installed_sha256().to_string());
+    state.downloads = previous_state.map(|s| s.downloads).unwrap_or_default();
+    if let Some(sample) = download {
+        state.record_download(sample);
+    }
+    state.save().await?;
+    if !config.keep_downloads {
+        cache::prune_except(config.platform, Some(&metadata.version)).await?;
+    }
+    if let Some(budget) = config.cache_budget {
+        cache::enforce_budget(&dest_dir, budget).await?;
+    }
+
+    let previous_version = journal.previous_version.clone();
+    github::update_summary(
+        previous_version.as_deref(),
+        &metadata.version,
+        provenance.size,
+    );
+    let mut entry = history::HistoryEntry::new(history::Outcome::Updated, previous_version);
+    entry.to_version = Some(metadata.version.clone());
+    entry.commit_sha = Some(metadata.commit_sha.clone());
+    history::record(&entry).await?;
+    journal.complete(Step::Record).await?;
+    Ok(appimage_dest_path)
+}
+
+/// Puts the configured `icon` in place of the installed one right away
+/// rather than at the next update. Does nothing before the first install.
+pub async fn apply_icon(config: &Config) -> Result<()> {
+    let Some(custom) = &config.icon else {
+        return Ok(());
+    };
+    if status::installed_appimage(&config.install_dir)
+        .await?
+        .is_none()
+    {
+        return Ok(());
+    }
+    let dest = config.install_dir.join("code.png");
+    fs::copy(custom, &dest).await?;
+    fsutil::set_mode(&dest, fsutil::data_mode(config.file_mode)).await?;
+    events::log(format!("Copied {:?} to {:?}", custom, dest));
     Ok(())
 }
 
-pub async fn echo_2_desktop(appimage_path: &PathBuf, icon_path: &PathBuf) -> Result<()> {
-    let contents = format!(
+/// Writes the desktop entry. With `verbose` the changes to an existing entry
+/// are shown first; in a terminal the user is also asked before changes
+/// beyond the `Exec=` and `Icon=` paths, i.e. to a hand-tuned entry, are
+/// written.
+pub async fn echo_2_desktop(
+    name: &str,
+    appimage_path: &Path,
+    icon_path: &Path,
+    launch_args: &[&str],
+    verbose: bool,
+) -> Result<()> {
+    let contents = desktop_entry_contents(name, appimage_path, icon_path, launch_args);
+    let path = desktop_entry_path(false)?;
+    if let Ok(existing) = fs::read_to_string(&path).await {
+        let lines = diff::lines(&existing, &contents);
+        let label = path.display().to_string();
+        if let Some(diff) = diff::unified(&lines, &label, &label) {
+            let hand_tuned = lines.iter().any(|line| match line {
+                diff::Line::Removed(text) | diff::Line::Added(text) => !is_path_line(text),
+                diff::Line::Same(_) => false,
+            });
+            if verbose || hand_tuned {
+                events::log(format!(
+                    "Changes to the desktop entry:\n{}",
+                    diff.trim_end()
+                ));
+            }
+            let prompt = events::Prompt::RewriteDesktopEntry {
+                path: path.clone(),
+                diff,
+            };
+            if hand_tuned && events::ask(prompt).await? == Some(false) {
+                events::log(format!("Kept the existing desktop entry {:?}", path));
+                return Ok(());
+            }
+        }
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::write(&path, contents).await?;
+    fsutil::set_mode(&path, fsutil::protected_mode()).await
+}
+
+/// Contents of a desktop entry launching `exec_path` with `launch_args`.
+pub fn desktop_entry_contents(
+    name: &str,
+    exec_path: &Path,
+    icon_path: &Path,
+    launch_args: &[&str],
+) -> String {
+    // Arguments with spaces, such as profile dirs, are quoted as the desktop
+    // entry spec describes.
+    let exec = std::iter::once(exec_path.to_string_lossy())
+        .chain(launch_args.iter().map(|arg| {
+            if arg.contains(char::is_whitespace) {
+                format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\"")).into()
+            } else {
+                (*arg).into()
+            }
+        }))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut contents = format!(
         r#"[Desktop Entry]
-Name=Cursor
+Name={}
 Exec={}
 Icon={}
 Type=Application
 Categories=Utility;Development;
-Terminal=false"#,
-        appimage_path.to_str().unwrap(),
-        icon_path.to_str().unwrap(),
+Terminal=false
+"#,
+        name,
+        exec,
+        icon_path.display(),
     );
-    let home_dir = PathBuf::from(std::env::var("HOME")?);
-    let desktop_path = home_dir.join(".local/share/applications/cursor.desktop");
-    fs::write(desktop_path, contents).await?;
-    Ok(())
+    for (lang, comment) in i18n::all_translations("desktop-comment") {
+        match lang {
+            "en" => contents.push_str(&format!("Comment={}\n", comment)),
+            lang => contents.push_str(&format!("Comment[{}]={}\n", lang, comment)),
+        }
+    }
+    contents
+}
+
+/// `Exec=` or `Icon=` with nothing but a path (and the flags cursorup adds
+/// itself), which every update rewrites. Arguments added to `Exec=` by hand
+/// don't count.
+fn is_path_line(line: &str) -> bool {
+    ["Exec=", "Icon="].iter().any(|key| {
+        line.strip_prefix(key).is_some_and(|value| {
+            let value = value
+                .strip_suffix(execution::EXTRACT_AND_RUN_FLAG)
+                .map_or(value, str::trim_end);
+            !value.contains(char::is_whitespace)
+        })
+    })
+}
+
+/// Where the desktop entry goes: the user's applications dir, or the
+/// system-wide one for `system_install`.
+pub fn desktop_entry_path(system_install: bool) -> Result<PathBuf> {
+    if system_install {
+        return Ok(PathBuf::from(system::DESKTOP_ENTRY));
+    }
+    Ok(home_dir()?.join(".local/share/applications/cursor.desktop"))
 }
 
-pub async fn back_file(dir_path: PathBuf) -> Result<()> {
-    let back_dir = dir_path.join("back");
+/// Moves the current install's files into `back/`, compressing AppImages
+/// with zstd when `compress` is set. Files cursorup did not write are only
+/// moved aside into `foreign/` if the user confirms.
+pub async fn back_file(dir_path: PathBuf, compress: bool) -> Result<()> {
+    let back_dir = dir_path.join(backup::BACKUP_DIR);
     fs::create_dir_all(&back_dir).await?;
 
-    let mut entries = fs::read_dir(dir_path).await?;
-    while let Some(entry) = entries.next_entry().await? {
-        let path = entry.path();
+    let classified = manifest::classify(&dir_path).await?;
+    if !classified.foreign.is_empty() {
+        set_aside_foreign(&dir_path, &classified.foreign).await?;
+    }
 
-        if path.is_dir() {
-            continue;
-        }
+    for path in classified.tracked {
+        if let Some(file_name) = path.file_name() {
+            let mut backup_file_name = file_name.to_os_string();
+            backup_file_name.push(".bak");
+            let dest_path = back_dir.join(backup_file_name);
 
-        if let Some(extension) = path.extension().and_then(|s| s.to_str()) {
-            if extension == "AppImage" || extension == "png" {
-                if let Some(file_name) = path.file_name() {
-                    let mut backup_file_name = file_name.to_os_string();
-                    backup_file_name.push(".bak");
-                    let dest_path = back_dir.join(backup_file_name);
+            fs::rename(&path, &dest_path).await?;
+            events::emit(InstallEvent::BackedUp {
+                from: path.clone(),
+                to: dest_path.clone(),
+            });
 
-                    println!("Backing up {:?} to {:?}", &path, &dest_path);
-                    fs::rename(&path, &dest_path).await?;
+            // Drop the other representation of the same generation so it
+            // isn't listed twice. The previous install is already moved out,
+            // so a failed compression only costs space, never the install.
+            let compressed_path = backup::compressed_path(&dest_path);
+            let compressed = if compress
+                && path.extension().and_then(|s| s.to_str()) == Some("AppImage")
+            {
+                match backup::compress(&dest_path).await {
+                    Ok(_) => true,
+                    Err(e) => {
+                        tracing::warn!("keeping the backup {:?} uncompressed: {}", dest_path, e);
+                        false
+                    }
                 }
+            } else {
+                false
+            };
+            if !compressed && fs::try_exists(&compressed_path).await? {
+                fs::remove_file(&compressed_path).await?;
             }
         }
     }
     Ok(())
 }
 
-pub async fn run() -> Result<()> {
-    println!("Starting cursorup process...");
+/// What `--no-backup` does instead of `back_file`: deletes the files of the
+/// previous install other than `keep`, once the new one is linked.
+async fn remove_replaced(dir_path: &Path, keep: &[PathBuf]) -> Result<()> {
+    for path in manifest::classify(dir_path).await?.tracked {
+        if !keep.contains(&path) {
+            fs::remove_file(&path).await?;
+            events::log(format!("Removed {:?}", path));
+        }
+    }
+    Ok(())
+}
+
+async fn set_aside_foreign(dir_path: &Path, foreign: &[PathBuf]) -> Result<()> {
+    let names: Vec<String> = foreign
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect();
+    let prompt = events::Prompt::MoveForeignFiles {
+        files: foreign.to_vec(),
+        to: dir_path.join(manifest::FOREIGN_DIR),
+    };
+    let confirmed = events::ask(prompt).await?.unwrap_or(false);
+    if !confirmed {
+        events::log(format!(
+            "Leaving {} file(s) cursorup did not install in place: {}",
+            names.len(),
+            names.join(", ")
+        ));
+        return Ok(());
+    }
+    for (from, to) in foreign
+        .iter()
+        .zip(manifest::move_foreign(dir_path, foreign).await?)
+    {
+        events::log(format!("Moved unrecognized file {:?} to {:?}", from, to));
+    }
+    Ok(())
+}
+
+/// Loads the config file, creating it on first run either from the defaults
+/// (`use_defaults`, or when stdin is not a terminal) or via the interactive wizard.
+pub async fn load_config(use_defaults: bool) -> Result<Config> {
+    if let Some(config) = Config::load().await? {
+        return Ok(config);
+    }
+
+    let config = if use_defaults || !stdin().is_terminal() {
+        Config::default()
+    } else {
+        wizard::run()?
+    };
+    let path = config.save().await?;
+    events::log(format!("Wrote config to {:?}", path));
+    Ok(config)
+}
 
-    let metadata = fetch_metadata().await?;
-    println!("Successfully fetched metadata: {metadata:#?}");
+/// Refuses to install a build the machine's CPU cannot run, which would
+/// otherwise only fail at extraction.
+pub(crate) fn ensure_runnable(platform: Platform) -> Result<()> {
+    if let Some(host) = Platform::host()
+        && host != platform
+    {
+        return Err(format!(
+            "The {} build cannot run on this {} machine; set platform = \"{}\" in the config, or fetch it for another machine with `cursorup download --platform {}`",
+            platform,
+            std::env::consts::ARCH,
+            host,
+            platform
+        )
+        .into());
+    }
+    Ok(())
+}
 
-    let tmp_dir = tmpdir::TmpDir::default();
-    fs::create_dir_all(&tmp_dir.path).await?;
-    println!("Created temporary directory: {:?}", tmp_dir.path);
+/// Installs the release selected by `options` (or stages it, see
+/// `InstallOptions::stage`) whether or not it is already installed,
+/// recording the attempt in the history log (and the opt-in telemetry ping)
+/// either way.
+pub async fn install(config: &Config, options: &InstallOptions) -> Result<()> {
+    within_budget(config, options, install_unbounded(config, options)).await
+}
 
-    let download_url = &metadata.download_url;
-    let file_name = download_url
-        .split('/')
-        .last()
-        .unwrap_or("cursor-download.tmp");
-    let appimage_path = tmp_dir.path.join(file_name);
-    download_file(download_url, &appimage_path).await?;
-    install(&appimage_path, &metadata.version, &tmp_dir.path).await?;
-    println!("Cursorup process finished successfully.");
+async fn install_unbounded(config: &Config, options: &InstallOptions) -> Result<()> {
+    ensure_runnable(config.platform)?;
+    if options.no_cache && options.skip_download {
+        return Err("--no-cache cannot be combined with --skip-download".into());
+    }
+    if options.stage {
+        if !options.targets.is_empty() {
+            return Err("--stage cannot be combined with --targets".into());
+        }
+        if options.no_backup {
+            return Err("--stage cannot be combined with --no-backup".into());
+        }
+        if options.no_cache {
+            return Err("--stage cannot be combined with --no-cache".into());
+        }
+        return stage::stage(config, options).await;
+    }
+    let previous_version = status::installed_appimage(&config.install_dir)
+        .await?
+        .and_then(|path| status::version_from_file_name(&path));
+    if options.no_backup
+        && let Some(previous_version) = &previous_version
+    {
+        acknowledge_no_backup(previous_version).await?;
+    }
+    let result = if options.targets.is_empty() {
+        install_release(config, options, previous_version.as_deref()).await
+    } else {
+        targets::install(config, options, previous_version.as_deref()).await
+    };
+    if let Err(e) = &result {
+        history::record_failure(previous_version, e.as_ref()).await;
+    }
+    telemetry::ping(config, result.is_ok()).await;
+    result
+}
+
+/// `--no-backup` throws the installed version away, so the user has to
+/// confirm it; scripts have said so by passing the flag.
+async fn acknowledge_no_backup(previous_version: &str) -> Result<()> {
+    let prompt = events::Prompt::DeleteWithoutBackup {
+        version: previous_version.to_string(),
+    };
+    let risk = prompt.details().unwrap_or_default();
+    match events::ask(prompt).await? {
+        Some(true) => Ok(()),
+        Some(false) => Err("Cancelled; run without --no-backup to keep a backup".into()),
+        None => {
+            tracing::warn!("{}", risk);
+            Ok(())
+        }
+    }
+}
+
+/// Backed-up AppImages of the install, newest first, for front-ends that
+/// offer a rollback.
+pub async fn list_backups(config: &Config) -> Result<backup::Backups> {
+    backup::list(&config.install_dir).await
+}
+
+/// Puts the backup with this `id` back in place, as `cursorup rollback`
+/// does with the newest one. The `id` is a `Backup::id` or a version.
+pub async fn restore_backup(config: &Config, id: &str) -> Result<rollback::RolledBack> {
+    rollback::restore(config, Some(id), false).await
+}
+
+/// Deletes the backups `policy` selects, e.g. all but the newest few.
+pub async fn prune_backups(
+    config: &Config,
+    policy: &backup::PrunePolicy,
+) -> Result<backup::Pruned> {
+    backup::prune(&config.install_dir, policy).await
+}
+
+/// Installs the newest known build matching `version`, e.g. `1.2.3` or
+/// `1.2.x`, to reproduce a known-good setup; see `resolve_version`.
+pub async fn install_version(config: &Config, version: &str) -> Result<()> {
+    let options = InstallOptions {
+        version: Some(version.to_string()),
+        ..InstallOptions::default()
+    };
+    install(config, &options).await
+}
+
+/// Like `install`, but does nothing when the latest release of the track is
+/// already installed. A build pinned with `--commit` or `--version` or a
+/// cached download requested with `--skip-download` is always installed.
+pub async fn update(config: &Config, options: &InstallOptions) -> Result<()> {
+    within_budget(config, options, update_unbounded(config, options)).await
+}
+
+async fn update_unbounded(config: &Config, options: &InstallOptions) -> Result<()> {
+    ensure_runnable(config.platform)?;
+    if options.commit.is_none() && options.version.is_none() && !options.skip_download {
+        let client = http::client(config)?;
+        let metadata = fetch_metadata(
+            &client,
+            &config.api_url,
+            config.platform,
+            config.release_track,
+        )
+        .await?;
+        let check = check::check(config, &metadata).await?;
+        if let Some(current) = check.current.filter(|_| !check.update_available) {
+            if current == metadata.version {
+                events::log(format!("Cursor {} is already up to date.", current));
+            } else {
+                events::log(format!(
+                    "Cursor {} is newer than the latest {} release, {}; pass --force to install {} anyway.",
+                    current, config.release_track, metadata.version, metadata.version
+                ));
+            }
+            return Ok(());
+        }
+    }
+    install_unbounded(config, options).await
+}
+
+/// Runs `work` under `--max-duration`, if given. Running out of time drops
+/// it at its next await: a partial download stays resumable and an install
+/// that had begun keeps its journal, so the error can say whether `update`
+/// or `resume` picks up from there.
+async fn within_budget(
+    config: &Config,
+    options: &InstallOptions,
+    work: impl std::future::Future<Output = Result<()>>,
+) -> Result<()> {
+    let Some(limit) = options.max_duration else {
+        return work.await;
+    };
+    let started_at = state::now();
+    if let Ok(result) = tokio::time::timeout(Duration::from_secs(limit), work).await {
+        return result;
+    }
+    let stopped = format!("Cancelled after the --max-duration of {}s", limit);
+    let e: Box<dyn std::error::Error> = match journal::load(None).await? {
+        Some(journal) if journal.started_at >= started_at => journal.interrupted(stopped.into()),
+        _ => format!(
+            "{}\nAny partial download is kept; run the command again to continue it.",
+            stopped
+        )
+        .into(),
+    };
+    let previous_version = status::installed_appimage(&config.install_dir)
+        .await?
+        .and_then(|path| status::version_from_file_name(&path));
+    history::record_failure(previous_version, e.as_ref()).await;
+    Err(e)
+}
+
+async fn install_release(
+    config: &Config,
+    options: &InstallOptions,
+    previous_version: Option<&str>,
+) -> Result<()> {
+    events::log("Starting cursorup process...");
+    let fetched = fetch_release(config, options).await?;
+    let appimage = install_fetched(config, fetched, previous_version, options).await?;
+    smoke::verify_or_roll_back(config, &appimage, options.verbose).await?;
+    if let Some(version) = status::version_from_file_name(&appimage) {
+        restart::after_update(config, &version).await?;
+    }
+    if options.no_cache {
+        cache::prune_except(config.platform, None).await?;
+    }
+    events::log("Cursorup process finished successfully.");
+    Ok(())
+}
+
+/// Asks before the installed Cursor `from`, if any, is replaced with `to`;
+/// an explicit no cancels. Every path that replaces the install, i.e.
+/// install, commit, resume and rollback, asks before it changes anything.
+pub(crate) async fn confirm_replace(from: Option<&str>, to: &str) -> Result<()> {
+    let Some(from) = from else {
+        return Ok(());
+    };
+    let prompt = events::Prompt::ReplaceInstall {
+        from: from.to_string(),
+        to: to.to_string(),
+    };
+    if events::ask(prompt).await? == Some(false) {
+        return Err(format!("Cancelled; Cursor {} is still installed", from).into());
+    }
     Ok(())
 }
+
+/// Extracts a fetched release and puts it in place, returning the installed
+/// AppImage.
+pub(crate) async fn install_fetched(
+    config: &Config,
+    fetched: Prepared,
+    previous_version: Option<&str>,
+    options: &InstallOptions,
+) -> Result<PathBuf> {
+    // `--no-backup` was confirmed already.
+    confirm_replace(
+        previous_version.filter(|_| !options.no_backup),
+        &fetched.metadata.version,
+    )
+    .await?;
+    let tmp_dir = tmpdir::TmpDir::new()?;
+    events::log(format!("Created temporary directory: {:?}", tmp_dir.path));
+    let extracted_dir = extract(
+        &fetched.appimage,
+        &tmp_dir.path,
+        fsutil::executable_mode(config.file_mode),
+        config.execution_mode,
+    )
+    .await?;
+
+    let prepared = Prepared {
+        icon: extracted_dir.join("code.png"),
+        ..fetched
+    };
+    let mut journal = journal::Journal::begin(
+        config,
+        &prepared,
+        previous_version.map(str::to_string),
+        options.no_backup,
+    )
+    .await?;
+    let appimage = match activate(config, prepared, &mut journal, false, options.verbose).await {
+        Ok(appimage) => appimage,
+        Err(e) => return Err(journal.interrupted(e)),
+    };
+    journal::clear().await?;
+    stage::discard(config).await?;
+    Ok(appimage)
+}
+
+/// Resolves, downloads (or takes from the cache) and verifies the release
+/// selected by `options`. The returned `icon` is not extracted yet.
+pub(crate) async fn fetch_release(config: &Config, options: &InstallOptions) -> Result<Prepared> {
+    preflight::require(config).await?;
+    let client = http::client(config)?;
+    let metadata = resolve_metadata(&client, config, options).await?;
+    fetch_build(config, &client, metadata, options).await
+}
+
+/// `fetch_release` for a build whose metadata is already resolved.
+pub(crate) async fn fetch_build(
+    config: &Config,
+    client: &reqwest::Client,
+    mut metadata: Resp,
+    options: &InstallOptions,
+) -> Result<Prepared> {
+    metadata.download_url = urls::mirrored(&metadata.download_url, config.download_base.as_deref());
+    events::emit(InstallEvent::MetadataFetched {
+        version: metadata.version.clone(),
+        commit_sha: metadata.commit_sha.clone(),
+        download_url: metadata.download_url.clone(),
+    });
+
+    let previous_state = state::State::load().await.ok().flatten();
+
+    let download_url = &metadata.download_url;
+    let appimage_path = cache::artifact_path(
+        config.platform,
+        &metadata.version,
+        urls::file_name_from_url(download_url),
+    )?;
+    let fetched_at = state::now();
+    let mut verification = Vec::new();
+    // A complete download left by an earlier install, e.g. when
+    // reinstalling, is used again; it is still checked against the
+    // published checksum below.
+    let cached = if options.skip_download {
+        Some(cache::verify(&appimage_path).await?)
+    } else if options.no_cache {
+        None
+    } else {
+        cache::verify(&appimage_path).await.ok()
+    };
+    let download = if let Some(sha256) = cached {
+        events::log(format!("Using cached download {:?}", appimage_path));
+        verification.push(provenance::Verification::new(
+            "cached-sha256",
+            true,
+            "matches checksum recorded at download time",
+        ));
+        let bytes = fs::metadata(&appimage_path).await?.len();
+        Download {
+            bytes,
+            expected_bytes: bytes,
+            sha256,
+            elapsed: Duration::ZERO,
+        }
+    } else {
+        let cache_dir = appimage_path.parent().ok_or("Cache path has no parent")?;
+        fs::create_dir_all(cache_dir).await?;
+        let _ = fs::remove_file(provenance::checksum_path(&appimage_path)).await;
+        if options.no_cache {
+            let part = fsutil::with_suffix(&appimage_path, ".part");
+            let _ = fs::remove_file(fsutil::with_suffix(&part, ".validator")).await;
+            let _ = fs::remove_file(part).await;
+        }
+        let buffer_size = download_buffer_size(
+            previous_state
+                .as_ref()
+                .and_then(state::State::median_bytes_per_second),
+        );
+        let download =
+            download_file(client, download_url, &appimage_path, buffer_size, config).await?;
+        provenance::write_checksum(&appimage_path, &download.sha256, None).await?;
+        download
+    };
+    let published =
+        checksums::published(client, config, options.sha256.as_deref(), &metadata).await?;
+    match &published {
+        Some(published) if published.sha256 == download.sha256 => {
+            events::log(format!("Verified SHA-256 against {}", published.source));
+            verification.push(provenance::Verification::new(
+                "published-sha256",
+                true,
+                format!("matches {}", published.source),
+            ));
+        }
+        Some(published) => {
+            // Make sure `--skip-download` won't pick up the bad file either.
+            let _ = fs::remove_file(provenance::checksum_path(&appimage_path)).await;
+            return Err(format!(
+                "Checksum mismatch for {}: {} lists {}, the download has {}",
+                download_url, published.source, published.sha256, download.sha256
+            )
+            .into());
+        }
+        // Nothing was compared, so nothing passed.
+        None => verification.push(provenance::Verification::new(
+            "published-sha256",
+            false,
+            "skipped: no checksum published; relying on the hash recorded at download time",
+        )),
+    }
+    verification.push(provenance::Verification::new(
+        "content-length",
+        download.bytes == download.expected_bytes,
+        format!("{} of {} bytes", download.bytes, download.expected_bytes),
+    ));
+
+    let sample = (!download.elapsed.is_zero()).then(|| state::DownloadSample {
+        at: fetched_at,
+        host: reqwest::Url::parse(download_url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_default(),
+        bytes: download.bytes,
+        bytes_per_second: (download.bytes as f64 / download.elapsed.as_secs_f64()) as u64,
+    });
+    let (appimage_path, transformed) = match &config.transform {
+        Some(command) => {
+            let (path, transformed) =
+                transform::apply(command, &appimage_path, &metadata.version).await?;
+            (path, Some(transformed))
+        }
+        None => (appimage_path, None),
+    };
+    let provenance = provenance::Provenance {
+        file: urls::file_name_from_url(download_url).to_string(),
+        version: metadata.version.clone(),
+        commit_sha: metadata.commit_sha.clone(),
+        source_url: download_url.clone(),
+        sha256: download.sha256,
+        checksum_source: published.map(|published| published.source),
+        size: download.bytes,
+        fetched_at,
+        verification,
+        transformed,
+    };
+    Ok(Prepared {
+        metadata,
+        provenance,
+        download: sample,
+        icon: PathBuf::new(),
+        appimage: appimage_path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_ranges() {
+        assert_eq!(parse_content_range("bytes 0-99/100"), Some((0, 100)));
+        assert_eq!(
+            parse_content_range("bytes 1048576-5242879/5242880"),
+            Some((1048576, 5242880))
+        );
+        // An unknown total can't be resumed against.
+        assert_eq!(parse_content_range("bytes 0-99/*"), None);
+        // What a 416 carries.
+        assert_eq!(parse_content_range("bytes */100"), None);
+        assert_eq!(parse_content_range("items 0-99/100"), None);
+        assert_eq!(parse_content_range("bytes 0-99"), None);
+        assert_eq!(parse_content_range(""), None);
+    }
+}
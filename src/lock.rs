@@ -0,0 +1,103 @@
+//! Lock files for reproducible fleets: `cursorup lock export` records the
+//! exact build installed here (version, commit, file and SHA-256), and
+//! `cursorup install --locked <file>` installs that build on another
+//! machine, refusing anything else.
+
+use crate::config::Config;
+use crate::provenance::{PROVENANCE_FILE, Provenance};
+use crate::{InstallOptions, Platform, Result, urls};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tokio::fs;
+
+/// Format version of lock files this cursorup writes and reads.
+const LOCK_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Lock {
+    pub lock_version: u32,
+    pub platform: Platform,
+    pub version: String,
+    pub commit_sha: String,
+    /// File name of the download, e.g. `Cursor-1.2.3-x86_64.AppImage`.
+    pub file: String,
+    /// SHA-256 and size of the download as published, before any
+    /// `transform`.
+    pub sha256: String,
+    pub size: u64,
+}
+
+/// The lock for the installed build, from its `provenance.json`.
+pub async fn export(config: &Config) -> Result<Lock> {
+    let path = config.install_dir.join(PROVENANCE_FILE);
+    let contents = fs::read_to_string(&path)
+        .await
+        .map_err(|e| format!("No install to lock: cannot read {:?}: {}", path, e))?;
+    let provenance: Provenance = serde_json::from_str(&contents)
+        .map_err(|e| format!("{}: invalid provenance: {}", path.display(), e))?;
+    Ok(Lock {
+        lock_version: LOCK_VERSION,
+        platform: config.platform,
+        version: provenance.version,
+        commit_sha: provenance.commit_sha,
+        file: provenance.file,
+        sha256: provenance.sha256,
+        size: provenance.size,
+    })
+}
+
+pub async fn load(path: &Path) -> Result<Lock> {
+    let contents = fs::read_to_string(path)
+        .await
+        .map_err(|e| format!("Cannot read the lock file {:?}: {}", path, e))?;
+    let lock: Lock = serde_json::from_str(&contents)
+        .map_err(|e| format!("{}: invalid lock file: {}", path.display(), e))?;
+    if lock.lock_version != LOCK_VERSION {
+        return Err(format!(
+            "{}: lock file version {} is not supported; this cursorup reads version {}",
+            path.display(),
+            lock.lock_version,
+            LOCK_VERSION
+        )
+        .into());
+    }
+    Ok(lock)
+}
+
+/// Installs exactly the build in `lock`. The download must have the locked
+/// file name and SHA-256, or nothing is installed.
+pub async fn install(config: &Config, lock: &Lock, options: &InstallOptions) -> Result<()> {
+    if options.commit.is_some() || options.version.is_some() || options.sha256.is_some() {
+        return Err("--locked cannot be combined with --commit, --version or --sha256".into());
+    }
+    if !options.targets.is_empty() {
+        return Err("--locked cannot be combined with --targets".into());
+    }
+    if lock.platform != config.platform {
+        return Err(format!(
+            "The lock file is for {}, but this machine installs {}; pass --platform {} to install it anyway",
+            lock.platform, config.platform, lock.platform
+        )
+        .into());
+    }
+    let file = urls::file_name_from_url(&urls::appimage_url(
+        &lock.commit_sha,
+        &lock.version,
+        lock.platform,
+    ))
+    .to_string();
+    if file != lock.file {
+        return Err(format!(
+            "The lock file names {}, but Cursor {} ({}) is published as {}",
+            lock.file, lock.version, lock.commit_sha, file
+        )
+        .into());
+    }
+    let options = InstallOptions {
+        commit: Some(lock.commit_sha.clone()),
+        version: Some(lock.version.clone()),
+        sha256: Some(lock.sha256.clone()),
+        ..options.clone()
+    };
+    crate::install(config, &options).await
+}
@@ -1,10 +1,493 @@
 use cursorup::Result;
+use cursorup::check::Porcelain;
+use cursorup::config::Config;
+use cursorup::events::Interaction;
+use cursorup::output::{self, Format};
+
+mod cli;
+
+use cli::{Cli, Command};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// `--output json`: stdout is newline-delimited JSON, so reports are
+/// printed on one line too.
+static JSON_LINES: AtomicBool = AtomicBool::new(false);
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    if let Err(e) = cursorup::run().await {
-        eprintln!("Application error: {e}");
-        std::process::exit(1);
+    let cli = match Cli::parse(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{e}\n\n{}", cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    init_logging(&cli);
+    // The daemon and `watch` run unattended even when started in a terminal.
+    let interaction = match cli.command {
+        Command::Daemon | Command::Watch if cli.interaction == Interaction::Ask => {
+            Interaction::Never
+        }
+        _ => cli.interaction,
+    };
+    cursorup::events::set_interaction(interaction);
+    if cli.json_events {
+        JSON_LINES.store(true, Ordering::Relaxed);
+        cursorup::events::set_handler(Arc::new(|event: &cursorup::events::Event| {
+            if let Ok(line) = serde_json::to_string(event) {
+                println!("{}", line);
+            }
+        }));
+    }
+
+    // Only commands that install write a config on first run, via the
+    // wizard where someone can answer it. The others, and dry runs, use the
+    // defaults in memory, so `status --format json` or `check --porcelain`
+    // print nothing but their own output.
+    let sets_up = matches!(
+        cli.command,
+        Command::Update
+            | Command::Install
+            | Command::Commit
+            | Command::Resume
+            | Command::Adopt
+            | Command::Daemon
+    );
+    let loaded = if cli.dry_run || !sets_up {
+        Config::load().await.map(Option::unwrap_or_default)
+    } else {
+        cursorup::load_config(cli.defaults || interaction != Interaction::Ask).await
+    };
+    let config = match loaded {
+        Ok(mut config) => match apply_overrides(&cli, &mut config) {
+            Ok(()) => config,
+            Err(e) => fail(e, None),
+        },
+        Err(e) => fail(e, None),
+    };
+    cursorup::report::install_panic_hook(Some(config.clone()));
+    // A dry run leaves the config and caches alone too.
+    let saved = if cli.dry_run {
+        Ok(())
+    } else {
+        save_overrides(&cli, &config).await
+    };
+    let result = match saved {
+        Ok(()) => {
+            notify_outdated(&cli, &config).await;
+            match acquire_lock(&cli) {
+                // Held until dispatch returns.
+                Ok(_lock) => dispatch(&cli, &config).await,
+                Err(e) => Err(e),
+            }
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        fail(e, Some(&config));
+    }
+    Ok(())
+}
+
+/// Reports `error` and exits. Errors cursorup did not anticipate also get a
+/// diagnostic report.
+fn fail(error: Box<dyn std::error::Error>, config: Option<&Config>) -> ! {
+    cursorup::github::error(&error.to_string());
+    cursorup::events::error(error.as_ref());
+    if cursorup::report::is_unclassified(error.as_ref())
+        && let Some(path) = cursorup::report::write("error", &error.to_string(), config)
+    {
+        cursorup::report::print_path(&path);
+    }
+    std::process::exit(1);
+}
+
+/// Sends `tracing` output to stderr: warnings by default, debug or trace
+/// details with `-v` or `-vv`. The install events are printed by `events`
+/// already, so they are left out; `RUST_LOG` replaces the filter entirely.
+fn init_logging(cli: &Cli) {
+    use tracing_subscriber::EnvFilter;
+
+    let filter = match cli.log_verbosity {
+        0 => "warn",
+        1 => "warn,cursorup=debug,cursorup::events=off",
+        _ => "warn,cursorup=trace,cursorup::events=off",
+    };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(filter));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::IsTerminal::is_terminal(&std::io::stderr()))
+        .without_time()
+        .with_target(false)
+        .init();
+    if cli.quiet {
+        cursorup::events::set_printing(false);
+    }
+}
+
+/// Applies per-invocation overrides on top of the loaded config:
+/// `CURSORUP_*` environment variables first, then the command line.
+fn apply_overrides(cli: &Cli, config: &mut Config) -> Result<()> {
+    config.apply_env()?;
+    if let Some(profile) = &cli.profile {
+        config.apply_profile(profile)?;
+    }
+    if cli.system {
+        config.system_install = true;
+        config.install_dir = cursorup::system::INSTALL_DIR.into();
+    }
+    if let Some(install_dir) = &cli.install_dir {
+        config.install_dir = std::path::absolute(install_dir)?;
+    }
+    for (key, value) in &cli.overrides {
+        config.set(key, value)?;
+    }
+    if let Some(name) = &cli.name {
+        config.desktop_name = name.clone();
+    }
+    if let Some(api_url) = &cli.api_url {
+        config.api_url = api_url.clone();
+    }
+    if let Some(proxy) = &cli.proxy {
+        config.proxy = Some(proxy.clone());
+    }
+    if let Some(ca_cert) = &cli.ca_cert {
+        config.ca_cert = Some(ca_cert.clone());
+    }
+    if cli.insecure {
+        config.insecure = true;
+    }
+    if cli.force_metered {
+        config.metered_updates = cursorup::metered::MeteredPolicy::Install;
+    }
+    if cli.keep_download {
+        if cli.install.no_cache {
+            return Err("--keep-download cannot be combined with --no-cache".into());
+        }
+        config.keep_downloads = true;
+    }
+    if let Some(connections) = &cli.connections {
+        config
+            .set("connections", connections)
+            .map_err(|e| e.replace("--set connections", "--connections"))?;
+    }
+    if let Some(limit_rate) = &cli.limit_rate {
+        config
+            .set("limit_rate", limit_rate)
+            .map_err(|e| e.replace("--set limit_rate", "--limit-rate"))?;
+    }
+    if let Some(ip_version) = cli.ip_version {
+        config.ip_version = ip_version;
+    }
+    config.resolve.extend(cli.resolve.iter().cloned());
+    if let Some(channel) = cli.channel {
+        config.release_track = channel;
+    }
+    if let Some(platform) = cli.platform {
+        config.platform = platform;
+    }
+    if let Some(icon) = &cli.icon {
+        // Saved for later runs, which may start elsewhere.
+        config.icon = Some(std::path::absolute(icon)?);
+    }
+    Ok(())
+}
+
+/// Opens the release notes in the browser, printing them where that is not
+/// possible.
+async fn show_changelog(changelog: &cursorup::changelog::Changelog, format: Format) -> Result<()> {
+    if cursorup::changelog::open_in_browser(&changelog.url).await {
+        eprintln!("Opened {}", changelog.url);
+        return Ok(());
+    }
+    print(changelog, format)
+}
+
+/// Unlike the other overrides, `--channel` and `--icon` are kept for later
+/// runs. A new icon replaces the installed one right away.
+async fn save_overrides(cli: &Cli, config: &Config) -> Result<()> {
+    if let Some(channel) = cli.channel
+        && Config::save_release_track(channel).await?
+    {
+        eprintln!("Now following the {} channel", channel);
+    }
+    if let Some(icon) = &config.icon
+        && cli.icon.is_some()
+    {
+        match tokio::fs::metadata(icon).await {
+            Ok(metadata) if metadata.is_file() => {}
+            Ok(_) => return Err(format!("Cannot use {:?} as the icon: not a file", icon).into()),
+            Err(e) => return Err(format!("Cannot use {:?} as the icon: {}", icon, e).into()),
+        }
+        if Config::save_setting("icon", &icon.display().to_string()).await? {
+            eprintln!("Using the icon {} from now on", icon.display());
+        }
+        cursorup::apply_icon(config).await?;
+    }
+    Ok(())
+}
+
+/// Takes the instance lock for commands that change the install, cache or
+/// state. The daemon and `rpc` take it only while they change something, so
+/// they don't keep manual runs out while idle.
+fn acquire_lock(cli: &Cli) -> Result<Option<cursorup::instance::InstanceLock>> {
+    let changes = matches!(
+        cli.command,
+        Command::Update
+            | Command::Install
+            | Command::Commit
+            | Command::Download
+            | Command::Resume
+            | Command::Adopt
+            | Command::Rollback
+            | Command::Gc
+            | Command::Uninstall
+            | Command::SelfUpdate
+            | Command::ProfileAdd
+            | Command::ProfileRemove
+    );
+    if !changes || cli.dry_run {
+        return Ok(None);
+    }
+    Ok(Some(cursorup::instance::acquire()?))
+}
+
+/// Hints at `cursorup self update` when `check` or `update` runs on an
+/// outdated cursorup. Prompt integrations (`--porcelain`) must stay quick and
+/// quiet, so they never look.
+async fn notify_outdated(cli: &Cli, config: &Config) {
+    if !matches!(cli.command, Command::Check | Command::Update)
+        || !config.self_update_notice
+        || cli.quiet
+        || cli.dry_run
+        || cli.porcelain.is_some()
+    {
+        return;
+    }
+    let Ok(client) = cursorup::http::client(config) else {
+        return;
+    };
+    if let Some(latest) = cursorup::selfupdate::newer_release(&client).await {
+        eprintln!(
+            "cursorup {} is available (this is {}); run `cursorup self update` to upgrade",
+            latest,
+            env!("CARGO_PKG_VERSION")
+        );
+    }
+}
+
+/// `--dry-run`: fetches the metadata and prints the plan of the update or
+/// install, without writing anything.
+async fn dry_run(cli: &Cli, config: &Config) -> Result<()> {
+    let client = cursorup::http::client(config)?;
+    let metadata = cursorup::resolve_metadata(&client, config, &cli.install).await?;
+    let pinned = cli.install.version.is_some() || cli.install.commit.is_some();
+    if matches!(cli.command, Command::Update) && !cli.force && !pinned {
+        let check = cursorup::check::check(config, &metadata).await?;
+        if let Some(current) = check.current.filter(|_| !check.update_available) {
+            eprintln!(
+                "Cursor {} is installed and {} is the latest release, so `update` would change nothing; pass --force to plan a reinstall.",
+                current, metadata.version
+            );
+            return Ok(());
+        }
+    }
+    print(
+        &cursorup::plan::plan(config, &client, &metadata, &cli.install).await?,
+        cli.format,
+    )?;
+    eprintln!("Dry run: nothing was changed.");
+    Ok(())
+}
+
+async fn dispatch(cli: &Cli, config: &Config) -> Result<()> {
+    match cli.command {
+        Command::Update | Command::Install if cli.dry_run => dry_run(cli, config).await,
+        Command::Update if cli.force => cursorup::install(config, &cli.install).await,
+        Command::Update => cursorup::update(config, &cli.install).await,
+        Command::Install => match &cli.locked {
+            Some(path) => {
+                let lock = cursorup::lock::load(path).await?;
+                cursorup::lock::install(config, &lock, &cli.install).await
+            }
+            None => cursorup::install(config, &cli.install).await,
+        },
+        Command::Resume => {
+            cursorup::journal::resume(config, cli.resume_id.as_deref(), cli.verbose).await
+        }
+        Command::Commit => cursorup::stage::commit(config, cli.verbose).await,
+        Command::Download => print(
+            &cursorup::export::download(config, &cli.install, &cli.export).await?,
+            cli.format,
+        ),
+        Command::Check => {
+            let update_available = if cli.all_tracks {
+                if cli.porcelain.is_some() {
+                    return Err("--porcelain cannot be combined with --all-tracks".into());
+                }
+                let comparison = cursorup::check::all_tracks(config).await?;
+                print(&comparison, cli.format)?;
+                comparison.update_available()
+            } else if cli.porcelain == Some(Porcelain::V2) {
+                let check = cursorup::check::cached(config).await?;
+                println!("{}", serde_json::to_string(&check)?);
+                check.check.update_available
+            } else {
+                let client = cursorup::http::client(config)?;
+                let metadata = cursorup::fetch_metadata(
+                    &client,
+                    &config.api_url,
+                    config.platform,
+                    config.release_track,
+                )
+                .await?;
+                let check = cursorup::check::check(config, &metadata).await?;
+                if cli.porcelain.is_some() {
+                    println!("{}", check.porcelain());
+                } else {
+                    print(&check, cli.format)?;
+                }
+                check.update_available
+            };
+            if update_available {
+                std::process::exit(cursorup::check::EXIT_UPDATE_AVAILABLE);
+            }
+            Ok(())
+        }
+        Command::Adopt => print(&cursorup::adopt::adopt(config).await?, cli.format),
+        Command::Rollback => print(
+            &cursorup::rollback::rollback(config, cli.verbose).await?,
+            cli.format,
+        ),
+        Command::Gc => print(&cursorup::cleanup::gc(config).await?, cli.format),
+        Command::Du => print(&cursorup::du::disk_usage(config).await?, cli.format),
+        Command::TelemetryShow => print(&cursorup::telemetry::show(config), cli.format),
+        Command::Uninstall if cli.purge => {
+            print(&cursorup::cleanup::purge(config).await?, cli.format)
+        }
+        Command::Uninstall => print(&cursorup::cleanup::uninstall(config).await?, cli.format),
+        Command::Status if cli.open => {
+            show_changelog(&cursorup::changelog::installed(config).await?, cli.format).await
+        }
+        Command::Changelog => {
+            let changelog = cursorup::changelog::latest(config).await?;
+            if cli.open {
+                show_changelog(&changelog, cli.format).await
+            } else {
+                print(&changelog, cli.format)
+            }
+        }
+        Command::Status => print(
+            &cursorup::status::status(config, cli.verbose).await?,
+            cli.format,
+        ),
+        Command::Info if cli.all_platforms => print(
+            &cursorup::matrix::fetch_all(&cursorup::http::client(config)?, &config.api_url).await,
+            cli.format,
+        ),
+        Command::Info => print(
+            &cursorup::fetch_metadata(
+                &cursorup::http::client(config)?,
+                &config.api_url,
+                config.platform,
+                config.release_track,
+            )
+            .await?,
+            cli.format,
+        ),
+        Command::Plan => {
+            let client = cursorup::http::client(config)?;
+            let metadata = cursorup::resolve_metadata(&client, config, &cli.install).await?;
+            print(
+                &cursorup::plan::plan(config, &client, &metadata, &cli.install).await?,
+                cli.format,
+            )
+        }
+        Command::Env => print(
+            &cursorup::environment::environment(config).await?,
+            cli.format,
+        ),
+        Command::Doctor => {
+            let preflight = cursorup::preflight::probe(config).await?;
+            print(&preflight, cli.format)?;
+            match preflight.error() {
+                Some(error) => Err(error.into()),
+                None => Ok(()),
+            }
+        }
+        Command::HistoryExport => print(&cursorup::history::load(cli.since).await?, cli.format),
+        Command::BackupsList => print(
+            &cursorup::backup::list(&config.install_dir).await?,
+            cli.format,
+        ),
+        Command::BackupsVerify => {
+            let checks = cursorup::backup::verify(&config.install_dir).await?;
+            print(&checks, cli.format)?;
+            if !checks.all_ok() {
+                return Err("Some backups failed verification".into());
+            }
+            Ok(())
+        }
+        Command::Verify => {
+            let verification = cursorup::status::verify(config).await?;
+            print(&verification, cli.format)?;
+            if !verification.ok {
+                return Err("The installed AppImage failed verification".into());
+            }
+            Ok(())
+        }
+        Command::SelfUpdate => {
+            let client = cursorup::http::client(config)?;
+            let result =
+                cursorup::selfupdate::self_update(&client, config, cli.allow_unsigned).await?;
+            print(&result, cli.format)
+        }
+        Command::LockExport => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&cursorup::lock::export(config).await?)?
+            );
+            Ok(())
+        }
+        Command::ProfileAdd => print(
+            &cursorup::profiles::add(
+                config,
+                cli.profile_name.as_deref().unwrap_or_default(),
+                cli.user_data_dir
+                    .as_deref()
+                    .ok_or("--user-data-dir is required")?,
+                cli.extensions_dir.as_deref(),
+            )
+            .await?,
+            cli.format,
+        ),
+        Command::ProfileList => print(&cursorup::profiles::Profiles::load().await?, cli.format),
+        Command::ProfileRemove => {
+            cursorup::profiles::remove(cli.profile_name.as_deref().unwrap_or_default())
+                .await
+                .map(drop)
+        }
+        Command::ShimCode => print(&cursorup::shim::create(cli.force).await?, cli.format),
+        Command::ShimRemove => cursorup::shim::remove().await.map(drop),
+        Command::Daemon => cursorup::daemon::run(config).await,
+        Command::Watch => cursorup::watch::run(config).await,
+        Command::Rpc => cursorup::rpc::serve(config).await,
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            Ok(())
+        }
+    }
+}
+
+fn print<T: serde::Serialize + std::fmt::Display>(value: &T, format: Format) -> Result<()> {
+    if JSON_LINES.load(Ordering::Relaxed) {
+        println!("{}", serde_json::to_string(value)?);
+        return Ok(());
     }
+    let rendered = output::render(value, format)?;
+    println!("{}", rendered.trim_end());
     Ok(())
 }
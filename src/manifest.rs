@@ -0,0 +1,190 @@
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Records which files in the install dir cursorup wrote, so backups never
+/// sweep up files the user put there.
+pub const MANIFEST_FILE: &str = ".cursorup-manifest.json";
+
+/// Directory inside the install dir that unrecognized files are moved to.
+pub const FOREIGN_DIR: &str = "foreign";
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Manifest {
+    /// File names relative to the install dir.
+    pub files: BTreeSet<String>,
+    /// How the installed AppImage looked when cursorup put it there.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub appimage: Option<FileRecord>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FileRecord {
+    pub file: String,
+    pub size: u64,
+    /// Modification time in seconds since the Unix epoch.
+    pub modified: u64,
+    pub sha256: String,
+}
+
+impl FileRecord {
+    /// Describes `path` as it is now, with a hash already known for it.
+    pub async fn new(path: &Path, sha256: &str) -> Result<FileRecord> {
+        let (size, modified) = size_and_mtime(path).await?;
+        Ok(FileRecord {
+            file: path
+                .file_name()
+                .ok_or("Path has no file name")?
+                .to_string_lossy()
+                .into_owned(),
+            size,
+            modified,
+            sha256: sha256.to_string(),
+        })
+    }
+}
+
+/// Whether the installed AppImage still matches its manifest record. Size
+/// and mtime are compared first; only when they differ is the file hashed,
+/// so a mere `touch` is not reported.
+pub async fn modified_externally(install_dir: &Path, appimage: &Path) -> Result<bool> {
+    let Some(record) = Manifest::load(install_dir).await?.and_then(|m| m.appimage) else {
+        return Ok(false);
+    };
+    if appimage.file_name() != Some(std::ffi::OsStr::new(&record.file)) {
+        return Ok(false);
+    }
+    if size_and_mtime(appimage).await? == (record.size, record.modified) {
+        return Ok(false);
+    }
+    Ok(crate::cache::sha256_file(appimage).await? != record.sha256)
+}
+
+/// The warning shown by `status` and `update` for a modified AppImage.
+pub fn modified_warning(appimage: &Path) -> String {
+    format!(
+        "installed AppImage {:?} was modified outside cursorup, so it no longer matches its recorded checksum; `cursorup update` reinstalls it",
+        appimage
+    )
+}
+
+async fn size_and_mtime(path: &Path) -> Result<(u64, u64)> {
+    let metadata = fs::metadata(path).await?;
+    let modified = metadata
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    Ok((metadata.len(), modified))
+}
+
+impl Manifest {
+    pub async fn load(install_dir: &Path) -> Result<Option<Manifest>> {
+        let path = install_dir.join(MANIFEST_FILE);
+        match fs::read_to_string(&path).await {
+            Ok(contents) => {
+                Ok(Some(serde_json::from_str(&contents).map_err(|e| {
+                    format!("{}: invalid manifest: {}", path.display(), e)
+                })?))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub async fn save(&self, install_dir: &Path) -> Result<()> {
+        crate::fsutil::write_atomic(
+            &install_dir.join(MANIFEST_FILE),
+            serde_json::to_string_pretty(self)?,
+        )
+        .await
+    }
+
+    pub fn add(&mut self, path: &Path) {
+        if let Some(name) = path.file_name() {
+            self.files.insert(name.to_string_lossy().into_owned());
+        }
+    }
+
+    fn tracks(&self, name: &str) -> bool {
+        self.files.contains(name)
+    }
+}
+
+/// Files of an install dir that look like install artifacts, split by
+/// whether cursorup owns them.
+#[derive(Debug, Default)]
+pub struct Classified {
+    pub tracked: Vec<PathBuf>,
+    pub foreign: Vec<PathBuf>,
+}
+
+/// Sorts the AppImages, icons and checksums in `install_dir` into tracked
+/// and foreign files. Install dirs written before the manifest existed are
+/// judged by cursorup's own file names instead.
+pub async fn classify(install_dir: &Path) -> Result<Classified> {
+    let mut entries = match fs::read_dir(install_dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Classified::default()),
+        Err(e) => return Err(e.into()),
+    };
+    let manifest = Manifest::load(install_dir).await?;
+    let mut classified = Classified::default();
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if !is_artifact(&path) {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let tracked = match &manifest {
+            Some(manifest) => manifest.tracks(&name),
+            None => has_own_name(&name),
+        };
+        if tracked {
+            classified.tracked.push(path);
+        } else {
+            classified.foreign.push(path);
+        }
+    }
+    classified.tracked.sort();
+    classified.foreign.sort();
+    Ok(classified)
+}
+
+fn is_artifact(path: &Path) -> bool {
+    !path.is_dir()
+        && matches!(
+            path.extension().and_then(|s| s.to_str()),
+            Some("AppImage" | "png" | "sha256")
+        )
+}
+
+fn has_own_name(name: &str) -> bool {
+    name == "code.png"
+        || (name.starts_with("Cursor-")
+            && (name.ends_with(".AppImage") || name.ends_with(".AppImage.sha256")))
+}
+
+/// Moves `files` into `<install_dir>/foreign/`, never overwriting anything
+/// already there.
+pub async fn move_foreign(install_dir: &Path, files: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let foreign_dir = install_dir.join(FOREIGN_DIR);
+    fs::create_dir_all(&foreign_dir).await?;
+    let mut moved = Vec::new();
+    for path in files {
+        let name = path.file_name().ok_or("Path has no file name")?;
+        let mut dest = foreign_dir.join(name);
+        let mut suffix = 1;
+        while fs::try_exists(&dest).await? {
+            let mut numbered = name.to_os_string();
+            numbered.push(format!(".{}", suffix));
+            dest = foreign_dir.join(numbered);
+            suffix += 1;
+        }
+        fs::rename(path, &dest).await?;
+        moved.push(dest);
+    }
+    Ok(moved)
+}
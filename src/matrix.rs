@@ -0,0 +1,75 @@
+use crate::{Platform, ReleaseTrack, Resp};
+use serde::Serialize;
+use std::fmt;
+use tokio::task::JoinSet;
+
+/// Latest release of one platform/track combination, or why it could not be
+/// fetched.
+#[derive(Serialize, Debug)]
+pub struct MatrixEntry {
+    pub platform: Platform,
+    pub release_track: ReleaseTrack,
+    pub release: Option<Resp>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(transparent)]
+pub struct Matrix(pub Vec<MatrixEntry>);
+
+impl fmt::Display for Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, entry) in self.0.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{:<12} {:<7} ",
+                entry.platform.to_string(),
+                entry.release_track.to_string()
+            )?;
+            match (&entry.release, &entry.error) {
+                (Some(release), _) => {
+                    write!(f, "{:<10} {}", release.version, release.download_url)?
+                }
+                (None, error) => write!(f, "error: {}", error.as_deref().unwrap_or("unknown"))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Queries the API for every platform/track combination concurrently. A
+/// failing combination is reported in its entry rather than failing the
+/// whole matrix.
+pub async fn fetch_all(client: &reqwest::Client, api_url: &str) -> Matrix {
+    let mut tasks = JoinSet::new();
+    let combinations = Platform::ALL
+        .into_iter()
+        .flat_map(|platform| ReleaseTrack::ALL.map(|track| (platform, track)));
+    for (index, (platform, release_track)) in combinations.enumerate() {
+        let client = client.clone();
+        let api_url = api_url.to_string();
+        tasks.spawn(async move {
+            let result = crate::fetch_metadata(&client, &api_url, platform, release_track)
+                .await
+                .map_err(|e| e.to_string());
+            let (release, error) = match result {
+                Ok(release) => (Some(release), None),
+                Err(e) => (None, Some(e)),
+            };
+            let entry = MatrixEntry {
+                platform,
+                release_track,
+                release,
+                error,
+            };
+            (index, entry)
+        });
+    }
+
+    let mut entries = tasks.join_all().await;
+    entries.sort_by_key(|(index, _)| *index);
+    Matrix(entries.into_iter().map(|(_, entry)| entry).collect())
+}
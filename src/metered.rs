@@ -0,0 +1,127 @@
+//! Metered connections (`metered_updates`): the daemon asks NetworkManager
+//! whether the primary connection is metered, e.g. a phone's hotspot, before
+//! it downloads a scheduled update of a few hundred MB. NetworkManager is
+//! asked over D-Bus with `busctl`, or `dbus-send` where systemd's tools are
+//! missing; without either, or without NetworkManager, the connection counts
+//! as unmetered.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// How long NetworkManager gets to answer.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MeteredPolicy {
+    /// Only report that an update is available.
+    #[default]
+    Notify,
+    /// Download and prepare the update for `cursorup commit`.
+    Stage,
+    /// Install as on any other connection.
+    Install,
+}
+
+impl FromStr for MeteredPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "notify" => Ok(MeteredPolicy::Notify),
+            "stage" => Ok(MeteredPolicy::Stage),
+            "install" => Ok(MeteredPolicy::Install),
+            _ => Err(format!(
+                "unknown metered policy `{}` (expected notify, stage or install)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for MeteredPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MeteredPolicy::Notify => "notify",
+            MeteredPolicy::Stage => "stage",
+            MeteredPolicy::Install => "install",
+        })
+    }
+}
+
+/// Whether NetworkManager considers the connection metered, either because
+/// it was set so or because it guessed so, as it does for hotspots.
+pub async fn is_metered() -> bool {
+    // NMMetered: 0 unknown, 1 yes, 2 no, 3 guess yes, 4 guess no.
+    matches!(metered_state().await, Some(1 | 3))
+}
+
+async fn metered_state() -> Option<u32> {
+    let busctl = query(
+        "busctl",
+        &[
+            "--system",
+            "get-property",
+            "org.freedesktop.NetworkManager",
+            "/org/freedesktop/NetworkManager",
+            "org.freedesktop.NetworkManager",
+            "Metered",
+        ],
+    );
+    // Prints `u 4`.
+    if let Some(output) = busctl.await {
+        return output.strip_prefix("u ")?.trim().parse().ok();
+    }
+    let output = query(
+        "dbus-send",
+        &[
+            "--system",
+            "--print-reply",
+            "--dest=org.freedesktop.NetworkManager",
+            "/org/freedesktop/NetworkManager",
+            "org.freedesktop.DBus.Properties.Get",
+            "string:org.freedesktop.NetworkManager",
+            "string:Metered",
+        ],
+    )
+    .await?;
+    // Prints the reply header, then `   variant       uint32 4`.
+    output
+        .split_once("uint32")?
+        .1
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Stdout of a successful `program args`, or `None` with the reason logged.
+async fn query(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    match tokio::time::timeout(QUERY_TIMEOUT, output).await {
+        Ok(Ok(output)) if output.status.success() => {
+            Some(String::from_utf8_lossy(&output.stdout).into_owned())
+        }
+        Ok(Ok(output)) => {
+            tracing::debug!(
+                "{} failed: {}",
+                program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            None
+        }
+        Ok(Err(e)) => {
+            tracing::debug!("cannot run {}: {}", program, e);
+            None
+        }
+        Err(_) => {
+            tracing::debug!("{} did not answer in time", program);
+            None
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// Counters and gauges the daemon exposes on `/metrics`.
+#[derive(Debug, Default, Clone)]
+pub struct Metrics {
+    pub last_check: Option<u64>,
+    pub last_update: Option<u64>,
+    pub installed_version: Option<String>,
+    pub installed_commit: Option<String>,
+    pub latest_version: Option<String>,
+    pub update_available: bool,
+    pub check_failures: u64,
+    pub update_failures: u64,
+}
+
+pub type SharedMetrics = Arc<Mutex<Metrics>>;
+
+impl Metrics {
+    /// Renders the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, help: &str, kind: &str, value: String| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            let _ = writeln!(out, "{}", value);
+        };
+
+        if let Some(last_check) = self.last_check {
+            metric(
+                "cursorup_last_check_timestamp_seconds",
+                "Unix time of the last successful update check.",
+                "gauge",
+                format!("cursorup_last_check_timestamp_seconds {}", last_check),
+            );
+        }
+        if let Some(last_update) = self.last_update {
+            metric(
+                "cursorup_last_update_timestamp_seconds",
+                "Unix time of the last successful update.",
+                "gauge",
+                format!("cursorup_last_update_timestamp_seconds {}", last_update),
+            );
+        }
+        if let Some(version) = &self.installed_version {
+            metric(
+                "cursorup_installed_info",
+                "Installed Cursor build.",
+                "gauge",
+                format!(
+                    "cursorup_installed_info{{version=\"{}\",commit=\"{}\"}} 1",
+                    escape(version),
+                    escape(self.installed_commit.as_deref().unwrap_or_default())
+                ),
+            );
+        }
+        if let Some(version) = &self.latest_version {
+            metric(
+                "cursorup_latest_info",
+                "Latest Cursor release on the configured track.",
+                "gauge",
+                format!("cursorup_latest_info{{version=\"{}\"}} 1", escape(version)),
+            );
+        }
+        metric(
+            "cursorup_update_available",
+            "Whether the installed Cursor is behind the latest release.",
+            "gauge",
+            format!(
+                "cursorup_update_available {}",
+                u8::from(self.update_available)
+            ),
+        );
+        metric(
+            "cursorup_check_failures_total",
+            "Update checks that failed.",
+            "counter",
+            format!("cursorup_check_failures_total {}", self.check_failures),
+        );
+        metric(
+            "cursorup_update_failures_total",
+            "Updates that failed.",
+            "counter",
+            format!("cursorup_update_failures_total {}", self.update_failures),
+        );
+        out
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Serves `GET /metrics` on `addr` until the process exits.
+pub async fn serve(addr: &str, metrics: SharedMetrics) -> std::io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    crate::events::log(format!(
+        "Serving metrics on http://{}/metrics",
+        listener.local_addr()?
+    ));
+    loop {
+        let (mut socket, _) = listener.accept().await?;
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            let mut request = [0u8; 1024];
+            let read = socket.read(&mut request).await.unwrap_or(0);
+            let request = String::from_utf8_lossy(&request[..read]);
+            let response = match request.split_whitespace().take(2).collect::<Vec<_>>()[..] {
+                ["GET", "/metrics"] => {
+                    let body = metrics
+                        .lock()
+                        .map(|metrics| metrics.render())
+                        .unwrap_or_default();
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                }
+                _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_string(),
+            };
+            let _ = socket.write_all(response.as_bytes()).await;
+        });
+    }
+}
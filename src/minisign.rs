@@ -0,0 +1,271 @@
+//! Verification of minisign signatures (both the legacy `Ed` and the
+//! prehashed `ED` algorithms), used for self-update artifacts.
+
+use crate::Result;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ring::signature::{ED25519, UnparsedPublicKey};
+
+pub struct PublicKey {
+    key_id: [u8; 8],
+    key: [u8; 32],
+}
+
+impl PublicKey {
+    /// Parses the base64 key as printed on the second line of a `.pub` file.
+    pub fn from_base64(encoded: &str) -> Result<PublicKey> {
+        let bytes = STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| format!("Invalid minisign public key: {}", e))?;
+        if bytes.len() != 42 || &bytes[..2] != b"Ed" {
+            return Err("Invalid minisign public key: unexpected length or algorithm".into());
+        }
+        Ok(PublicKey {
+            key_id: bytes[2..10].try_into()?,
+            key: bytes[10..42].try_into()?,
+        })
+    }
+
+    /// Checks a `.minisig` file's signature and trusted comment over `message`.
+    pub fn verify(&self, message: &[u8], signature_file: &str) -> Result<()> {
+        let lines: Vec<&str> = signature_file.lines().collect();
+        let [_untrusted, signature, trusted, global, ..] = lines.as_slice() else {
+            return Err("Malformed minisign signature: expected 4 lines".into());
+        };
+        let signature = STANDARD
+            .decode(signature.trim())
+            .map_err(|e| format!("Malformed minisign signature: {}", e))?;
+        if signature.len() != 74 {
+            return Err("Malformed minisign signature: unexpected length".into());
+        }
+        let (algorithm, key_id, signature) = (&signature[..2], &signature[2..10], &signature[10..]);
+        if key_id != self.key_id {
+            return Err("Signature was made with a different minisign key".into());
+        }
+
+        let key = UnparsedPublicKey::new(&ED25519, self.key);
+        let prehashed;
+        let signed: &[u8] = match algorithm {
+            b"Ed" => message,
+            b"ED" => {
+                prehashed = blake2b_512(message);
+                &prehashed
+            }
+            _ => return Err("Unsupported minisign signature algorithm".into()),
+        };
+        key.verify(signed, signature)
+            .map_err(|_| "Minisign signature does not match the artifact")?;
+
+        let trusted = trusted
+            .strip_prefix("trusted comment: ")
+            .ok_or("Malformed minisign signature: missing trusted comment")?;
+        let global = STANDARD
+            .decode(global.trim())
+            .map_err(|e| format!("Malformed minisign signature: {}", e))?;
+        let mut global_message = signature.to_vec();
+        global_message.extend_from_slice(trusted.as_bytes());
+        key.verify(&global_message, &global)
+            .map_err(|_| "Minisign trusted comment signature is invalid")?;
+        Ok(())
+    }
+}
+
+const IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// BLAKE2b with a 64-byte digest and no key (RFC 7693), as used by minisign
+/// for prehashed signatures.
+fn blake2b_512(message: &[u8]) -> [u8; 64] {
+    let mut h = IV;
+    h[0] ^= 0x0101_0000 ^ 64;
+
+    let mut compressed: u128 = 0;
+    let mut chunks = message.chunks(128).peekable();
+    if chunks.peek().is_none() {
+        compress(&mut h, &[0; 128], 0, true);
+    }
+    while let Some(chunk) = chunks.next() {
+        let mut block = [0u8; 128];
+        block[..chunk.len()].copy_from_slice(chunk);
+        compressed += chunk.len() as u128;
+        compress(&mut h, &block, compressed, chunks.peek().is_none());
+    }
+
+    let mut out = [0u8; 64];
+    for (word, bytes) in h.iter().zip(out.chunks_mut(8)) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+    out
+}
+
+fn compress(h: &mut [u64; 8], block: &[u8; 128], counter: u128, last: bool) {
+    let mut m = [0u64; 16];
+    for (word, bytes) in m.iter_mut().zip(block.chunks(8)) {
+        *word = u64::from_le_bytes(bytes.try_into().unwrap_or_default());
+    }
+    let mut v = [0u64; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&IV);
+    v[12] ^= counter as u64;
+    v[13] ^= (counter >> 64) as u64;
+    if last {
+        v[14] = !v[14];
+    }
+
+    for round in 0..12 {
+        let s = &SIGMA[round % 10];
+        mix(&mut v, 0, 4, 8, 12, m[s[0]], m[s[1]]);
+        mix(&mut v, 1, 5, 9, 13, m[s[2]], m[s[3]]);
+        mix(&mut v, 2, 6, 10, 14, m[s[4]], m[s[5]]);
+        mix(&mut v, 3, 7, 11, 15, m[s[6]], m[s[7]]);
+        mix(&mut v, 0, 5, 10, 15, m[s[8]], m[s[9]]);
+        mix(&mut v, 1, 6, 11, 12, m[s[10]], m[s[11]]);
+        mix(&mut v, 2, 7, 8, 13, m[s[12]], m[s[13]]);
+        mix(&mut v, 3, 4, 9, 14, m[s[14]], m[s[15]]);
+    }
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i + 8];
+    }
+}
+
+fn mix(v: &mut [u64; 16], a: usize, b: usize, c: usize, d: usize, x: u64, y: u64) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn blake2b_512_vectors() {
+        // RFC 7693, appendix A, and hashlib.blake2b.
+        assert_eq!(
+            hex(&blake2b_512(b"")),
+            "786a02f742015903c6c6fd852552d272912f4740e15847618a86e217f71f5419d25e1031afee585313896444934eb04b903a685b1448b755d56f701afe9be2ce"
+        );
+        assert_eq!(
+            hex(&blake2b_512(b"abc")),
+            "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d17d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+        );
+    }
+
+    #[test]
+    fn blake2b_512_block_boundaries() {
+        let block: Vec<u8> = (0..128).collect();
+        assert_eq!(
+            hex(&blake2b_512(&block)),
+            "2319e3789c47e2daa5fe807f61bec2a1a6537fa03f19ff32e87eecbfd64b7e0e8ccff439ac333b040f19b0c4ddd11a61e24ac1fe0f10a039806c5dcc0da3d115"
+        );
+        let longer: Vec<u8> = (0..129).map(|i| (i % 251) as u8).collect();
+        assert_eq!(
+            hex(&blake2b_512(&longer)),
+            "f59711d44a031d5f97a9413c065d1e614c417ede998590325f49bad2fd444d3e4418be19aec4e11449ac1a57207898bc57d76a1bcf3566292c20c683a5c4648f"
+        );
+    }
+
+    const PUBLIC_KEY: &str = "RWQBI0VniavN7wOhB7/zzhC+HXDdGOdLwJln5NYwm6UNXx3chmQSVTG4";
+    const MESSAGE: &[u8] = b"cursorup test artifact\n";
+    const TRUSTED: &str = "trusted comment: timestamp:1700000000\tfile:cursorup";
+
+    fn signature_file(signature: &str, trusted: &str, global: &str) -> String {
+        format!(
+            "untrusted comment: signature from minisign secret key\n{}\n{}\n{}\n",
+            signature, trusted, global
+        )
+    }
+
+    fn legacy() -> String {
+        signature_file(
+            "RWQBI0VniavN7ypas2Jzo+NWrIXEiKZHrGlPQE3m5RiP5PjQExVcarEqdK00vGqf4NHqeGTWK3KmgWGxfN+F4lekk0twuZ3AmQs=",
+            TRUSTED,
+            "GRju177hmGFEamj2PIOoCgVGkNZKU9roEzvzoMYG8C73GAHE1ZSGPq8fDiMHnHG9eRQyNl6uWDg/MuVLUWyeAQ==",
+        )
+    }
+
+    fn prehashed() -> String {
+        signature_file(
+            "RUQBI0VniavN77Pa9D0hcxe6BlgLdoslkSAy8tMzijYTdj46lZOb8Jgb+iBZy95joVXpxH2HHHTtriEbqOnTKZOfWPw0fU+3LAc=",
+            TRUSTED,
+            "oYXwsMENnnmBMnf2VBctpbpMxMBcDzXWJCkd5qCJUY01C9zRhTvzjn0CLj0iPsSFBA0361J53iRMyVZx3vwfCQ==",
+        )
+    }
+
+    #[test]
+    fn verifies_both_algorithms() {
+        let key = PublicKey::from_base64(PUBLIC_KEY).unwrap();
+        key.verify(MESSAGE, &legacy()).unwrap();
+        key.verify(MESSAGE, &prehashed()).unwrap();
+    }
+
+    #[test]
+    fn rejects_a_changed_artifact() {
+        let key = PublicKey::from_base64(PUBLIC_KEY).unwrap();
+        assert!(key.verify(b"cursorup test artifact!\n", &legacy()).is_err());
+        assert!(
+            key.verify(b"cursorup test artifact!\n", &prehashed())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn rejects_a_changed_trusted_comment() {
+        let key = PublicKey::from_base64(PUBLIC_KEY).unwrap();
+        let forged = prehashed().replace("1700000000", "1800000000");
+        assert!(key.verify(MESSAGE, &forged).is_err());
+    }
+
+    #[test]
+    fn rejects_another_key() {
+        // Same key bytes under a different key id.
+        let other = PublicKey {
+            key_id: [0; 8],
+            ..PublicKey::from_base64(PUBLIC_KEY).unwrap()
+        };
+        assert!(other.verify(MESSAGE, &legacy()).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(PublicKey::from_base64("not base64!").is_err());
+        assert!(PublicKey::from_base64("RWQB").is_err());
+        let key = PublicKey::from_base64(PUBLIC_KEY).unwrap();
+        assert!(key.verify(MESSAGE, "untrusted comment: x\n").is_err());
+    }
+
+    #[test]
+    fn release_key_parses() {
+        assert!(PublicKey::from_base64(crate::selfupdate::RELEASE_PUBKEY).is_ok());
+    }
+}
@@ -0,0 +1,270 @@
+use crate::Result;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Output format for the reporting commands (`status`, `info`, `plan`).
+/// `Csv` only applies to list reports such as `history export`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+    Yaml,
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "yaml" => Ok(Format::Yaml),
+            "csv" => Ok(Format::Csv),
+            _ => Err(format!(
+                "unknown format `{}` (expected text, json, yaml or csv)",
+                s
+            )),
+        }
+    }
+}
+
+/// Renders a report in the requested format; `Text` uses its `Display` impl.
+pub fn render<T: Serialize + Display>(value: &T, format: Format) -> Result<String> {
+    match format {
+        Format::Text => Ok(value.to_string()),
+        Format::Json => Ok(serde_json::to_string_pretty(value)?),
+        Format::Yaml => Ok(to_yaml(&serde_json::to_value(value)?)),
+        Format::Csv => to_csv(&serde_json::to_value(value)?),
+    }
+}
+
+/// Serializes a list of flat objects as CSV with a header row taken from the
+/// first object's keys. Nested values are embedded as JSON.
+pub fn to_csv(value: &Value) -> Result<String> {
+    let Value::Array(rows) = value else {
+        return Err("CSV output is only available for lists".into());
+    };
+    let Some(Value::Object(first)) = rows.first() else {
+        return Ok(String::new());
+    };
+    let columns: Vec<&String> = first.keys().collect();
+    let mut out = String::new();
+    let header: Vec<String> = columns.iter().map(|column| csv_field(column)).collect();
+    out.push_str(&header.join(","));
+    out.push('\n');
+    for row in rows {
+        let fields: Vec<String> = columns
+            .iter()
+            .map(|column| match row.get(column.as_str()) {
+                None | Some(Value::Null) => String::new(),
+                Some(Value::String(s)) => csv_field(s),
+                Some(other) => csv_field(&other.to_string()),
+            })
+            .collect();
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Serializes a JSON value as a YAML document. Strings that could be mistaken
+/// for other scalars are emitted as double-quoted (JSON-compatible) literals.
+pub fn to_yaml(value: &Value) -> String {
+    let mut out = String::new();
+    match value {
+        Value::Array(items) if !items.is_empty() => write_seq(&mut out, items, 0),
+        Value::Object(map) if !map.is_empty() => write_map(&mut out, map, 0),
+        scalar => {
+            out.push_str(&yaml_scalar(scalar));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn write_map(out: &mut String, map: &serde_json::Map<String, Value>, indent: usize) {
+    for (key, value) in map {
+        out.push_str(&" ".repeat(indent));
+        out.push_str(&yaml_string(key));
+        out.push(':');
+        write_nested(out, value, indent);
+    }
+}
+
+fn write_seq(out: &mut String, items: &[Value], indent: usize) {
+    for item in items {
+        out.push_str(&" ".repeat(indent));
+        out.push('-');
+        match item {
+            Value::Object(map) if !map.is_empty() => {
+                // The first key shares the line with the dash.
+                let mut nested = String::new();
+                write_map(&mut nested, map, indent + 2);
+                out.push(' ');
+                out.push_str(&nested[indent + 2..]);
+            }
+            _ => write_nested(out, item, indent),
+        }
+    }
+}
+
+fn write_nested(out: &mut String, value: &Value, indent: usize) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            out.push('\n');
+            write_map(out, map, indent + 2);
+        }
+        Value::Array(items) if !items.is_empty() => {
+            out.push('\n');
+            write_seq(out, items, indent + 2);
+        }
+        scalar => {
+            out.push(' ');
+            out.push_str(&yaml_scalar(scalar));
+            out.push('\n');
+        }
+    }
+}
+
+fn yaml_scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => yaml_string(s),
+        Value::Array(_) => "[]".to_string(),
+        Value::Object(_) => "{}".to_string(),
+    }
+}
+
+fn yaml_string(s: &str) -> String {
+    let plain = !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._/-+@()".contains(c))
+        && !s.starts_with(['-', '.', '@'])
+        && s.parse::<f64>().is_err()
+        && !is_radix_or_date(s)
+        && !matches!(
+            s.to_ascii_lowercase().as_str(),
+            "true" | "false" | "yes" | "no" | "on" | "off" | "null" | "y" | "n" | "~"
+        );
+    if plain {
+        s.to_string()
+    } else {
+        Value::String(s.to_string()).to_string()
+    }
+}
+
+/// Plain scalars YAML 1.1 resolves to an integer or a timestamp although
+/// `f64` doesn't parse them: `0x1f`, `0o17`, `0b101` and `2024-01-01`.
+/// Only called on ASCII strings.
+fn is_radix_or_date(s: &str) -> bool {
+    let unsigned = s.strip_prefix(['-', '+']).unwrap_or(s).to_ascii_lowercase();
+    let bytes = s.as_bytes();
+    ["0x", "0o", "0b"]
+        .iter()
+        .any(|prefix| unsigned.starts_with(prefix))
+        || (bytes.len() > 5
+            && bytes[..4].iter().all(u8::is_ascii_digit)
+            && bytes[4] == b'-'
+            && bytes[5].is_ascii_digit())
+}
+
+/// Formats seconds since the Unix epoch as `YYYY-MM-DD HH:MM UTC`.
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let (hour, minute) = ((secs % 86_400) / 3600, (secs % 3600) / 60);
+
+    // Civil-from-days conversion (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02} UTC",
+        year, month, day, hour, minute
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn quotes_strings_yaml_would_retype() {
+        for s in [
+            "2024-01-01",
+            "0x1f",
+            "0O17",
+            "-0b101",
+            "1e3",
+            "017",
+            "+1",
+            ".inf",
+            "on",
+            "Yes",
+            "NULL",
+            "~",
+            "",
+            "-",
+            "a: b",
+            "#x",
+        ] {
+            assert_eq!(yaml_string(s), format!("{:?}", s), "{}", s);
+        }
+    }
+
+    #[test]
+    fn leaves_plain_strings_alone() {
+        for s in [
+            "1.2.3",
+            "linux-x64",
+            "stable",
+            "a/b@c",
+            "v2024-01-01",
+            "2024.1.5",
+        ] {
+            assert_eq!(yaml_string(s), s, "{}", s);
+        }
+    }
+
+    #[test]
+    fn nests_maps_and_sequences() {
+        let value = json!({
+            "version": "1.2.3",
+            "released": "2024-01-01",
+            "size": 10,
+            "tags": ["on", "x"],
+            "builds": [{"platform": "linux-x64", "sha": null}],
+            "empty": {},
+        });
+        assert_eq!(
+            to_yaml(&value),
+            "version: 1.2.3\n\
+             released: \"2024-01-01\"\n\
+             size: 10\n\
+             tags:\n  - \"on\"\n  - x\n\
+             builds:\n  - platform: linux-x64\n    sha: null\n\
+             empty: {}\n"
+        );
+    }
+}
@@ -0,0 +1,285 @@
+use crate::config::Config;
+use crate::{InstallOptions, Resp, Result};
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// What an update would do, computed from the metadata without side effects.
+/// `plan --format json` prints it for orchestration tools to review before
+/// the nodes install.
+#[derive(Serialize, Debug)]
+pub struct Plan {
+    pub current_version: Option<String>,
+    pub target_version: String,
+    /// The target is older than what is installed.
+    pub downgrade: bool,
+    pub commit_sha: String,
+    pub download_url: String,
+    pub install_dir: PathBuf,
+    pub downloads: Vec<PlannedDownload>,
+    /// Files of the current install moved into `back/`.
+    pub backups: Vec<Move>,
+    /// Files of the current install deleted instead, with `--no-backup`.
+    pub removes: Vec<PathBuf>,
+    /// Files cursorup did not install; moved to `foreign/` only on confirmation.
+    pub foreign: Vec<PathBuf>,
+    pub writes: Vec<PathBuf>,
+    /// The desktop entry as it would be written, if `desktop_entry` is on.
+    pub desktop_entry: Option<PlannedEntry>,
+}
+
+/// A file the update fetches, with what it is expected to be. `size` and
+/// `sha256` are `None` where neither the server nor a checksum source says.
+#[derive(Serialize, Debug)]
+pub struct PlannedDownload {
+    pub url: String,
+    /// Where the download is kept in the cache.
+    pub path: PathBuf,
+    pub size: Option<u64>,
+    pub sha256: Option<String>,
+    /// Where `sha256` comes from, e.g. a `.sha256` file on the CDN.
+    pub sha256_source: Option<String>,
+    /// A complete download is in the cache already, so nothing is fetched.
+    pub cached: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PlannedEntry {
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Move {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} {} -> {} ({})",
+            if self.downgrade {
+                "Downgrade"
+            } else {
+                "Update"
+            },
+            self.current_version.as_deref().unwrap_or("nothing"),
+            self.target_version,
+            self.commit_sha
+        )?;
+        for download in &self.downloads {
+            let size = download
+                .size
+                .map(|size| format!("{:.2} MB", size as f64 / 1_048_576.0))
+                .unwrap_or_else(|| "size unknown".to_string());
+            let sha256 = download.sha256.as_deref().unwrap_or("sha256 unknown");
+            if download.cached {
+                writeln!(
+                    f,
+                    "Cached:   {} ({}, {})",
+                    download.path.display(),
+                    size,
+                    sha256
+                )?;
+            } else {
+                writeln!(f, "Download: {} ({}, {})", download.url, size, sha256)?;
+            }
+        }
+        for backup in &self.backups {
+            writeln!(
+                f,
+                "Back up:  {} -> {}",
+                backup.from.display(),
+                backup.to.display()
+            )?;
+        }
+        for path in &self.removes {
+            writeln!(f, "Remove:   {}", path.display())?;
+        }
+        for path in &self.foreign {
+            writeln!(
+                f,
+                "Foreign:  {} (left alone unless confirmed)",
+                path.display()
+            )?;
+        }
+        for (index, path) in self.writes.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "Write:    {}", path.display())?;
+        }
+        if let Some(entry) = &self.desktop_entry {
+            write!(f, "\nDesktop entry {}:", entry.path.display())?;
+            for line in entry.contents.lines() {
+                write!(f, "\n  {}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub async fn plan(
+    config: &Config,
+    client: &reqwest::Client,
+    metadata: &Resp,
+    options: &InstallOptions,
+) -> Result<Plan> {
+    let current_version = crate::status::installed_appimage(&config.install_dir)
+        .await?
+        .and_then(|path| crate::status::version_from_file_name(&path));
+
+    let dest_dir = &config.install_dir;
+    let appimage_dest_path = dest_dir.join(crate::urls::file_name_from_url(&metadata.download_url));
+    let mut writes = vec![
+        crate::provenance::checksum_path(&appimage_dest_path),
+        appimage_dest_path.clone(),
+        dest_dir.join(crate::provenance::PROVENANCE_FILE),
+        dest_dir.join(crate::manifest::MANIFEST_FILE),
+        dest_dir.join("code.png"),
+        dest_dir.join(crate::wrapper::CURRENT_LINK),
+    ];
+    if config.cli_wrapper {
+        writes.push(crate::wrapper::wrapper_path()?);
+    }
+    let desktop_entry = if config.desktop_entry {
+        let path = crate::desktop_entry_path(config.system_install)?;
+        writes.push(path.clone());
+        let contents = if config.system_install {
+            crate::system::desktop_entry_contents(config)
+        } else {
+            crate::desktop_entry_contents(
+                &config.desktop_name,
+                &appimage_dest_path,
+                &dest_dir.join("code.png"),
+                &config.launch_args(),
+            )
+        };
+        Some(PlannedEntry { path, contents })
+    } else {
+        None
+    };
+
+    let classified = crate::manifest::classify(dest_dir).await?;
+    let (backups, removes) = if options.no_backup {
+        (Vec::new(), classified.tracked)
+    } else {
+        let backups = classified
+            .tracked
+            .into_iter()
+            .map(|from| Move {
+                to: backup_path(dest_dir, &from, config.compress_backups),
+                from,
+            })
+            .collect();
+        (backups, Vec::new())
+    };
+    Ok(Plan {
+        downgrade: current_version.as_deref().is_some_and(|current| {
+            crate::version::compare(current, &metadata.version).is_some_and(|o| o.is_gt())
+        }),
+        current_version,
+        target_version: metadata.version.clone(),
+        commit_sha: metadata.commit_sha.clone(),
+        download_url: metadata.download_url.clone(),
+        install_dir: dest_dir.clone(),
+        downloads: vec![planned_download(config, client, metadata, options).await?],
+        backups,
+        removes,
+        foreign: classified.foreign,
+        writes,
+        desktop_entry,
+    })
+}
+
+/// The AppImage download as `fetch_build` would make it: from the mirror if
+/// one is set, skipped when the cache already holds it.
+async fn planned_download(
+    config: &Config,
+    client: &reqwest::Client,
+    metadata: &Resp,
+    options: &InstallOptions,
+) -> Result<PlannedDownload> {
+    let mut metadata = metadata.clone();
+    metadata.download_url =
+        crate::urls::mirrored(&metadata.download_url, config.download_base.as_deref());
+    let url = metadata.download_url.clone();
+    let path = crate::cache::artifact_path(
+        config.platform,
+        &metadata.version,
+        crate::urls::file_name_from_url(&url),
+    )?;
+    let published =
+        crate::checksums::published(client, config, options.sha256.as_deref(), &metadata).await?;
+    let recorded = if options.no_cache {
+        None
+    } else {
+        crate::provenance::read_checksum(&crate::provenance::checksum_path(&path))
+            .await
+            .ok()
+            .flatten()
+    };
+    let cached = recorded.is_some();
+    let size = if cached {
+        tokio::fs::metadata(&path).await.ok().map(|m| m.len())
+    } else {
+        remote_size(client, &url).await
+    };
+    let (sha256, sha256_source) = match (published, recorded) {
+        (Some(published), _) => (Some(published.sha256), Some(published.source)),
+        (None, Some(recorded)) => (
+            Some(recorded.sha256),
+            Some("the checksum recorded when it was cached".to_string()),
+        ),
+        _ => (None, None),
+    };
+    Ok(PlannedDownload {
+        url,
+        path,
+        size,
+        sha256,
+        sha256_source,
+        cached,
+    })
+}
+
+/// `Content-Length` of `url` from a `HEAD` request, if the server gives one.
+async fn remote_size(client: &reqwest::Client, url: &str) -> Option<u64> {
+    let response = match client
+        .head(url)
+        .header(reqwest::header::ACCEPT_ENCODING, "identity")
+        .send()
+        .await
+    {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => {
+            tracing::debug!("HEAD {}: {}", url, response.status());
+            return None;
+        }
+        Err(e) => {
+            tracing::debug!("HEAD {}: {}", url, e);
+            return None;
+        }
+    };
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Where `back_file` moves `path`.
+fn backup_path(install_dir: &Path, path: &Path, compress: bool) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    let backup = install_dir.join(crate::backup::BACKUP_DIR).join(name);
+    if compress && path.extension().and_then(|s| s.to_str()) == Some("AppImage") {
+        crate::backup::compressed_path(&backup)
+    } else {
+        backup
+    }
+}
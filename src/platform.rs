@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Build target as named by the download API (`platform=linux-x64`).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Platform {
+    #[default]
+    #[serde(rename = "linux-x64")]
+    LinuxX64,
+    #[serde(rename = "linux-arm64")]
+    LinuxArm64,
+}
+
+impl Platform {
+    pub const ALL: [Platform; 2] = [Platform::LinuxX64, Platform::LinuxArm64];
+
+    /// Architecture segment of CDN paths and REH tarball names.
+    pub fn cdn_arch(self) -> &'static str {
+        match self {
+            Platform::LinuxX64 => "x64",
+            Platform::LinuxArm64 => "arm64",
+        }
+    }
+
+    /// The build for the machine cursorup runs on, or `None` on
+    /// architectures Cursor has no Linux build for.
+    pub fn host() -> Option<Platform> {
+        match std::env::consts::ARCH {
+            "x86_64" => Some(Platform::LinuxX64),
+            "aarch64" => Some(Platform::LinuxArm64),
+            _ => None,
+        }
+    }
+
+    /// Architecture suffix of AppImage file names.
+    pub fn appimage_arch(self) -> &'static str {
+        match self {
+            Platform::LinuxX64 => "x86_64",
+            Platform::LinuxArm64 => "aarch64",
+        }
+    }
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "linux-x64" => Ok(Platform::LinuxX64),
+            "linux-arm64" => Ok(Platform::LinuxArm64),
+            _ => Err(format!(
+                "unknown platform `{}` (expected linux-x64 or linux-arm64)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Platform::LinuxX64 => "linux-x64",
+            Platform::LinuxArm64 => "linux-arm64",
+        })
+    }
+}
+
+/// Release channel as named by the download API (`releaseTrack=stable`).
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseTrack {
+    #[default]
+    Stable,
+    Latest,
+}
+
+impl ReleaseTrack {
+    pub const ALL: [ReleaseTrack; 2] = [ReleaseTrack::Stable, ReleaseTrack::Latest];
+}
+
+impl FromStr for ReleaseTrack {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "stable" => Ok(ReleaseTrack::Stable),
+            "latest" => Ok(ReleaseTrack::Latest),
+            _ => Err(format!(
+                "unknown release track `{}` (expected stable or latest)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ReleaseTrack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ReleaseTrack::Stable => "stable",
+            ReleaseTrack::Latest => "latest",
+        })
+    }
+}
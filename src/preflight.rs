@@ -0,0 +1,264 @@
+use crate::Result;
+use crate::config::Config;
+use crate::http::IpVersion;
+use serde::Serialize;
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+/// How long each stage may take before it counts as failed.
+const STAGE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    Dns,
+    Tcp,
+    Tls,
+    Http,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Stage::Dns => "DNS",
+            Stage::Tcp => "TCP",
+            Stage::Tls => "TLS",
+            Stage::Http => "HTTP",
+        })
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Probe {
+    pub stage: Stage,
+    pub ok: bool,
+    pub detail: String,
+    pub elapsed_ms: u64,
+}
+
+/// Reachability of the API host, one probe per network layer. Probing stops
+/// at the first failing stage, since later stages depend on it. `doctor`
+/// also reports whether the `cursor` command is on `PATH`.
+#[derive(Serialize, Debug)]
+pub struct Preflight {
+    pub host: String,
+    pub probes: Vec<Probe>,
+    /// Whether the `cursor` command will be found, when it is installed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<crate::wrapper::PathCheck>,
+}
+
+impl Preflight {
+    pub fn ok(&self) -> bool {
+        self.probes.iter().all(|probe| probe.ok)
+    }
+
+    /// The first failure as a one-line, categorized message.
+    pub fn error(&self) -> Option<String> {
+        let failed = self.probes.iter().find(|probe| !probe.ok)?;
+        let what = match failed.stage {
+            Stage::Dns => "DNS resolution failed",
+            Stage::Tcp => "TCP connection failed",
+            Stage::Tls => "TLS handshake failed",
+            Stage::Http => "HTTP request failed",
+        };
+        Some(format!("{} for {}: {}", what, self.host, failed.detail))
+    }
+
+    fn push(
+        &mut self,
+        stage: Stage,
+        result: std::result::Result<String, String>,
+        started: Instant,
+    ) {
+        let ok = result.is_ok();
+        self.probes.push(Probe {
+            stage,
+            ok,
+            detail: result.unwrap_or_else(|e| e),
+            elapsed_ms: started.elapsed().as_millis() as u64,
+        });
+    }
+}
+
+impl fmt::Display for Preflight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Connectivity to {}:", self.host)?;
+        for probe in &self.probes {
+            write!(
+                f,
+                "\n  {:<4} {:<4} {:>5} ms  {}",
+                probe.stage.to_string(),
+                if probe.ok { "ok" } else { "FAIL" },
+                probe.elapsed_ms,
+                probe.detail
+            )?;
+        }
+        match &self.path {
+            Some(path) if path.on_path => {
+                write!(f, "\nCLI wrapper: {} is on PATH", path.dir.display())?
+            }
+            Some(path) => write!(f, "\nCLI wrapper: {}", path.instructions())?,
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+/// Probes DNS, TCP, TLS and HTTP against the API host, honouring the
+/// configured IP version and `resolve` overrides.
+pub async fn probe(config: &Config) -> Result<Preflight> {
+    let url = reqwest::Url::parse(&config.api_url)
+        .map_err(|e| format!("invalid api_url `{}`: {}", config.api_url, e))?;
+    let host = url.host_str().ok_or("API URL has no host")?.to_string();
+    let port = url.port_or_known_default().unwrap_or(443);
+    let mut preflight = Preflight {
+        host: host.clone(),
+        probes: Vec::new(),
+        path: config
+            .cli_wrapper
+            .then(crate::wrapper::PathCheck::new)
+            .transpose()?,
+    };
+
+    // Through a proxy the host may not even resolve locally; the request
+    // below tells whether the proxy gets through.
+    if let Some(proxy) = crate::http::proxy(config) {
+        let detail = format!("skipped, connecting through {}", proxy);
+        preflight.push(Stage::Dns, Ok(detail.clone()), Instant::now());
+        preflight.push(Stage::Tcp, Ok(detail), Instant::now());
+    } else if !probe_direct(config, &host, port, &mut preflight).await {
+        return Ok(preflight);
+    }
+
+    // reqwest performs the TLS handshake and request in one step; with TCP
+    // known to work, a connect error can only come from the handshake.
+    let started = Instant::now();
+    let client = crate::http::client(config)?;
+    let response = timeout(STAGE_TIMEOUT, client.head(&config.api_url).send()).await;
+    match response {
+        Err(_) => preflight.push(Stage::Tls, Err("timed out".to_string()), started),
+        Ok(Err(e)) if e.is_connect() => {
+            let mut message = error_chain(&e);
+            if message.contains("certificate") && config.ca_cert.is_none() {
+                message.push_str(
+                    "; if a proxy re-signs TLS traffic, pass its CA certificate with --ca-cert",
+                );
+            }
+            preflight.push(Stage::Tls, Err(message), started)
+        }
+        Ok(Err(e)) => {
+            preflight.push(Stage::Tls, Ok("handshake completed".to_string()), started);
+            preflight.push(Stage::Http, Err(error_chain(&e)), started);
+        }
+        Ok(Ok(response)) => {
+            preflight.push(Stage::Tls, Ok("handshake completed".to_string()), started);
+            let status = response.status();
+            let result = if status.is_server_error() {
+                Err(format!("server answered {}", status))
+            } else {
+                Ok(format!("server answered {}", status))
+            };
+            preflight.push(Stage::Http, result, started);
+        }
+    }
+    Ok(preflight)
+}
+
+/// DNS and TCP probes of a direct connection. Returns whether they passed.
+async fn probe_direct(config: &Config, host: &str, port: u16, preflight: &mut Preflight) -> bool {
+    let started = Instant::now();
+    let addrs = resolve(config, host, port).await;
+    let addrs = match addrs {
+        Ok(addrs) => {
+            let detail = addrs
+                .iter()
+                .map(|addr| addr.ip().to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            preflight.push(Stage::Dns, Ok(detail), started);
+            addrs
+        }
+        Err(e) => {
+            preflight.push(Stage::Dns, Err(e), started);
+            return false;
+        }
+    };
+
+    let started = Instant::now();
+    let connected = connect(&addrs).await;
+    let tcp_ok = connected.is_ok();
+    preflight.push(Stage::Tcp, connected, started);
+    tcp_ok
+}
+
+/// Runs the probe and turns a failure into an error, for use before long
+/// operations.
+pub async fn require(config: &Config) -> Result<()> {
+    let preflight = probe(config).await?;
+    match preflight.error() {
+        Some(error) => Err(error.into()),
+        None => Ok(()),
+    }
+}
+
+async fn resolve(
+    config: &Config,
+    host: &str,
+    port: u16,
+) -> std::result::Result<Vec<SocketAddr>, String> {
+    let overridden: Vec<SocketAddr> = config
+        .resolve
+        .iter()
+        .filter(|entry| entry.host == host && entry.port == port)
+        .map(|entry| SocketAddr::new(entry.addr, entry.port))
+        .collect();
+    let addrs = if overridden.is_empty() {
+        timeout(STAGE_TIMEOUT, tokio::net::lookup_host((host, port)))
+            .await
+            .map_err(|_| "timed out".to_string())?
+            .map_err(|e| e.to_string())?
+            .collect()
+    } else {
+        overridden
+    };
+    let addrs: Vec<SocketAddr> = addrs
+        .into_iter()
+        .filter(|addr| match config.ip_version {
+            IpVersion::Any => true,
+            IpVersion::V4 => addr.is_ipv4(),
+            IpVersion::V6 => addr.is_ipv6(),
+        })
+        .collect();
+    if addrs.is_empty() {
+        return Err(format!("no {} addresses", config.ip_version));
+    }
+    Ok(addrs)
+}
+
+async fn connect(addrs: &[SocketAddr]) -> std::result::Result<String, String> {
+    let mut last_error = String::new();
+    for addr in addrs {
+        match timeout(STAGE_TIMEOUT, TcpStream::connect(addr)).await {
+            Ok(Ok(_)) => return Ok(format!("connected to {}", addr)),
+            Ok(Err(e)) => last_error = format!("{}: {}", addr, e),
+            Err(_) => last_error = format!("{}: timed out", addr),
+        }
+    }
+    Err(last_error)
+}
+
+/// reqwest's top-level message is generic; the cause chain says what broke.
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
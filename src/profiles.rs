@@ -0,0 +1,209 @@
+//! Extra Cursor launchers with their own user-data and extensions dirs, so
+//! e.g. work and personal setups don't share settings or extensions. Each
+//! profile gets a desktop entry and a `cursor-<name>` wrapper starting the
+//! same installed AppImage; cursorup records them in
+//! `~/.local/share/cursorup/profiles.json` to refresh and remove them later.
+//! Not to be confused with `[profile.<name>]` sections of the config.
+
+use crate::config::Config;
+use crate::state::State;
+use crate::{Result, events, fsutil};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+const PROFILES_FILE: &str = "profiles.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Profile {
+    pub name: String,
+    pub user_data_dir: PathBuf,
+    pub extensions_dir: PathBuf,
+    /// Files cursorup wrote for the profile and removes with it.
+    pub desktop_entry: PathBuf,
+    pub wrapper: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(transparent)]
+pub struct Profiles(pub Vec<Profile>);
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Profile:        {}", self.name)?;
+        writeln!(f, "User data:      {}", self.user_data_dir.display())?;
+        writeln!(f, "Extensions:     {}", self.extensions_dir.display())?;
+        writeln!(f, "Desktop entry:  {}", self.desktop_entry.display())?;
+        write!(f, "Command:        {}", self.wrapper.display())
+    }
+}
+
+impl fmt::Display for Profiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(
+                f,
+                "No profiles. Add one with `cursorup profile add <name> --user-data-dir <dir>`."
+            );
+        }
+        for (index, profile) in self.0.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{:<12} {}",
+                profile.name,
+                profile.user_data_dir.display()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Profiles {
+    pub(crate) fn path() -> Result<PathBuf> {
+        Ok(State::dir()?.join(PROFILES_FILE))
+    }
+
+    pub async fn load() -> Result<Profiles> {
+        let path = Self::path()?;
+        match fs::read_to_string(&path).await {
+            Ok(contents) => Ok(serde_json::from_str(&contents)
+                .map_err(|e| format!("{}: invalid profiles file: {}", path.display(), e))?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Profiles::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn save(&self) -> Result<()> {
+        fsutil::write_atomic(&Self::path()?, serde_json::to_string_pretty(self)?).await
+    }
+}
+
+/// Adds or redefines the profile `name` and writes its launchers.
+/// `extensions_dir` defaults to `extensions/` inside `user_data_dir`.
+pub async fn add(
+    config: &Config,
+    name: &str,
+    user_data_dir: &Path,
+    extensions_dir: Option<&Path>,
+) -> Result<Profile> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "invalid profile name `{}` (use letters, digits, `-` and `_`)",
+            name
+        )
+        .into());
+    }
+    let user_data_dir = absolute(user_data_dir)?;
+    let extensions_dir = match extensions_dir {
+        Some(dir) => absolute(dir)?,
+        None => user_data_dir.join("extensions"),
+    };
+    let home = crate::home_dir()?;
+    let profile = Profile {
+        name: name.to_string(),
+        user_data_dir,
+        extensions_dir,
+        desktop_entry: home.join(format!(".local/share/applications/cursor-{}.desktop", name)),
+        wrapper: home.join(format!(".local/bin/cursor-{}", name)),
+    };
+    write_launchers(config, &profile).await?;
+
+    let mut profiles = Profiles::load().await?;
+    profiles.0.retain(|existing| existing.name != name);
+    profiles.0.push(profile.clone());
+    profiles.0.sort_by(|a, b| a.name.cmp(&b.name));
+    profiles.save().await?;
+    Ok(profile)
+}
+
+/// Removes the launchers of profile `name`. Its user data is left alone.
+pub async fn remove(name: &str) -> Result<Profile> {
+    let mut profiles = Profiles::load().await?;
+    let index = profiles
+        .0
+        .iter()
+        .position(|profile| profile.name == name)
+        .ok_or_else(|| format!("unknown profile `{}`", name))?;
+    let profile = profiles.0.remove(index);
+    for path in [&profile.desktop_entry, &profile.wrapper] {
+        match fs::remove_file(path).await {
+            Ok(()) => events::log(format!("Removed {:?}", path)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    profiles.save().await?;
+    events::log(format!(
+        "Removed profile {}; its data in {:?} was kept",
+        profile.name, profile.user_data_dir
+    ));
+    Ok(profile)
+}
+
+/// Rewrites the launchers of every profile, e.g. after an update changed the
+/// execution mode's launch flags.
+pub async fn refresh(config: &Config) -> Result<()> {
+    for profile in Profiles::load().await?.0 {
+        write_launchers(config, &profile).await?;
+    }
+    Ok(())
+}
+
+async fn write_launchers(config: &Config, profile: &Profile) -> Result<()> {
+    let user_data_dir = profile.user_data_dir.to_string_lossy();
+    let extensions_dir = profile.extensions_dir.to_string_lossy();
+    let mut launch_args = config.launch_args();
+    launch_args.extend([
+        "--user-data-dir",
+        &user_data_dir,
+        "--extensions-dir",
+        &extensions_dir,
+    ]);
+
+    crate::wrapper::write_launcher(
+        &profile.wrapper,
+        &config.install_dir,
+        fsutil::executable_mode(config.file_mode),
+        &launch_args,
+    )
+    .await?;
+
+    let contents = crate::desktop_entry_contents(
+        &format!("{} ({})", config.desktop_name, profile.name),
+        &config.install_dir.join(crate::wrapper::CURRENT_LINK),
+        &config.install_dir.join("code.png"),
+        &launch_args,
+    );
+    if fs::read_to_string(&profile.desktop_entry)
+        .await
+        .ok()
+        .as_deref()
+        == Some(contents.as_str())
+    {
+        return Ok(());
+    }
+    if let Some(parent) = profile.desktop_entry.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::write(&profile.desktop_entry, contents).await?;
+    fsutil::set_mode(&profile.desktop_entry, fsutil::protected_mode()).await?;
+    events::log(format!("Wrote desktop entry {:?}", profile.desktop_entry));
+    Ok(())
+}
+
+/// Launchers need absolute paths; `~/` is expanded for `--flag=~/dir`,
+/// which the shell leaves alone.
+fn absolute(path: &Path) -> Result<PathBuf> {
+    if let Ok(rest) = path.strip_prefix("~") {
+        return Ok(crate::home_dir()?.join(rest));
+    }
+    Ok(std::env::current_dir()?.join(path))
+}
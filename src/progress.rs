@@ -0,0 +1,86 @@
+//! Progress bars for the terminal output of `events`: a bar with speed and
+//! ETA while downloading, and a spinner while extracting or copying. Only
+//! one is shown at a time; it is cleared when the next phase begins. When
+//! stdout is not a terminal there are no bars, only the plain lines.
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::io::{IsTerminal, stdout};
+use std::sync::Mutex;
+use std::time::Duration;
+
+static ACTIVE: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+fn enabled() -> bool {
+    stdout().is_terminal()
+}
+
+fn show(bar: ProgressBar) {
+    if let Ok(mut active) = ACTIVE.lock()
+        && let Some(previous) = active.replace(bar)
+    {
+        previous.finish_and_clear();
+    }
+}
+
+/// Starts the bar for a download of `total_bytes`.
+pub(crate) fn download(message: String, total_bytes: u64) {
+    if !enabled() {
+        return;
+    }
+    let bar = ProgressBar::with_draw_target(Some(total_bytes), ProgressDrawTarget::stdout());
+    bar.set_style(
+        ProgressStyle::with_template(
+            "{msg} [{wide_bar}] {binary_bytes}/{binary_total_bytes} {binary_bytes_per_sec} ETA {eta}",
+        )
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+        .progress_chars("=> "),
+    );
+    bar.set_message(message);
+    show(bar);
+}
+
+pub(crate) fn downloaded(bytes: u64) {
+    if let Ok(active) = ACTIVE.lock()
+        && let Some(bar) = active.as_ref()
+    {
+        bar.set_position(bytes);
+    }
+}
+
+/// Starts a spinner for a step of unknown length.
+pub(crate) fn spinner(message: String) {
+    if !enabled() {
+        println!("{}", message);
+        return;
+    }
+    let bar = ProgressBar::with_draw_target(None, ProgressDrawTarget::stdout());
+    bar.set_style(
+        ProgressStyle::with_template("{spinner} {msg} {elapsed}")
+            .unwrap_or_else(|_| ProgressStyle::default_spinner()),
+    );
+    bar.set_message(message);
+    bar.enable_steady_tick(Duration::from_millis(100));
+    show(bar);
+}
+
+/// Clears the bar or spinner, if one is shown.
+pub(crate) fn finish() {
+    if let Ok(mut active) = ACTIVE.lock()
+        && let Some(bar) = active.take()
+    {
+        bar.finish_and_clear();
+    }
+}
+
+/// Prints a line above the bar, or plainly when there is none.
+pub(crate) fn println(line: &str) {
+    match ACTIVE
+        .lock()
+        .ok()
+        .as_ref()
+        .and_then(|active| active.as_ref())
+    {
+        Some(bar) => bar.println(line),
+        None => println!("{}", line),
+    }
+}
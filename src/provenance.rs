@@ -0,0 +1,149 @@
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Name of the provenance record written into the install dir.
+pub const PROVENANCE_FILE: &str = "provenance.json";
+
+/// Where an installed artifact came from and which checks it passed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Provenance {
+    pub file: String,
+    pub version: String,
+    pub commit_sha: String,
+    pub source_url: String,
+    pub sha256: String,
+    /// Where the checksum was published, if it was checked against one.
+    pub checksum_source: Option<String>,
+    pub size: u64,
+    /// Seconds since the Unix epoch.
+    pub fetched_at: u64,
+    pub verification: Vec<Verification>,
+    /// What the `transform` command made of the download, if one is set.
+    /// `sha256` and `size` above stay those of the upstream file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transformed: Option<Transformed>,
+}
+
+/// The file installed in place of the download when a `transform` command
+/// is configured.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Transformed {
+    pub command: String,
+    pub sha256: String,
+    pub size: u64,
+}
+
+impl Provenance {
+    /// SHA-256 of the file that gets installed: the transformed copy if
+    /// there is one, otherwise the download.
+    pub fn installed_sha256(&self) -> &str {
+        match &self.transformed {
+            Some(transformed) => &transformed.sha256,
+            None => &self.sha256,
+        }
+    }
+
+    /// Size of the file that gets installed.
+    pub fn installed_size(&self) -> u64 {
+        self.transformed
+            .as_ref()
+            .map_or(self.size, |transformed| transformed.size)
+    }
+
+    /// Where the installed file's checksum was published. A transformed
+    /// copy's was only computed locally.
+    pub fn installed_checksum_source(&self) -> Option<&str> {
+        match &self.transformed {
+            Some(_) => None,
+            None => self.checksum_source.as_deref(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Verification {
+    pub check: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+impl Verification {
+    pub fn new(check: &str, passed: bool, detail: impl Into<String>) -> Self {
+        Self {
+            check: check.to_string(),
+            passed,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Path of the `sha256sum`-compatible sidecar for an artifact.
+pub fn checksum_path(artifact: &Path) -> PathBuf {
+    let mut name = artifact.file_name().unwrap_or_default().to_os_string();
+    name.push(".sha256");
+    artifact.with_file_name(name)
+}
+
+/// Writes `<artifact>.sha256` and `provenance.json` next to the artifact, so
+/// it can be checked with `sha256sum -c` without involving cursorup.
+pub async fn write_sidecars(artifact: &Path, provenance: &Provenance) -> Result<()> {
+    write_checksum(
+        artifact,
+        provenance.installed_sha256(),
+        provenance.installed_checksum_source(),
+    )
+    .await?;
+
+    let dir = artifact.parent().ok_or("Artifact path has no parent")?;
+    crate::fsutil::write_atomic(
+        &dir.join(PROVENANCE_FILE),
+        serde_json::to_string_pretty(provenance)?,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Writes only the `<artifact>.sha256` sidecar. A published checksum's
+/// source goes into a `#` comment line, which `sha256sum -c` skips.
+pub async fn write_checksum(artifact: &Path, sha256: &str, source: Option<&str>) -> Result<()> {
+    let file_name = artifact
+        .file_name()
+        .ok_or("Artifact path has no file name")?;
+    let mut checksum = String::new();
+    if let Some(source) = source {
+        checksum.push_str(&format!("# source: {}\n", source));
+    }
+    checksum.push_str(&format!("{}  {}\n", sha256, file_name.to_string_lossy()));
+    crate::fsutil::write_atomic(&checksum_path(artifact), checksum).await?;
+    Ok(())
+}
+
+/// A checksum read back from a `.sha256` sidecar.
+#[derive(Debug, Clone)]
+pub struct RecordedChecksum {
+    pub sha256: String,
+    /// The `# source:` line, i.e. where the checksum was published; `None`
+    /// if it was only computed locally at download time.
+    pub source: Option<String>,
+}
+
+/// Reads a sidecar written by `write_checksum`, or `None` if it is missing.
+pub async fn read_checksum(path: &Path) -> Result<Option<RecordedChecksum>> {
+    let contents = match tokio::fs::read_to_string(path).await {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let sha256 = crate::checksums::parse_sha256(&contents)
+        .ok_or_else(|| format!("{}: no SHA-256 checksum found", path.display()))?;
+    let source = contents
+        .lines()
+        .find_map(|line| line.strip_prefix("# source:"))
+        .map(|source| source.trim().to_string());
+    Ok(Some(RecordedChecksum { sha256, source }))
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
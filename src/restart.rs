@@ -0,0 +1,212 @@
+//! What happens to a Cursor that is running while it is updated
+//! (`restart_strategy`). The running instance keeps working from its old
+//! AppImage, which stays mounted, so by default it is left alone and the
+//! new version starts with the next launch.
+
+use crate::config::Config;
+use crate::events::{self, Prompt};
+use crate::{Result, wrapper};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// How long a graceful quit may take before cursorup gives up on it.
+const GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RestartStrategy {
+    /// Leave Cursor running; the update applies from its next launch.
+    #[default]
+    Never,
+    /// Ask whether to restart gracefully, where someone can be asked.
+    Prompt,
+    /// Ask Cursor to quit with SIGTERM, so it saves its windows and
+    /// unsaved-file backups, then start the new version.
+    Graceful,
+    /// Kill Cursor with SIGKILL and start the new version.
+    Force,
+}
+
+impl FromStr for RestartStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "never" => Ok(RestartStrategy::Never),
+            "prompt" => Ok(RestartStrategy::Prompt),
+            "graceful" => Ok(RestartStrategy::Graceful),
+            "force" => Ok(RestartStrategy::Force),
+            _ => Err(format!(
+                "unknown restart strategy `{}` (expected never, prompt, graceful or force)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for RestartStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RestartStrategy::Never => "never",
+            RestartStrategy::Prompt => "prompt",
+            RestartStrategy::Graceful => "graceful",
+            RestartStrategy::Force => "force",
+        })
+    }
+}
+
+/// Applies `restart_strategy` once `version` is installed.
+pub(crate) async fn after_update(config: &Config, version: &str) -> Result<()> {
+    let pids = running(&config.install_dir);
+    if pids.is_empty() {
+        return Ok(());
+    }
+    let still_running = || {
+        events::log(format!(
+            "Cursor is still running the previous version; restart it to use {}",
+            version
+        ))
+    };
+    let graceful = match config.restart_strategy {
+        RestartStrategy::Never => {
+            still_running();
+            return Ok(());
+        }
+        RestartStrategy::Prompt => {
+            let prompt = Prompt::RestartCursor {
+                version: version.to_string(),
+            };
+            if events::ask(prompt).await? != Some(true) {
+                still_running();
+                return Ok(());
+            }
+            true
+        }
+        RestartStrategy::Graceful => true,
+        RestartStrategy::Force => false,
+    };
+
+    // Cursor's helper processes follow its main process, which handles
+    // SIGTERM like closing the last window.
+    let targets: Vec<u32> = if graceful {
+        pids.iter()
+            .copied()
+            .filter(|pid| parent(*pid).is_none_or(|parent| !pids.contains(&parent)))
+            .collect()
+    } else {
+        pids.iter().copied().collect()
+    };
+    events::log(format!("Stopping Cursor (pid {})", join(&targets)));
+    for pid in &targets {
+        stop(*pid, graceful)?;
+    }
+    let deadline = Instant::now() + GRACE_PERIOD;
+    while pids.iter().any(|pid| alive(*pid)) {
+        if Instant::now() >= deadline {
+            tracing::warn!(
+                "Cursor did not quit within {}s and still runs the previous version; close it to use {}",
+                GRACE_PERIOD.as_secs(),
+                version
+            );
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+    launch(config, graceful)?;
+    events::log(format!("Restarted Cursor {}", version));
+    Ok(())
+}
+
+/// Processes of AppImages in `install_dir`, found by the `APPIMAGE`
+/// variable the AppImage runtime sets for them.
+fn running(install_dir: &Path) -> HashSet<u32> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return HashSet::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter(|pid| *pid != std::process::id())
+        .filter(|pid| appimage(*pid).is_some_and(|path| path.parent() == Some(install_dir)))
+        .collect()
+}
+
+fn appimage(pid: u32) -> Option<PathBuf> {
+    let environ = std::fs::read(format!("/proc/{}/environ", pid)).ok()?;
+    environ
+        .split(|byte| *byte == 0)
+        .find_map(|entry| entry.strip_prefix(b"APPIMAGE="))
+        .map(|path| PathBuf::from(String::from_utf8_lossy(path).into_owned()))
+}
+
+fn parent(pid: u32) -> Option<u32> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name in parentheses may itself contain spaces.
+    stat.rsplit_once(')')?
+        .1
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()
+}
+
+fn alive(pid: u32) -> bool {
+    std::fs::read_to_string(format!("/proc/{}/stat", pid))
+        .ok()
+        .and_then(|stat| Some(stat.rsplit_once(')')?.1.split_whitespace().next()? != "Z"))
+        .unwrap_or(false)
+}
+
+/// Sends `pid` SIGTERM, or SIGKILL unless `graceful`.
+#[cfg(unix)]
+fn stop(pid: u32, graceful: bool) -> Result<()> {
+    let signal = if graceful {
+        libc::SIGTERM
+    } else {
+        libc::SIGKILL
+    };
+    // SAFETY: kill(2) only sends a signal; a pid that has exited since it
+    // was listed makes it fail with ESRCH, which is fine.
+    unsafe { libc::kill(pid as libc::pid_t, signal) };
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn stop(_pid: u32, _graceful: bool) -> Result<()> {
+    Err("Restarting Cursor is only supported on Unix; set restart_strategy = never".into())
+}
+
+/// Starts the installed Cursor detached from cursorup, so it outlives it.
+/// After a graceful quit it also gets `restart_args`, to restore the session.
+fn launch(config: &Config, graceful: bool) -> Result<()> {
+    let mut command = std::process::Command::new(config.install_dir.join(wrapper::CURRENT_LINK));
+    command.args(config.launch_args());
+    if graceful {
+        command.args(&config.restart_args);
+    }
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Cannot start the new Cursor: {}", e))?;
+    // Reaped in the background so the daemon does not collect zombies.
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+fn join(pids: &[u32]) -> String {
+    pids.iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
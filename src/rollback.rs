@@ -0,0 +1,198 @@
+//! `cursorup rollback`: puts the most recently backed-up AppImage back in
+//! place, e.g. when an update broke something. The install being replaced
+//! is backed up in turn, so a second rollback returns to it.
+
+use crate::config::Config;
+use crate::state::State;
+use crate::{Result, backup, events, fsutil, manifest, provenance, wrapper};
+use serde::Serialize;
+use std::fmt;
+use std::path::PathBuf;
+use tokio::fs;
+
+/// Name of the backed-up icon next to the backed-up AppImages.
+const ICON_BACKUP: &str = "code.png.bak";
+
+#[derive(Serialize, Debug)]
+pub struct RolledBack {
+    /// Version that was installed before, if known.
+    pub from: Option<String>,
+    pub to: String,
+    pub appimage: PathBuf,
+    /// The backup the AppImage was restored from.
+    pub backup: PathBuf,
+}
+
+impl fmt::Display for RolledBack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Rolled back {} -> {}",
+            self.from.as_deref().unwrap_or("-"),
+            self.to
+        )?;
+        write!(f, "Restored {}", self.appimage.display())
+    }
+}
+
+/// Restores the newest backup; see `restore`.
+pub async fn rollback(config: &Config, verbose: bool) -> Result<RolledBack> {
+    restore(config, None, verbose).await
+}
+
+/// Restores the backup with the given `id` (its file name, or the version
+/// it holds), or the newest one, after checking it against its recorded
+/// checksum, and rewrites the launchers, manifest, state and history.
+pub async fn restore(config: &Config, id: Option<&str>, verbose: bool) -> Result<RolledBack> {
+    let install_dir = &config.install_dir;
+    let mut backups = backup::list(install_dir).await?.0.into_iter();
+    let chosen = match id {
+        Some(id) => backups
+            .find(|backup| backup.id == id || backup.version.as_deref() == Some(id))
+            .ok_or_else(|| format!("No backup {:?} in {:?}", id, install_dir))?,
+        None => backups
+            .next()
+            .ok_or_else(|| format!("No backups in {:?} to roll back to", install_dir))?,
+    };
+    let to = chosen
+        .version
+        .clone()
+        .ok_or_else(|| format!("Cannot tell the version of {:?}", chosen.file))?;
+    let original = backup::original_name(&chosen.file).ok_or("Backup has no .bak suffix")?;
+    let appimage = install_dir.join(original.file_name().ok_or("Backup has no file name")?);
+    let installed = crate::status::installed_appimage(install_dir).await?;
+    if installed.as_deref() == Some(appimage.as_path()) {
+        return Err(format!(
+            "The backup {:?} is of the installed file; nothing to roll back to",
+            chosen.file
+        )
+        .into());
+    }
+    let from = match State::load().await? {
+        Some(state) => Some(state.version),
+        None => installed.and_then(|path| crate::status::version_from_file_name(&path)),
+    };
+    crate::confirm_replace(from.as_deref(), &to).await?;
+    events::log(format!(
+        "Rolling back to Cursor {} from {:?}",
+        to, chosen.file
+    ));
+
+    // Restore next to the install first, so the current files are only
+    // backed up once the old ones are known to be intact. The icon backup
+    // is copied too, as backing up the current icon replaces it.
+    let restored = fsutil::with_suffix(&appimage, ".rollback");
+    let restored_icon = install_dir.join("code.png.rollback");
+    backup::restore_file(&chosen.file, &restored, config.hardlinks).await?;
+    let sha256 = crate::cache::sha256_file(&restored).await?;
+    let sidecar = backup::checksum_sidecar(&chosen.file);
+    let recorded = match &sidecar {
+        Some(sidecar) => provenance::read_checksum(sidecar).await?,
+        None => None,
+    };
+    if let Some(recorded) = &recorded
+        && recorded.sha256 != sha256
+    {
+        let _ = fs::remove_file(&restored).await;
+        return Err(format!(
+            "Backup {:?} is corrupt: expected sha256 {}, found {}",
+            chosen.file, recorded.sha256, sha256
+        )
+        .into());
+    }
+    let icon_backup = install_dir.join(backup::BACKUP_DIR).join(ICON_BACKUP);
+    let had_icon_backup = fs::try_exists(&icon_backup).await?;
+    if had_icon_backup {
+        fs::copy(&icon_backup, &restored_icon).await?;
+    }
+
+    crate::back_file(install_dir.clone(), config.compress_backups).await?;
+    let mode = fsutil::executable_mode(config.file_mode);
+    fs::rename(&restored, &appimage).await?;
+    fsutil::set_mode(&appimage, mode).await?;
+    // Without an older icon, the one just backed up is as good.
+    let icon = install_dir.join("code.png");
+    if had_icon_backup {
+        fs::rename(&restored_icon, &icon).await?;
+    } else if fs::try_exists(&icon_backup).await? {
+        fs::copy(&icon_backup, &icon).await?;
+    }
+    // A custom icon outlives the version it was installed with.
+    if let Some(custom) = &config.icon {
+        fs::copy(custom, &icon).await?;
+    }
+    let has_icon = fs::try_exists(&icon).await?;
+    if has_icon {
+        fsutil::set_mode(&icon, fsutil::data_mode(config.file_mode)).await?;
+    }
+    let checksum = provenance::checksum_path(&appimage);
+    if let Some(sidecar) = &sidecar
+        && recorded.is_some()
+    {
+        fs::copy(sidecar, &checksum).await?;
+    }
+    // provenance.json describes the build that was just replaced.
+    match fs::remove_file(install_dir.join(provenance::PROVENANCE_FILE)).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    for path in [Some(chosen.file.clone()), sidecar].into_iter().flatten() {
+        match fs::remove_file(&path).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+
+    wrapper::link_current(install_dir, &appimage).await?;
+    if config.cli_wrapper {
+        wrapper::write_wrapper(install_dir, mode, &config.launch_args()).await?;
+    }
+    if config.desktop_entry && !config.system_install {
+        crate::echo_2_desktop(
+            &config.desktop_name,
+            &appimage,
+            &icon,
+            &config.launch_args(),
+            verbose,
+        )
+        .await?;
+    }
+    crate::profiles::refresh(config).await?;
+
+    let mut manifest = manifest::Manifest::default();
+    manifest.add(&appimage);
+    if recorded.is_some() {
+        manifest.add(&checksum);
+    }
+    if has_icon {
+        manifest.add(&icon);
+    }
+    manifest.appimage = Some(manifest::FileRecord::new(&appimage, &sha256).await?);
+    manifest.save(install_dir).await?;
+
+    let previous_state = State::load().await.ok().flatten();
+    let mut state = State::new(
+        &config.desktop_name,
+        &to,
+        "",
+        &appimage,
+        config.platform,
+        config.release_track,
+    );
+    state.sha256 = Some(sha256);
+    state.rolled_back_from = from.clone();
+    state.downloads = previous_state.map(|s| s.downloads).unwrap_or_default();
+    state.save().await?;
+
+    let mut entry =
+        crate::history::HistoryEntry::new(crate::history::Outcome::RolledBack, from.clone());
+    entry.to_version = Some(to.clone());
+    crate::history::record(&entry).await?;
+
+    Ok(RolledBack {
+        from,
+        to,
+        appimage,
+        backup: chosen.file,
+    })
+}
@@ -0,0 +1,327 @@
+//! `cursorup rpc`: a JSON-RPC 2.0 server over stdin/stdout for embedding
+//! cursorup in graphical front-ends. Messages are framed as one JSON object
+//! per line. Install progress is sent as `progress` notifications whose
+//! params are the serialized `events::Event`. Decisions are sent as `prompt`
+//! notifications with an `id`, answered with a `respond` request; stdin is
+//! read while a method runs, so the answer can arrive while it waits.
+
+use crate::config::Config;
+use crate::events::{self, Event, Prompt, Responder};
+use crate::{InstallOptions, Result};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::mpsc;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const APPLICATION_ERROR: i64 = -32000;
+
+#[derive(Deserialize)]
+struct Request {
+    jsonrpc: String,
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Option<Value>,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for RpcError {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        RpcError::new(APPLICATION_ERROR, e.to_string())
+    }
+}
+
+type Output = Arc<Mutex<std::io::Stdout>>;
+
+/// Prompts waiting for a `respond`, by the id sent with them.
+type Pending = Arc<Mutex<HashMap<u64, Responder>>>;
+
+fn send(output: &Output, message: &Value) {
+    if let Ok(mut stdout) = output.lock() {
+        let _ = writeln!(stdout, "{}", message);
+        let _ = stdout.flush();
+    }
+}
+
+/// Serves requests until stdin is closed or an `exit` request arrives.
+pub async fn serve(config: &Config) -> Result<()> {
+    let output: Output = Arc::new(Mutex::new(std::io::stdout()));
+    let notifier = output.clone();
+    events::set_handler(Arc::new(move |event: &Event| {
+        send(
+            &notifier,
+            &json!({ "jsonrpc": "2.0", "method": "progress", "params": event }),
+        );
+    }));
+    // Prompts must never fall back to the terminal: stdin is the channel.
+    let pending: Pending = Arc::default();
+    let next_id = Arc::new(AtomicU64::new(1));
+    let prompter = output.clone();
+    let waiting = pending.clone();
+    events::set_prompt_handler(Arc::new(move |prompt: Prompt, responder: Responder| {
+        let id = next_id.fetch_add(1, Ordering::Relaxed);
+        let mut params = serde_json::to_value(&prompt).unwrap_or_else(|_| json!({}));
+        if let Value::Object(params) = &mut params {
+            params.insert("id".to_string(), id.into());
+            params.insert("default".to_string(), prompt.default_answer().into());
+            params.insert("question".to_string(), prompt.question().into());
+            if let Some(details) = prompt.details() {
+                params.insert("details".to_string(), details.into());
+            }
+        }
+        if let Ok(mut waiting) = waiting.lock() {
+            waiting.insert(id, responder);
+        }
+        send(
+            &prompter,
+            &json!({ "jsonrpc": "2.0", "method": "prompt", "params": params }),
+        );
+    }));
+
+    let mut lines = read_requests(output.clone(), pending.clone());
+    while let Some(line) = lines.recv().await {
+        let request = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                let code = if serde_json::from_str::<Value>(&line).is_ok() {
+                    INVALID_REQUEST
+                } else {
+                    PARSE_ERROR
+                };
+                send(
+                    &output,
+                    &error_response(Value::Null, RpcError::new(code, e.to_string())),
+                );
+                continue;
+            }
+        };
+        let id = request.id.clone();
+        let exit = request.method == "exit";
+        let result = if request.jsonrpc != "2.0" {
+            Err(RpcError::new(INVALID_REQUEST, "jsonrpc must be \"2.0\""))
+        } else {
+            handle(config, &request).await
+        };
+
+        // Requests without an id are notifications and get no response.
+        if let Some(id) = id {
+            let response = match result {
+                Ok(value) => json!({ "jsonrpc": "2.0", "id": id, "result": value }),
+                Err(e) => error_response(id, e),
+            };
+            send(&output, &response);
+        }
+        if exit {
+            break;
+        }
+    }
+
+    events::clear_handler();
+    events::clear_prompt_handler();
+    Ok(())
+}
+
+/// Reads stdin in the background, answering `respond` requests at once and
+/// passing every other line on, so a method waiting for an answer doesn't
+/// keep the answer from being read. Stops after `exit`, so nothing is left
+/// blocked on stdin.
+fn read_requests(output: Output, pending: Pending) -> mpsc::UnboundedReceiver<String> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let method = serde_json::from_str::<Request>(&line)
+                .ok()
+                .filter(|request| request.jsonrpc == "2.0");
+            if let Some(request) = method.as_ref().filter(|r| r.method == "respond") {
+                let result = respond(&pending, request);
+                if let Some(id) = request.id.clone() {
+                    let response = match result {
+                        Ok(()) => json!({ "jsonrpc": "2.0", "id": id, "result": null }),
+                        Err(e) => error_response(id, e),
+                    };
+                    send(&output, &response);
+                }
+                continue;
+            }
+            let exit = method.is_some_and(|request| request.method == "exit");
+            if sender.send(line).is_err() || exit {
+                break;
+            }
+        }
+        // Nobody is left to answer; dropped responders give the defaults.
+        if let Ok(mut pending) = pending.lock() {
+            pending.clear();
+        }
+    });
+    receiver
+}
+
+/// `respond {id, yes}`: answers the prompt sent with `id`.
+fn respond(pending: &Pending, request: &Request) -> std::result::Result<(), RpcError> {
+    let params = request.params.as_ref();
+    let id = params
+        .and_then(|params| params.get("id"))
+        .and_then(Value::as_u64)
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, "respond needs a numeric `id`"))?;
+    let yes = params
+        .and_then(|params| params.get("yes"))
+        .and_then(Value::as_bool)
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, "respond needs a boolean `yes`"))?;
+    let responder = pending
+        .lock()
+        .ok()
+        .and_then(|mut pending| pending.remove(&id))
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("no prompt {} is waiting", id)))?;
+    responder.answer(yes);
+    Ok(())
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
+
+async fn handle(config: &Config, request: &Request) -> std::result::Result<Value, RpcError> {
+    let to_value = |value: serde_json::Result<Value>| {
+        value.map_err(|e| RpcError::new(APPLICATION_ERROR, e.to_string()))
+    };
+    // Methods that change the install hold the instance lock while they run.
+    let _lock = match request.method.as_str() {
+        "install" | "commit" | "resume" | "rollback" | "gc" | "uninstall" | "purge" => {
+            Some(crate::instance::acquire()?)
+        }
+        _ => None,
+    };
+    match request.method.as_str() {
+        "status" => to_value(serde_json::to_value(
+            crate::status::status(config, verbose(request)).await?,
+        )),
+        "env" => to_value(serde_json::to_value(
+            crate::environment::environment(config).await?,
+        )),
+        "info" => {
+            let client = crate::http::client(config)?;
+            to_value(serde_json::to_value(
+                crate::fetch_metadata(
+                    &client,
+                    &config.api_url,
+                    config.platform,
+                    config.release_track,
+                )
+                .await?,
+            ))
+        }
+        "check" => {
+            let client = crate::http::client(config)?;
+            let metadata = crate::fetch_metadata(
+                &client,
+                &config.api_url,
+                config.platform,
+                config.release_track,
+            )
+            .await?;
+            to_value(serde_json::to_value(
+                crate::check::check(config, &metadata).await?,
+            ))
+        }
+        "plan" => {
+            let options = install_options(request)?;
+            let client = crate::http::client(config)?;
+            let metadata = crate::resolve_metadata(&client, config, &options).await?;
+            to_value(serde_json::to_value(
+                crate::plan::plan(config, &client, &metadata, &options).await?,
+            ))
+        }
+        "install" => {
+            crate::install(config, &install_options(request)?).await?;
+            to_value(serde_json::to_value(
+                crate::status::status(config, false).await?,
+            ))
+        }
+        "commit" => {
+            crate::stage::commit(config, verbose(request)).await?;
+            to_value(serde_json::to_value(
+                crate::status::status(config, false).await?,
+            ))
+        }
+        "resume" => {
+            let id = request
+                .params
+                .as_ref()
+                .and_then(|params| params.get("id"))
+                .and_then(Value::as_str);
+            crate::journal::resume(config, id, verbose(request)).await?;
+            to_value(serde_json::to_value(
+                crate::status::status(config, false).await?,
+            ))
+        }
+        "rollback" => {
+            let id = request
+                .params
+                .as_ref()
+                .and_then(|params| params.get("id"))
+                .and_then(Value::as_str);
+            to_value(serde_json::to_value(
+                crate::rollback::restore(config, id, verbose(request)).await?,
+            ))
+        }
+        "backups" => to_value(serde_json::to_value(crate::list_backups(config).await?)),
+        "gc" => to_value(serde_json::to_value(crate::cleanup::gc(config).await?)),
+        "du" => to_value(serde_json::to_value(crate::du::disk_usage(config).await?)),
+        "uninstall" => to_value(serde_json::to_value(
+            crate::cleanup::uninstall(config).await?,
+        )),
+        "purge" => to_value(serde_json::to_value(crate::cleanup::purge(config).await?)),
+        "exit" => Ok(Value::Null),
+        method => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("unknown method `{}`", method),
+        )),
+    }
+}
+
+fn verbose(request: &Request) -> bool {
+    request
+        .params
+        .as_ref()
+        .and_then(|params| params.get("verbose"))
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+fn install_options(request: &Request) -> std::result::Result<InstallOptions, RpcError> {
+    match &request.params {
+        None | Some(Value::Null) => Ok(InstallOptions::default()),
+        Some(params) => serde_json::from_value(params.clone())
+            .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string())),
+    }
+}
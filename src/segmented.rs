@@ -0,0 +1,271 @@
+//! Multi-connection downloads (`connections` > 1): the file is split into
+//! that many byte ranges, fetched concurrently and each written at its
+//! offset in the part file. Only used when the server honours ranges; the
+//! hash is computed once every range is in, and an interrupted segmented
+//! download starts over rather than resuming.
+
+use crate::events::{self, InstallEvent};
+use crate::{Download, Result, fsutil, provenance};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufWriter};
+use tokio::task::JoinSet;
+
+/// Most connections `connections` may ask for.
+pub const MAX_CONNECTIONS: u64 = 16;
+
+/// Ranges smaller than this are not worth a connection of their own.
+const MIN_SEGMENT: u64 = 1024 * 1024;
+
+/// Downloads `url` to `dest_path` over up to `connections` connections.
+/// Returns `None`, having changed nothing, when the server does not serve
+/// ranges or the file is too small to split, so the caller can download it
+/// in one piece.
+pub(crate) async fn download(
+    client: &reqwest::Client,
+    url: &str,
+    dest_path: &Path,
+    connections: u64,
+    buffer_size: usize,
+    limit_rate: Option<u64>,
+) -> Result<Option<Download>> {
+    let started = Instant::now();
+    let probe = crate::http::get_artifact(client, url)
+        .header(reqwest::header::RANGE, "bytes=0-0")
+        .send()
+        .await?;
+    if probe.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+        events::log("The server does not serve byte ranges; downloading over one connection");
+        return Ok(None);
+    }
+    let Some((_, total_size)) = crate::content_range(&probe) else {
+        return Ok(None);
+    };
+    let ranges = split(total_size, connections);
+    if ranges.is_empty() {
+        return Ok(None);
+    }
+    let connections = ranges.len() as u64;
+    // Every range must come from the same file as the probe.
+    let validator = [reqwest::header::ETAG, reqwest::header::LAST_MODIFIED]
+        .iter()
+        .find_map(|name| probe.headers().get(name)?.to_str().ok())
+        .map(str::to_string);
+    drop(probe);
+
+    events::emit(InstallEvent::DownloadStarted {
+        url: url.to_string(),
+        total_bytes: total_size,
+    });
+    events::log(format!(
+        "Downloading in {} segments over parallel connections",
+        connections
+    ));
+    let part_path = fsutil::with_suffix(dest_path, ".part");
+    let _ = fs::remove_file(fsutil::with_suffix(dest_path, ".part.validator")).await;
+    fs::File::create(&part_path)
+        .await?
+        .set_len(total_size)
+        .await?;
+
+    let downloaded = Arc::new(AtomicU64::new(0));
+    let mut tasks = JoinSet::new();
+    for (start, end) in ranges {
+        let client = client.clone();
+        let url = url.to_string();
+        let part_path = part_path.clone();
+        let validator = validator.clone();
+        let downloaded = downloaded.clone();
+        // The connections share the budget evenly.
+        let throttle = limit_rate.map(|rate| crate::http::Throttle::new(rate / connections));
+        tasks.spawn(async move {
+            fetch_segment(
+                &client,
+                &url,
+                &part_path,
+                (start, end),
+                validator.as_deref(),
+                buffer_size,
+                &downloaded,
+                total_size,
+                throttle,
+            )
+            .await
+            .map_err(|e| e.to_string())
+        });
+    }
+    while let Some(result) = tasks.join_next().await {
+        let failed = match result {
+            Ok(Ok(())) => continue,
+            Ok(Err(e)) => e,
+            Err(e) => e.to_string(),
+        };
+        tasks.abort_all();
+        let _ = fs::remove_file(&part_path).await;
+        return Err(format!(
+            "Segmented download of {} failed: {}; run again to start over",
+            url, failed
+        )
+        .into());
+    }
+
+    let sha256 = hash_file(&part_path, buffer_size).await?;
+    fs::rename(&part_path, dest_path).await?;
+    events::emit(InstallEvent::DownloadFinished {
+        path: dest_path.to_path_buf(),
+        bytes: total_size,
+    });
+    Ok(Some(Download {
+        bytes: total_size,
+        expected_bytes: total_size,
+        sha256,
+        elapsed: started.elapsed(),
+    }))
+}
+
+/// Splits `total_size` bytes into up to `connections` inclusive ranges of
+/// at least `MIN_SEGMENT` each. Empty when fewer than two would result.
+fn split(total_size: u64, connections: u64) -> Vec<(u64, u64)> {
+    let connections = connections.min(total_size / MIN_SEGMENT);
+    if connections < 2 {
+        return Vec::new();
+    }
+    // Sizes differ by at most a byte, so none falls below the minimum.
+    let offset = |index: u64| total_size * index / connections;
+    (0..connections)
+        .map(|index| (offset(index), offset(index + 1) - 1))
+        .collect()
+}
+
+/// Fetches the inclusive byte range `start..=end` into its place in the
+/// part file.
+#[allow(clippy::too_many_arguments)]
+async fn fetch_segment(
+    client: &reqwest::Client,
+    url: &str,
+    part_path: &Path,
+    (start, end): (u64, u64),
+    validator: Option<&str>,
+    buffer_size: usize,
+    downloaded: &AtomicU64,
+    total_size: u64,
+    mut throttle: Option<crate::http::Throttle>,
+) -> Result<()> {
+    let mut request = crate::http::get_artifact(client, url)
+        .header(reqwest::header::RANGE, format!("bytes={}-{}", start, end));
+    if let Some(validator) = validator {
+        request = request.header(reqwest::header::IF_RANGE, validator);
+    }
+    let mut response = request.send().await?;
+    if response.status() != reqwest::StatusCode::PARTIAL_CONTENT
+        || crate::content_range(&response).map(|(first, _)| first) != Some(start)
+    {
+        return Err(format!(
+            "bytes {}-{}: expected a partial response, got {}; the file may have changed",
+            start,
+            end,
+            response.status()
+        )
+        .into());
+    }
+    let mut file = fs::OpenOptions::new().write(true).open(part_path).await?;
+    file.seek(std::io::SeekFrom::Start(start)).await?;
+    let mut file = BufWriter::with_capacity(buffer_size, file);
+    let expected = end - start + 1;
+    let mut written = 0;
+    while let Some(chunk) = response.chunk().await? {
+        written += chunk.len() as u64;
+        if written > expected {
+            return Err(format!("bytes {}-{}: the server sent too much", start, end).into());
+        }
+        file.write_all(&chunk).await?;
+        let total =
+            downloaded.fetch_add(chunk.len() as u64, Ordering::Relaxed) + chunk.len() as u64;
+        events::emit(InstallEvent::DownloadProgress {
+            downloaded_bytes: total,
+            total_bytes: total_size,
+        });
+        if let Some(throttle) = &mut throttle {
+            throttle.pace(chunk.len()).await;
+        }
+    }
+    file.flush().await?;
+    if written != expected {
+        return Err(format!(
+            "bytes {}-{}: ended after {} of {} bytes",
+            start, end, written, expected
+        )
+        .into());
+    }
+    Ok(())
+}
+
+async fn hash_file(path: &Path, buffer_size: usize) -> Result<String> {
+    let mut hasher = ring::digest::Context::new(&ring::digest::SHA256);
+    let mut file = fs::File::open(path).await?;
+    let mut buffer = vec![0; buffer_size];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(provenance::hex(hasher.finish().as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The ranges must tile `0..total_size` in order without gaps.
+    fn assert_tiles(ranges: &[(u64, u64)], total_size: u64) {
+        let mut next = 0;
+        for &(start, end) in ranges {
+            assert_eq!(start, next);
+            assert!(end >= start);
+            assert!(end - start + 1 >= MIN_SEGMENT);
+            next = end + 1;
+        }
+        assert_eq!(next, total_size);
+    }
+
+    #[test]
+    fn splits_into_even_ranges() {
+        let ranges = split(8 * MIN_SEGMENT, 4);
+        assert_eq!(
+            ranges,
+            [
+                (0, 2 * MIN_SEGMENT - 1),
+                (2 * MIN_SEGMENT, 4 * MIN_SEGMENT - 1),
+                (4 * MIN_SEGMENT, 6 * MIN_SEGMENT - 1),
+                (6 * MIN_SEGMENT, 8 * MIN_SEGMENT - 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn uneven_sizes_are_covered_exactly() {
+        for (total_size, connections) in [
+            (10 * MIN_SEGMENT + 1, 3),
+            (16 * MIN_SEGMENT + 7, 16),
+            (200 * MIN_SEGMENT - 1, 16),
+            (5 * MIN_SEGMENT, MAX_CONNECTIONS),
+        ] {
+            let ranges = split(total_size, connections);
+            assert!(ranges.len() as u64 <= connections);
+            assert_tiles(&ranges, total_size);
+        }
+    }
+
+    #[test]
+    fn small_files_are_not_split() {
+        assert_eq!(split(3 * MIN_SEGMENT, 8).len(), 3);
+        assert!(split(2 * MIN_SEGMENT - 1, 8).is_empty());
+        assert!(split(100 * MIN_SEGMENT, 1).is_empty());
+        assert!(split(0, 4).is_empty());
+    }
+}
@@ -0,0 +1,253 @@
+use crate::Result;
+use crate::config::Config;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use tokio::fs;
+
+/// GitHub repository cursorup releases are published from.
+pub const REPOSITORY: &str = "elliot-zen/cursorup";
+
+/// Minisign public key cursorup's releases are signed with; the default
+/// `self_update_pubkey`.
+pub const RELEASE_PUBKEY: &str = "RWT4wiqvZHQ9Qz4c1ZxhMdKPzIRgnPsN71U7f3fVu6SpwTX2jD3/DiCa";
+
+#[derive(Deserialize, Debug)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SelfUpdate {
+    pub current: String,
+    pub latest: String,
+    pub updated: bool,
+    /// Which checks the new binary passed, e.g. `sha256`, `minisign`.
+    pub verified_with: Vec<String>,
+}
+
+impl fmt::Display for SelfUpdate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.updated {
+            write!(
+                f,
+                "Updated cursorup {} -> {} (verified: {})",
+                self.current,
+                self.latest,
+                if self.verified_with.is_empty() {
+                    "nothing".to_string()
+                } else {
+                    self.verified_with.join(", ")
+                }
+            )
+        } else {
+            write!(f, "cursorup {} is up to date", self.current)
+        }
+    }
+}
+
+/// How long the latest release seen by `newer_release` is trusted before
+/// GitHub is asked again.
+const NOTICE_INTERVAL: u64 = 24 * 60 * 60;
+
+/// The latest release as last looked up for the outdated-cursorup hint.
+#[derive(Serialize, Deserialize, Debug)]
+struct SeenRelease {
+    /// Seconds since the Unix epoch.
+    checked_at: u64,
+    latest: String,
+}
+
+async fn latest_release(client: &reqwest::Client) -> Result<Release> {
+    Ok(client
+        .get(format!(
+            "https://api.github.com/repos/{}/releases/latest",
+            REPOSITORY
+        ))
+        .header(
+            reqwest::header::USER_AGENT,
+            concat!("cursorup/", env!("CARGO_PKG_VERSION")),
+        )
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
+
+/// The latest cursorup release, if it is newer than the running one. GitHub
+/// is asked at most once a day; the answer is cached in between. This is
+/// only a hint, so failures are logged and count as up to date.
+pub async fn newer_release(client: &reqwest::Client) -> Option<String> {
+    let latest = match seen_release(client).await {
+        Ok(latest) => latest,
+        Err(e) => {
+            tracing::debug!("cannot look up the latest cursorup release: {}", e);
+            return None;
+        }
+    };
+    let newer = crate::version::compare(&latest, env!("CARGO_PKG_VERSION"))
+        == Some(std::cmp::Ordering::Greater);
+    newer.then_some(latest)
+}
+
+async fn seen_release(client: &reqwest::Client) -> Result<String> {
+    let path = crate::cache::dir()?.join("cursorup-release.json");
+    if let Ok(contents) = fs::read_to_string(&path).await
+        && let Ok(seen) = serde_json::from_str::<SeenRelease>(&contents)
+        && crate::state::now().saturating_sub(seen.checked_at) < NOTICE_INTERVAL
+    {
+        return Ok(seen.latest);
+    }
+    let release = tokio::time::timeout(std::time::Duration::from_secs(3), latest_release(client))
+        .await
+        .map_err(|_| "timed out")??;
+    let latest = release.tag_name.trim_start_matches('v').to_string();
+    let seen = SeenRelease {
+        checked_at: crate::state::now(),
+        latest: latest.clone(),
+    };
+    crate::fsutil::write_atomic(&path, serde_json::to_string(&seen)?).await?;
+    Ok(latest)
+}
+
+/// Name of the release asset built for this machine.
+fn asset_name() -> String {
+    format!("cursorup-{}-unknown-linux-gnu", std::env::consts::ARCH)
+}
+
+/// Replaces the running executable with the latest release if it is newer.
+/// The binary must match the published `<asset>.sha256` and its
+/// `<asset>.minisig` signature by `self_update_pubkey`. Without a checksum
+/// or without a signature, the release is refused
+/// unless `allow_unsigned` is set: a checksum served next to the binary
+/// only catches corruption, not a tampered release.
+pub async fn self_update(
+    client: &reqwest::Client,
+    config: &Config,
+    allow_unsigned: bool,
+) -> Result<SelfUpdate> {
+    let current = env!("CARGO_PKG_VERSION").to_string();
+    let release = latest_release(client).await?;
+    let latest = release.tag_name.trim_start_matches('v').to_string();
+    let newer = crate::version::compare(&latest, &current) == Some(std::cmp::Ordering::Greater);
+    if !newer {
+        return Ok(SelfUpdate {
+            current,
+            latest,
+            updated: false,
+            verified_with: Vec::new(),
+        });
+    }
+
+    let name = asset_name();
+    let find = |name: &str| {
+        release
+            .assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .map(|asset| asset.browser_download_url.clone())
+    };
+    let binary_url = find(&name)
+        .ok_or_else(|| format!("Release {} has no `{}` asset", release.tag_name, name))?;
+    crate::events::log(format!(
+        "Downloading cursorup {} from {}",
+        latest, binary_url
+    ));
+    let binary = crate::http::get_artifact(client, &binary_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+
+    let mut verified_with = Vec::new();
+    match find(&format!("{}.sha256", name)) {
+        Some(url) => {
+            let published = client
+                .get(&url)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?;
+            let published = published.split_whitespace().next().unwrap_or_default();
+            let actual = crate::provenance::hex(
+                ring::digest::digest(&ring::digest::SHA256, &binary).as_ref(),
+            );
+            if !published.eq_ignore_ascii_case(&actual) {
+                return Err(format!(
+                    "Downloaded cursorup does not match the published checksum (expected {}, got {})",
+                    published, actual
+                )
+                .into());
+            }
+            verified_with.push("sha256".to_string());
+        }
+        None if allow_unsigned => {
+            tracing::warn!("release publishes no checksum; installing unverified binary");
+        }
+        None => {
+            return Err(format!(
+                "Release {} publishes no checksum for `{}`; refusing to install (use --allow-unsigned to override)",
+                release.tag_name, name
+            )
+            .into());
+        }
+    }
+
+    match find(&format!("{}.minisig", name)) {
+        Some(url) => {
+            let signature = client
+                .get(&url)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?;
+            crate::minisign::PublicKey::from_base64(&config.self_update_pubkey)?
+                .verify(&binary, &signature)?;
+            verified_with.push("minisign".to_string());
+        }
+        None if allow_unsigned => {
+            tracing::warn!("release is not signed; installing without checking the signature");
+        }
+        None => {
+            return Err(format!(
+                "Release {} is not signed; refusing to install (use --allow-unsigned to override)",
+                release.tag_name
+            )
+            .into());
+        }
+    }
+
+    replace_executable(&binary).await?;
+    Ok(SelfUpdate {
+        current,
+        latest,
+        updated: true,
+        verified_with,
+    })
+}
+
+/// Swaps the running executable by writing next to it and renaming over it.
+async fn replace_executable(binary: &[u8]) -> Result<()> {
+    let exe = std::env::current_exe()?;
+    let dir = exe.parent().unwrap_or(Path::new("/"));
+    let staged = dir.join(".cursorup-update");
+    fs::write(&staged, binary).await?;
+    // Keep whatever permissions the installed binary was given.
+    fs::set_permissions(&staged, fs::metadata(&exe).await?.permissions()).await?;
+    if let Err(e) = fs::rename(&staged, &exe).await {
+        let _ = fs::remove_file(&staged).await;
+        return Err(format!("Failed to replace {:?}: {}", exe, e).into());
+    }
+    Ok(())
+}
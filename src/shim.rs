@@ -0,0 +1,155 @@
+//! `cursorup shim code`: a `code` command next to the `cursor` wrapper, for
+//! scripts and git configs (`core.editor = "code --wait"`) written for VS
+//! Code. It is a symlink to the wrapper, so it starts whatever version is
+//! installed with the configured launch flags.
+
+use crate::{Result, events, wrapper};
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Name of the command the shim provides.
+pub const CODE: &str = "code";
+
+#[derive(Serialize, Debug)]
+pub struct Shim {
+    pub path: PathBuf,
+    pub target: PathBuf,
+    /// Other `code` commands on `PATH`, e.g. VS Code's, kept with `--force`.
+    pub conflicts: Vec<PathBuf>,
+}
+
+impl fmt::Display for Shim {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` runs Cursor: {} -> {}",
+            CODE,
+            self.path.display(),
+            self.target.display()
+        )?;
+        for conflict in &self.conflicts {
+            write!(
+                f,
+                "\nAlso on PATH: {}; whichever directory comes first on PATH wins",
+                conflict.display()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Where the shim goes: next to the `cursor` wrapper.
+pub fn path() -> Result<PathBuf> {
+    Ok(wrapper::wrapper_path()?.with_file_name(CODE))
+}
+
+/// Links `code` to the `cursor` wrapper. Refuses if another `code`, most
+/// likely VS Code, is on `PATH`, unless `force`; a file of that name that
+/// is not the shim is never replaced.
+pub async fn create(force: bool) -> Result<Shim> {
+    let target = wrapper::wrapper_path()?;
+    if !fs::try_exists(&target).await? {
+        return Err(format!(
+            "The `cursor` wrapper {:?} does not exist; set cli_wrapper = true and run `cursorup install` first",
+            target
+        )
+        .into());
+    }
+    let path = path()?;
+    let conflicts = conflicts(&path);
+    if !conflicts.is_empty() && !force {
+        return Err(format!(
+            "Another `{}` command is installed at {}, probably VS Code; pass --force to add the shim anyway (the first one on PATH wins)",
+            CODE,
+            conflicts
+                .iter()
+                .map(|path| format!("{:?}", path))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+        .into());
+    }
+    if is_shim(&path).await {
+        return Ok(Shim {
+            path,
+            target,
+            conflicts,
+        });
+    }
+    if fs::symlink_metadata(&path).await.is_ok() {
+        return Err(format!(
+            "{:?} exists and was not created by cursorup; remove it to use it as the shim",
+            path
+        )
+        .into());
+    }
+    link(&path).await?;
+    events::log(format!("Linked {:?} to {:?}", path, target));
+    Ok(Shim {
+        path,
+        target,
+        conflicts,
+    })
+}
+
+/// Relative, like the `current` link, as both live in the same dir.
+#[cfg(unix)]
+async fn link(path: &Path) -> Result<()> {
+    fs::symlink(wrapper::CURRENT_LINK, path).await?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn link(_path: &Path) -> Result<()> {
+    Err(format!("The `{}` shim is only supported on Unix", CODE).into())
+}
+
+/// Removes the shim, leaving any other `code` alone.
+pub async fn remove() -> Result<PathBuf> {
+    let path = path()?;
+    if !is_shim(&path).await {
+        return Err(format!("{:?} is not a cursorup shim", path).into());
+    }
+    fs::remove_file(&path).await?;
+    events::log(format!("Removed {:?}", path));
+    Ok(path)
+}
+
+/// Whether `path` is the symlink `create` makes.
+pub async fn is_shim(path: &Path) -> bool {
+    match fs::read_link(path).await {
+        Ok(target) => {
+            target == Path::new(wrapper::CURRENT_LINK)
+                || wrapper::wrapper_path().is_ok_and(|wrapper| target == wrapper)
+        }
+        Err(_) => false,
+    }
+}
+
+/// Executable `code` files on `PATH` other than `shim`.
+fn conflicts(shim: &Path) -> Vec<PathBuf> {
+    let Some(path) = std::env::var_os("PATH") else {
+        return Vec::new();
+    };
+    let mut found: Vec<PathBuf> = Vec::new();
+    for candidate in std::env::split_paths(&path).map(|dir| dir.join(CODE)) {
+        if is_executable(&candidate) && candidate != shim && !found.contains(&candidate) {
+            found.push(candidate);
+        }
+    }
+    found
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
@@ -0,0 +1,156 @@
+//! Post-install smoke test: runs the new AppImage with `--version` to make
+//! sure it starts at all. A broken build is rolled back, but Chromium
+//! sandbox errors are not the build's fault (rolling back would hit them
+//! again), so for those the user is told how to fix the kernel setting or
+//! offered to launch Cursor with `--no-sandbox` from now on.
+
+use crate::config::Config;
+use crate::{Result, events, fsutil, wrapper};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+pub const NO_SANDBOX_FLAG: &str = "--no-sandbox";
+
+/// `--version` returns immediately; anything slower is hung.
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// What Chromium prints when it cannot set up its sandbox.
+const SANDBOX_ERRORS: &[&str] = &[
+    "SUID sandbox helper binary",
+    "No usable sandbox",
+    "setuid_sandbox_host",
+    "chrome-sandbox",
+];
+
+const SANDBOX_HINT: &str = "Cursor could not start its sandbox. Allow unprivileged user namespaces \
+     with `sudo sysctl -w kernel.apparmor_restrict_unprivileged_userns=0` (Ubuntu 24.04 and later) \
+     or `sudo sysctl -w kernel.unprivileged_userns_clone=1` (older kernels), or launch Cursor \
+     with --no-sandbox (no_sandbox = true)";
+
+enum Outcome {
+    Passed,
+    SandboxError(String),
+    Failed(String),
+}
+
+async fn run(appimage: &Path, launch_args: &[&str]) -> Result<Outcome> {
+    let mut command = Command::new(appimage);
+    command
+        .args(launch_args)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .kill_on_drop(true);
+    let output = match tokio::time::timeout(TIMEOUT, command.output()).await {
+        Ok(output) => output?,
+        Err(_) => {
+            return Ok(Outcome::Failed(format!(
+                "no answer within {}s",
+                TIMEOUT.as_secs()
+            )));
+        }
+    };
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    if output.status.success() {
+        return Ok(Outcome::Passed);
+    }
+    let text = text.trim().to_string();
+    if SANDBOX_ERRORS.iter().any(|error| text.contains(error)) {
+        Ok(Outcome::SandboxError(text))
+    } else {
+        Ok(Outcome::Failed(format!("{}: {}", output.status, text)))
+    }
+}
+
+/// Smoke-tests the freshly installed `appimage`. Unless `smoke_test` is
+/// off, a build that does not start is rolled back when there is a backup
+/// to return to, and the install fails either way.
+pub async fn verify_or_roll_back(config: &Config, appimage: &Path, verbose: bool) -> Result<()> {
+    if !config.smoke_test {
+        return Ok(());
+    }
+    let failure = match verify(config, appimage, verbose).await {
+        Ok(()) => return Ok(()),
+        Err(e) => e.to_string(),
+    };
+    if crate::backup::list(&config.install_dir).await?.0.is_empty() {
+        return Err(format!("{}; there is no backup to roll back to", failure).into());
+    }
+    let rolled_back = crate::rollback::rollback(config, verbose).await?;
+    Err(format!("{}; rolled back to Cursor {}", failure, rolled_back.to).into())
+}
+
+async fn verify(config: &Config, appimage: &Path, verbose: bool) -> Result<()> {
+    events::log(format!("Smoke-testing {:?}", appimage));
+    match run(appimage, &config.launch_args()).await? {
+        Outcome::Passed => {
+            events::log("Smoke test passed");
+            Ok(())
+        }
+        Outcome::Failed(output) => {
+            Err(format!("{:?} --version failed: {}", appimage, output).into())
+        }
+        Outcome::SandboxError(output) => {
+            tracing::debug!("sandbox error: {}", output);
+            sandbox_error(config, appimage, verbose).await
+        }
+    }
+}
+
+/// Keeps the update: with consent, switches to `--no-sandbox` and tests
+/// again; otherwise only explains what to change.
+async fn sandbox_error(config: &Config, appimage: &Path, verbose: bool) -> Result<()> {
+    tracing::warn!("{}", SANDBOX_HINT);
+    let confirmed = events::ask(events::Prompt::DisableSandbox)
+        .await?
+        .unwrap_or(false);
+    if !confirmed {
+        events::log("Keeping the update; Cursor will not start until the sandbox is fixed");
+        return Ok(());
+    }
+    Config::save_setting("no_sandbox", "true").await?;
+    let mut config = config.clone();
+    config.no_sandbox = true;
+    rewrite_launchers(&config, appimage, verbose).await?;
+    match run(appimage, &config.launch_args()).await? {
+        Outcome::Passed => {
+            events::log("Smoke test passed with --no-sandbox");
+            Ok(())
+        }
+        Outcome::SandboxError(output) | Outcome::Failed(output) => Err(format!(
+            "{:?} --version still fails with --no-sandbox: {}",
+            appimage, output
+        )
+        .into()),
+    }
+}
+
+/// Writes the wrapper, desktop entries and profile launchers again so they
+/// pick up the new launch arguments.
+async fn rewrite_launchers(config: &Config, appimage: &Path, verbose: bool) -> Result<()> {
+    let install_dir = &config.install_dir;
+    if config.cli_wrapper {
+        let mode = fsutil::executable_mode(config.file_mode);
+        wrapper::write_wrapper(install_dir, mode, &config.launch_args()).await?;
+    }
+    if config.desktop_entry {
+        if config.system_install {
+            crate::system::prepare(config).await?;
+        } else {
+            crate::echo_2_desktop(
+                &config.desktop_name,
+                appimage,
+                &install_dir.join("code.png"),
+                &config.launch_args(),
+                verbose,
+            )
+            .await?;
+        }
+    }
+    crate::profiles::refresh(config).await
+}
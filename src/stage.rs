@@ -0,0 +1,163 @@
+//! `update --stage` and `commit`: the download and verification happen
+//! whenever convenient, the swap into place later and without network.
+
+use crate::config::Config;
+use crate::provenance::Provenance;
+use crate::state::DownloadSample;
+use crate::{InstallOptions, Prepared, Resp, Result, events, fsutil};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+/// Staging area inside the install dir, so committing is a rename on the
+/// same filesystem.
+pub const STAGE_DIR: &str = ".staged";
+
+const STAGED_FILE: &str = "staged.json";
+const ICON_FILE: &str = "code.png";
+
+/// What `staged.json` records about the staged release.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Staged {
+    pub metadata: Resp,
+    pub provenance: Provenance,
+    pub download: Option<DownloadSample>,
+    /// Seconds since the Unix epoch.
+    pub staged_at: u64,
+}
+
+pub fn dir(config: &Config) -> PathBuf {
+    config.install_dir.join(STAGE_DIR)
+}
+
+/// Downloads, verifies and extracts the release into the staging area,
+/// replacing anything staged before.
+pub async fn stage(config: &Config, options: &InstallOptions) -> Result<()> {
+    let prepared = crate::fetch_release(config, options).await?;
+
+    if config.system_install {
+        crate::system::prepare(config).await?;
+    }
+    let stage_dir = dir(config);
+    if fs::try_exists(&stage_dir).await? {
+        fs::remove_dir_all(&stage_dir).await?;
+    }
+    fs::create_dir_all(&stage_dir).await?;
+
+    let appimage = stage_dir.join(&prepared.provenance.file);
+    if config.hardlinks {
+        fsutil::link_or_copy(&prepared.appimage, &appimage).await?;
+    } else {
+        fs::copy(&prepared.appimage, &appimage).await?;
+    }
+    let tmp_dir = crate::tmpdir::TmpDir::new()?;
+    let extracted_dir = crate::extract(
+        &appimage,
+        &tmp_dir.path,
+        fsutil::executable_mode(config.file_mode),
+        config.execution_mode,
+    )
+    .await?;
+    fs::copy(extracted_dir.join(ICON_FILE), stage_dir.join(ICON_FILE)).await?;
+
+    let version = prepared.metadata.version.clone();
+    let staged = Staged {
+        metadata: prepared.metadata,
+        provenance: prepared.provenance,
+        download: prepared.download,
+        staged_at: crate::state::now(),
+    };
+    fsutil::write_atomic(
+        &stage_dir.join(STAGED_FILE),
+        serde_json::to_string_pretty(&staged)?,
+    )
+    .await?;
+    events::log(format!(
+        "Staged Cursor {} in {:?}; run `cursorup commit` to install it",
+        version, stage_dir
+    ));
+    Ok(())
+}
+
+/// The staged release, if there is one.
+pub async fn load(config: &Config) -> Result<Option<Staged>> {
+    let path = dir(config).join(STAGED_FILE);
+    match fs::read_to_string(&path).await {
+        Ok(contents) => Ok(Some(serde_json::from_str(&contents).map_err(|e| {
+            format!("{}: invalid staged update: {}", path.display(), e)
+        })?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Installs the staged release by moving its files into place. Works
+/// offline; the staging area is removed afterwards.
+pub async fn commit(config: &Config, verbose: bool) -> Result<()> {
+    let stage_dir = dir(config);
+    let staged = load(config)
+        .await?
+        .ok_or("Nothing is staged; run `cursorup update --stage` first")?;
+    let appimage = stage_dir.join(&staged.provenance.file);
+    check_size(&appimage, staged.provenance.installed_size()).await?;
+
+    let previous_version = crate::status::installed_appimage(&config.install_dir)
+        .await?
+        .and_then(|path| crate::status::version_from_file_name(&path));
+    let prepared = Prepared {
+        metadata: staged.metadata,
+        provenance: staged.provenance,
+        download: staged.download,
+        appimage,
+        icon: stage_dir.join(ICON_FILE),
+    };
+    crate::confirm_replace(previous_version.as_deref(), &prepared.metadata.version).await?;
+    let result = async {
+        let mut journal =
+            crate::journal::Journal::begin(config, &prepared, previous_version.clone(), false)
+                .await?;
+        crate::activate(config, prepared, &mut journal, true, verbose)
+            .await
+            .map_err(|e| journal.interrupted(e))
+    }
+    .await;
+    if let Err(e) = &result {
+        crate::history::record_failure(previous_version, e.as_ref()).await;
+    }
+    let appimage = result?;
+    crate::journal::clear().await?;
+    fs::remove_dir_all(&stage_dir).await?;
+    events::log("Committed the staged update.");
+    crate::smoke::verify_or_roll_back(config, &appimage, verbose).await?;
+    if let Some(version) = crate::status::version_from_file_name(&appimage) {
+        crate::restart::after_update(config, &version).await?;
+    }
+    Ok(())
+}
+
+/// Removes a staged update that a regular update has made obsolete.
+pub async fn discard(config: &Config) -> Result<()> {
+    let stage_dir = dir(config);
+    if fs::try_exists(&stage_dir).await? {
+        fs::remove_dir_all(&stage_dir).await?;
+        events::log(format!("Discarded the update staged in {:?}", stage_dir));
+    }
+    Ok(())
+}
+
+/// A cheap guard against a staged file that was truncated or replaced since
+/// it was verified.
+async fn check_size(appimage: &Path, expected: u64) -> Result<()> {
+    let size = fs::metadata(appimage)
+        .await
+        .map_err(|e| format!("Staged AppImage {:?} is unreadable: {}", appimage, e))?
+        .len();
+    if size != expected {
+        return Err(format!(
+            "Staged AppImage {:?} has {} bytes, expected {}; stage the update again",
+            appimage, size, expected
+        )
+        .into());
+    }
+    Ok(())
+}
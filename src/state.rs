@@ -0,0 +1,210 @@
+use crate::Result;
+use crate::config::Config;
+use crate::{Platform, ReleaseTrack};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::fs;
+
+/// Current layout of `state.json`; bumped whenever fields change meaning.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// How many download measurements are kept.
+const MAX_DOWNLOAD_SAMPLES: usize = 20;
+
+/// What cursorup last installed, persisted at `~/.local/share/cursorup/state.json`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct State {
+    /// Files written before versioning was introduced deserialize as 0.
+    #[serde(default)]
+    pub schema: u32,
+    /// Launcher name the install was made under.
+    #[serde(default = "default_name")]
+    pub name: String,
+    pub version: String,
+    pub commit_sha: String,
+    pub appimage: PathBuf,
+    /// SHA-256 of `appimage` as cursorup placed it, for `cursorup verify`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Files written before platforms were recorded were always linux-x64.
+    #[serde(default)]
+    pub platform: Platform,
+    pub release_track: ReleaseTrack,
+    /// Seconds since the Unix epoch.
+    pub installed_at: u64,
+    /// Throughput of recent downloads, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub downloads: Vec<DownloadSample>,
+    /// Version replaced by `cursorup rollback`, if that is how this one was
+    /// installed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rolled_back_from: Option<String>,
+}
+
+/// One completed download, used to size buffers for the next one and shown
+/// by `status --verbose`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DownloadSample {
+    /// Seconds since the Unix epoch.
+    pub at: u64,
+    /// Host the artifact came from, to tell mirrors and proxies apart.
+    pub host: String,
+    pub bytes: u64,
+    pub bytes_per_second: u64,
+}
+
+impl State {
+    pub fn new(
+        name: &str,
+        version: &str,
+        commit_sha: &str,
+        appimage: &Path,
+        platform: Platform,
+        release_track: ReleaseTrack,
+    ) -> Self {
+        Self {
+            schema: SCHEMA_VERSION,
+            name: name.to_string(),
+            version: version.to_string(),
+            commit_sha: commit_sha.to_string(),
+            appimage: appimage.to_path_buf(),
+            sha256: None,
+            platform,
+            release_track,
+            installed_at: now(),
+            downloads: Vec::new(),
+            rolled_back_from: None,
+        }
+    }
+
+    /// Appends a measurement, dropping the oldest beyond the retention limit.
+    pub fn record_download(&mut self, sample: DownloadSample) {
+        self.downloads.push(sample);
+        let excess = self.downloads.len().saturating_sub(MAX_DOWNLOAD_SAMPLES);
+        self.downloads.drain(..excess);
+    }
+
+    /// Median throughput of the recorded downloads.
+    pub fn median_bytes_per_second(&self) -> Option<u64> {
+        let mut speeds: Vec<u64> = self.downloads.iter().map(|s| s.bytes_per_second).collect();
+        speeds.sort_unstable();
+        speeds.get(speeds.len() / 2).copied()
+    }
+
+    /// Directory holding cursorup's own data, honouring `XDG_DATA_HOME`.
+    pub fn dir() -> Result<PathBuf> {
+        let base = match std::env::var_os("XDG_DATA_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => crate::home_dir()?.join(".local/share"),
+        };
+        Ok(base.join("cursorup"))
+    }
+
+    pub fn path() -> Result<PathBuf> {
+        Ok(Self::dir()?.join("state.json"))
+    }
+
+    pub async fn load() -> Result<Option<State>> {
+        let path = Self::path()?;
+        let Some(contents) = read_if_exists(&path).await? else {
+            return Ok(None);
+        };
+        let state = serde_json::from_str(&contents)
+            .map_err(|e| format!("{}: invalid state file: {}", path.display(), e))?;
+        Ok(Some(check_schema(state, &path)?))
+    }
+
+    /// Like `load`, but a corrupted or truncated state file is moved aside to
+    /// `state.json.corrupt` and rebuilt from the AppImage found in the
+    /// install dir instead of failing.
+    pub async fn load_or_recover(config: &Config) -> Result<Option<State>> {
+        let path = Self::path()?;
+        let Some(contents) = read_if_exists(&path).await? else {
+            return Ok(None);
+        };
+        let error = match serde_json::from_str(&contents) {
+            Ok(state) => return Ok(Some(check_schema(state, &path)?)),
+            Err(e) => e,
+        };
+
+        let mut corrupt_path = path.clone().into_os_string();
+        corrupt_path.push(".corrupt");
+        tracing::warn!(
+            "{}: invalid state file: {}; moved it to {:?} and re-detecting the installation",
+            path.display(),
+            error,
+            corrupt_path
+        );
+        fs::rename(&path, &corrupt_path).await?;
+
+        let Some(state) = Self::detect(config).await? else {
+            return Ok(None);
+        };
+        state.save().await?;
+        Ok(Some(state))
+    }
+
+    /// Reconstructs what can be known from the install dir alone.
+    pub async fn detect(config: &Config) -> Result<Option<State>> {
+        let Some(appimage) = crate::status::installed_appimage(&config.install_dir).await? else {
+            return Ok(None);
+        };
+        let Some(version) = crate::status::version_from_file_name(&appimage) else {
+            return Ok(None);
+        };
+        let mut state = State::new(
+            &config.desktop_name,
+            &version,
+            "",
+            &appimage,
+            config.platform,
+            config.release_track,
+        );
+        if let Ok(metadata) = fs::metadata(&appimage).await
+            && let Ok(modified) = metadata.modified()
+        {
+            state.installed_at = modified
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+        }
+        Ok(Some(state))
+    }
+
+    pub async fn save(&self) -> Result<()> {
+        crate::fsutil::write_atomic(&Self::path()?, serde_json::to_string_pretty(self)?).await
+    }
+}
+
+async fn read_if_exists(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path).await {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn check_schema(state: State, path: &Path) -> Result<State> {
+    if state.schema > SCHEMA_VERSION {
+        return Err(format!(
+            "{}: state schema {} was written by a newer cursorup (this one understands up to {})",
+            path.display(),
+            state.schema,
+            SCHEMA_VERSION
+        )
+        .into());
+    }
+    Ok(state)
+}
+
+fn default_name() -> String {
+    "Cursor".to_string()
+}
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,267 @@
+use crate::Result;
+use crate::config::Config;
+use crate::state::DownloadSample;
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+
+#[derive(Serialize, Debug)]
+pub struct Status {
+    pub name: String,
+    pub install_dir: PathBuf,
+    pub release_track: crate::ReleaseTrack,
+    pub installed: Option<Installed>,
+    pub desktop_entry: Option<PathBuf>,
+    pub cli_wrapper: Option<PathBuf>,
+    /// Version prepared by `update --stage`, waiting for `commit`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub staged: Option<String>,
+    /// A failed scheduled update the daemon will retry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry: Option<crate::daemon::PendingRetry>,
+    /// Recent download throughput, only filled in for `--verbose`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub downloads: Vec<DownloadSample>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Installed {
+    pub appimage: PathBuf,
+    pub version: Option<String>,
+    pub commit_sha: Option<String>,
+    /// SHA-256 recorded in the state file when it was installed.
+    pub sha256: Option<String>,
+    /// The file no longer matches what cursorup installed.
+    pub modified_externally: bool,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.installed {
+            Some(installed) => {
+                writeln!(
+                    f,
+                    "Installed:     {} ({})",
+                    installed.version.as_deref().unwrap_or("unknown version"),
+                    installed.appimage.display()
+                )?;
+                if let Some(commit_sha) = &installed.commit_sha {
+                    writeln!(f, "Commit:        {}", commit_sha)?;
+                }
+                if let Some(sha256) = &installed.sha256 {
+                    writeln!(f, "SHA-256:       {}", sha256)?;
+                }
+                if installed.modified_externally {
+                    writeln!(
+                        f,
+                        "Warning:       {}",
+                        crate::manifest::modified_warning(&installed.appimage)
+                    )?;
+                }
+            }
+            None => writeln!(f, "Installed:     not installed")?,
+        }
+        writeln!(f, "Name:          {}", self.name)?;
+        writeln!(f, "Install dir:   {}", self.install_dir.display())?;
+        writeln!(f, "Release track: {}", self.release_track)?;
+        writeln!(f, "Desktop entry: {}", display_opt(&self.desktop_entry))?;
+        write!(f, "CLI wrapper:   {}", display_opt(&self.cli_wrapper))?;
+        if let Some(staged) = &self.staged {
+            write!(
+                f,
+                "\nStaged:        {} (run `cursorup commit` to install)",
+                staged
+            )?;
+        }
+        if let Some(retry) = &self.retry {
+            write!(
+                f,
+                "\nRetry:         attempt {} at {} after: {}",
+                retry.attempts + 1,
+                crate::output::format_timestamp(retry.next_at),
+                retry.error
+            )?;
+        }
+        if !self.downloads.is_empty() {
+            write!(f, "\nRecent downloads:")?;
+        }
+        for sample in self.downloads.iter().rev() {
+            write!(
+                f,
+                "\n  {}  {:>8.2} MB/s  {:>8.2} MB  from {}",
+                crate::output::format_timestamp(sample.at),
+                sample.bytes_per_second as f64 / 1_048_576.0,
+                sample.bytes as f64 / 1_048_576.0,
+                sample.host
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn display_opt(path: &Option<PathBuf>) -> String {
+    match path {
+        Some(path) => path.display().to_string(),
+        None => "not installed".to_string(),
+    }
+}
+
+/// Inspects the install dir and integration files without touching the
+/// network. `verbose` adds the recorded download history.
+pub async fn status(config: &Config, verbose: bool) -> Result<Status> {
+    let state = crate::state::State::load_or_recover(config).await?;
+    let mut name = config.desktop_name.clone();
+    let installed = match installed_appimage(&config.install_dir).await? {
+        Some(appimage) => Some((
+            crate::manifest::modified_externally(&config.install_dir, &appimage).await?,
+            appimage,
+        )),
+        None => None,
+    };
+    let installed = installed.map(|(modified_externally, appimage)| {
+        // Only trust the recorded build if it is still the installed file.
+        let recorded = state.as_ref().filter(|state| state.appimage == appimage);
+        if let Some(state) = recorded {
+            name = state.name.clone();
+        }
+        Installed {
+            version: recorded
+                .map(|state| state.version.clone())
+                .or_else(|| version_from_file_name(&appimage)),
+            commit_sha: recorded
+                .map(|state| state.commit_sha.clone())
+                .filter(|sha| !sha.is_empty()),
+            sha256: recorded.and_then(|state| state.sha256.clone()),
+            modified_externally,
+            appimage,
+        }
+    });
+    Ok(Status {
+        name,
+        install_dir: config.install_dir.clone(),
+        release_track: config.release_track,
+        installed,
+        desktop_entry: existing(crate::desktop_entry_path(config.system_install)?).await,
+        cli_wrapper: existing(crate::wrapper::wrapper_path()?).await,
+        staged: crate::stage::load(config)
+            .await
+            .ok()
+            .flatten()
+            .map(|staged| staged.metadata.version),
+        retry: crate::daemon::PendingRetry::load().await.ok().flatten(),
+        downloads: match (verbose, state) {
+            (true, Some(state)) => state.downloads,
+            _ => Vec::new(),
+        },
+    })
+}
+
+/// Result of re-hashing the installed AppImage for `cursorup verify`.
+#[derive(Serialize, Debug)]
+pub struct Verification {
+    pub appimage: PathBuf,
+    pub sha256: String,
+    /// What cursorup recorded when it installed the file, if anything.
+    pub recorded_sha256: Option<String>,
+    pub ok: bool,
+}
+
+impl fmt::Display for Verification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.recorded_sha256, self.ok) {
+            (Some(_), true) => write!(f, "ok        {}", self.appimage.display()),
+            (Some(recorded), false) => write!(
+                f,
+                "MISMATCH  {} (expected {}, got {})",
+                self.appimage.display(),
+                recorded,
+                self.sha256
+            ),
+            (None, _) => write!(
+                f,
+                "unknown   {} (no checksum recorded, {})",
+                self.appimage.display(),
+                self.sha256
+            ),
+        }
+    }
+}
+
+/// Re-hashes the installed AppImage against the checksum in the state file,
+/// or the install dir manifest for installs recorded before the state file
+/// kept one. A matching hash is written back to the state file if missing.
+pub async fn verify(config: &Config) -> Result<Verification> {
+    let appimage = installed_appimage(&config.install_dir)
+        .await?
+        .ok_or("Cursor is not installed")?;
+    let sha256 = crate::cache::sha256_file(&appimage).await?;
+    let mut state = crate::state::State::load_or_recover(config)
+        .await?
+        .filter(|state| state.appimage == appimage);
+    let mut recorded_sha256 = state.as_ref().and_then(|state| state.sha256.clone());
+    if recorded_sha256.is_none() {
+        recorded_sha256 = crate::manifest::Manifest::load(&config.install_dir)
+            .await?
+            .and_then(|manifest| manifest.appimage)
+            .filter(|record| appimage.file_name() == Some(std::ffi::OsStr::new(&record.file)))
+            .map(|record| record.sha256);
+    }
+    let ok = recorded_sha256.as_deref() == Some(sha256.as_str());
+    if let Some(state) = state.as_mut().filter(|state| ok && state.sha256.is_none()) {
+        state.sha256 = Some(sha256.clone());
+        state.save().await?;
+    }
+    Ok(Verification {
+        appimage,
+        sha256,
+        recorded_sha256,
+        ok,
+    })
+}
+
+async fn existing(path: PathBuf) -> Option<PathBuf> {
+    fs::try_exists(&path).await.ok()?.then_some(path)
+}
+
+/// Finds the currently installed AppImage, preferring the stable `cursor`
+/// symlink and falling back to the newest-looking `*.AppImage` in the dir.
+pub async fn installed_appimage(install_dir: &Path) -> Result<Option<PathBuf>> {
+    let link = install_dir.join(crate::wrapper::CURRENT_LINK);
+    if let Ok(target) = fs::read_link(&link).await {
+        let target = install_dir.join(target);
+        if fs::try_exists(&target).await? {
+            return Ok(Some(target));
+        }
+    }
+
+    let mut entries = match fs::read_dir(install_dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut found = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|s| s.to_str()) == Some("AppImage") {
+            found.push(path);
+        }
+    }
+    // Newest version last, so 1.10.0 wins over 1.9.3.
+    found.sort_by(|a, b| {
+        let versions = version_from_file_name(a).zip(version_from_file_name(b));
+        versions
+            .and_then(|(a, b)| crate::version::compare(&a, &b))
+            .unwrap_or_else(|| a.cmp(b))
+    });
+    Ok(found.pop())
+}
+
+/// Extracts the version from file names like `Cursor-1.2.3-x86_64.AppImage`.
+pub fn version_from_file_name(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_str()?;
+    let version = stem.split('-').nth(1)?;
+    version
+        .starts_with(|c: char| c.is_ascii_digit())
+        .then(|| version.to_string())
+}
@@ -0,0 +1,154 @@
+//! System-wide installs into `/opt/cursor`. Only creating that directory
+//! (group-writable, so later updates need no privileges) and writing the
+//! launcher in `/usr/share/applications` require root; downloads and
+//! everything else stay unprivileged.
+
+use crate::config::Config;
+use crate::{Result, events};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use tokio::fs;
+use tokio::process::Command;
+
+pub const INSTALL_DIR: &str = "/opt/cursor";
+pub const DESKTOP_ENTRY: &str = "/usr/share/applications/cursor.desktop";
+
+/// Creates the install dir owned by group `$1` with the setgid bit, so files
+/// added later keep that group, and installs the desktop entry `$3` as `$4`.
+const SETUP_SCRIPT: &str = r#"set -e
+mkdir -p "$2"
+chgrp "$1" "$2"
+chmod 2775 "$2"
+if [ -n "$3" ]; then
+    mkdir -p "$(dirname "$4")"
+    cp "$3" "$4"
+    chmod 0644 "$4"
+fi"#;
+
+/// How to gain root for the privileged steps when not already running as root.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Elevation {
+    /// Ask through polkit; works from desktop sessions and terminals.
+    #[default]
+    Pkexec,
+    /// Fail with instructions instead.
+    None,
+}
+
+impl FromStr for Elevation {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "pkexec" => Ok(Elevation::Pkexec),
+            "none" => Ok(Elevation::None),
+            _ => Err(format!(
+                "unknown elevation `{}` (expected pkexec or none)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Elevation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Elevation::Pkexec => "pkexec",
+            Elevation::None => "none",
+        })
+    }
+}
+
+/// The system desktop entry points at the stable `current` link rather than
+/// the versioned AppImage, so updates don't need to rewrite it.
+pub fn desktop_entry_contents(config: &Config) -> String {
+    crate::desktop_entry_contents(
+        &config.desktop_name,
+        &config.install_dir.join(crate::wrapper::CURRENT_LINK),
+        &config.install_dir.join("code.png"),
+        &config.launch_args(),
+    )
+}
+
+/// Makes sure the install dir is writable and the system desktop entry is
+/// current, running the privileged setup once if either isn't.
+pub async fn prepare(config: &Config) -> Result<()> {
+    let entry = config.desktop_entry.then(|| desktop_entry_contents(config));
+    let entry_current = match &entry {
+        Some(contents) => {
+            fs::read_to_string(DESKTOP_ENTRY).await.ok().as_deref() == Some(contents.as_str())
+        }
+        None => true,
+    };
+    if entry_current && writable(&config.install_dir).await {
+        return Ok(());
+    }
+
+    let tmp_dir = crate::tmpdir::TmpDir::new()?;
+    let entry_file = match &entry {
+        Some(contents) => {
+            let path = tmp_dir.path.join("cursor.desktop");
+            fs::write(&path, contents).await?;
+            path.to_string_lossy().into_owned()
+        }
+        None => String::new(),
+    };
+
+    let (program, mut command) = if crate::fsutil::is_root() {
+        ("/bin/sh", Command::new("/bin/sh"))
+    } else {
+        match config.elevate {
+            Elevation::Pkexec => {
+                let mut command = Command::new("pkexec");
+                command.arg("/bin/sh");
+                ("pkexec", command)
+            }
+            Elevation::None => {
+                return Err(format!(
+                    "{:?} is not writable; create it with `sudo install -d -m 2775 -g $(id -g) {}` or set elevate = \"pkexec\"",
+                    config.install_dir,
+                    config.install_dir.display()
+                )
+                .into());
+            }
+        }
+    };
+    events::log(format!(
+        "Setting up {:?}{} with elevated privileges",
+        config.install_dir,
+        if entry.is_some() {
+            format!(" and {}", DESKTOP_ENTRY)
+        } else {
+            String::new()
+        }
+    ));
+    let status = command
+        .arg("-c")
+        .arg(SETUP_SCRIPT)
+        .arg("sh")
+        .arg(crate::fsutil::primary_gid().to_string())
+        .arg(&config.install_dir)
+        .arg(entry_file)
+        .arg(DESKTOP_ENTRY)
+        .status()
+        .await
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    if !status.success() {
+        return Err(format!(
+            "Privileged setup of {:?} failed ({})",
+            config.install_dir, status
+        )
+        .into());
+    }
+    Ok(())
+}
+
+/// Whether files can be created in `dir`, which must already exist.
+async fn writable(dir: &Path) -> bool {
+    let probe = dir.join(".cursorup-write-test");
+    match fs::File::create(&probe).await {
+        Ok(_) => fs::remove_file(&probe).await.is_ok(),
+        Err(_) => false,
+    }
+}
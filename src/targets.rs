@@ -0,0 +1,250 @@
+//! `cursorup install --targets desktop,reh,cli`: installs several components
+//! of one build together. The metadata is resolved once, every download runs
+//! concurrently and is verified before anything is put in place, and a
+//! failure while committing leaves none of the components changed:
+//!
+//! - `desktop` is the AppImage with its desktop entry, as `install` does;
+//! - `reh` is the remote-development server, unpacked into
+//!   `~/.cursor-server/bin/<commit>` where Cursor's remote extension looks;
+//! - `cli` is the `cursor` command in `~/.local/bin`, whatever
+//!   `cli_wrapper` says.
+
+use crate::config::Config;
+use crate::{InstallOptions, Resp, Result, cache, events, provenance, urls, wrapper};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tokio::fs;
+use tokio::process::Command;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Target {
+    Desktop,
+    Reh,
+    Cli,
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "desktop" => Ok(Target::Desktop),
+            "reh" => Ok(Target::Reh),
+            "cli" => Ok(Target::Cli),
+            _ => Err(format!(
+                "unknown target `{}` (expected desktop, reh or cli)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Target::Desktop => "desktop",
+            Target::Reh => "reh",
+            Target::Cli => "cli",
+        })
+    }
+}
+
+/// Parses a comma-separated list such as `desktop,reh`, dropping repeats.
+pub fn parse_list(s: &str) -> std::result::Result<Vec<Target>, String> {
+    let mut targets = Vec::new();
+    for name in s.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+        let target = name.parse()?;
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+    if targets.is_empty() {
+        return Err("--targets expects at least one of desktop, reh or cli".to_string());
+    }
+    Ok(targets)
+}
+
+/// Where the remote-development server of `commit_sha` is installed.
+pub fn reh_dir(commit_sha: &str) -> Result<PathBuf> {
+    Ok(crate::home_dir()?
+        .join(".cursor-server")
+        .join("bin")
+        .join(commit_sha))
+}
+
+pub(crate) async fn install(
+    config: &Config,
+    options: &InstallOptions,
+    previous_version: Option<&str>,
+) -> Result<()> {
+    let wants = |target| options.targets.contains(&target);
+    if wants(Target::Cli)
+        && !wants(Target::Desktop)
+        && crate::status::installed_appimage(&config.install_dir)
+            .await?
+            .is_none()
+    {
+        return Err("The cli target needs Cursor installed; add the desktop target".into());
+    }
+    crate::preflight::require(config).await?;
+    let client = crate::http::client(config)?;
+    let metadata = crate::resolve_metadata(&client, config, options).await?;
+    let names: Vec<String> = options.targets.iter().map(Target::to_string).collect();
+    events::log(format!(
+        "Installing {} of Cursor {}",
+        names.join(", "),
+        metadata.version
+    ));
+    let reh_dest = reh_dir(&metadata.commit_sha)?;
+    let reh_needed = wants(Target::Reh) && !fs::try_exists(&reh_dest).await?;
+    if wants(Target::Reh) && !reh_needed {
+        events::log(format!("The remote server is already in {:?}", reh_dest));
+    }
+
+    // Fetch and verify everything before touching any installed component.
+    let desktop = async {
+        if wants(Target::Desktop) {
+            crate::fetch_build(config, &client, metadata.clone(), options)
+                .await
+                .map(Some)
+        } else {
+            Ok(None)
+        }
+    };
+    let reh = async {
+        if reh_needed {
+            fetch_reh(config, &client, &metadata, options.no_cache)
+                .await
+                .map(Some)
+        } else {
+            Ok(None)
+        }
+    };
+    let (fetched, reh_tarball) = tokio::try_join!(desktop, reh)?;
+    let reh_part = match &reh_tarball {
+        Some(tarball) => Some(unpack_reh(tarball, &reh_dest).await?),
+        None => None,
+    };
+
+    let mut config = config.clone();
+    config.cli_wrapper = wants(Target::Cli);
+    let appimage = match fetched {
+        Some(fetched) => {
+            match crate::install_fetched(&config, fetched, previous_version, options).await {
+                Ok(appimage) => Some(appimage),
+                Err(e) => {
+                    let message = e.to_string();
+                    if let Some(part) = &reh_part {
+                        let _ = fs::remove_dir_all(part).await;
+                    }
+                    return Err(message.into());
+                }
+            }
+        }
+        None => None,
+    };
+    if let Some(part) = &reh_part
+        && let Err(e) = fs::rename(part, &reh_dest).await
+    {
+        let _ = fs::remove_dir_all(part).await;
+        let mut message = format!(
+            "Could not move the remote server into {:?}: {}",
+            reh_dest, e
+        );
+        if appimage.is_some() {
+            match crate::rollback::rollback(&config, options.verbose).await {
+                Ok(rolled_back) => {
+                    message.push_str(&format!("; rolled back to Cursor {}", rolled_back.to))
+                }
+                Err(e) => message.push_str(&format!("; rolling back failed too: {}", e)),
+            }
+        }
+        return Err(message.into());
+    }
+    if reh_part.is_some() {
+        events::log(format!("Installed the remote server to {:?}", reh_dest));
+    }
+    if wants(Target::Cli) && !wants(Target::Desktop) {
+        let mode = crate::fsutil::executable_mode(config.file_mode);
+        wrapper::write_wrapper(&config.install_dir, mode, &config.launch_args()).await?;
+        wrapper::ensure_on_path().await?;
+    }
+    if let Some(appimage) = appimage {
+        crate::smoke::verify_or_roll_back(&config, &appimage, options.verbose).await?;
+    }
+    if options.no_cache {
+        cache::prune_except(config.platform, None).await?;
+    }
+    events::log("Cursorup process finished successfully.");
+    Ok(())
+}
+
+/// Downloads the remote server tarball into the cache, unless a complete
+/// copy is there already and `no_cache` is not set.
+async fn fetch_reh(
+    config: &Config,
+    client: &reqwest::Client,
+    metadata: &Resp,
+    no_cache: bool,
+) -> Result<PathBuf> {
+    let url = if metadata.reh_url.is_empty() {
+        urls::mirrored(
+            &urls::reh_url(&metadata.commit_sha, config.platform),
+            config.download_base.as_deref(),
+        )
+    } else {
+        urls::mirrored(&metadata.reh_url, config.download_base.as_deref())
+    };
+    let path = cache::artifact_path(
+        config.platform,
+        &metadata.version,
+        urls::file_name_from_url(&url),
+    )?;
+    if !no_cache && cache::verify(&path).await.is_ok() {
+        events::log(format!("Using cached download {:?}", path));
+        return Ok(path);
+    }
+    fs::create_dir_all(path.parent().ok_or("Cache path has no parent")?).await?;
+    let download = crate::download_file(
+        client,
+        &url,
+        &path,
+        crate::download_buffer_size(None),
+        config,
+    )
+    .await?;
+    provenance::write_checksum(&path, &download.sha256, None).await?;
+    Ok(path)
+}
+
+/// Unpacks the tarball next to `dest`, so that moving it into place is the
+/// only step left once the other components are installed.
+async fn unpack_reh(tarball: &Path, dest: &Path) -> Result<PathBuf> {
+    let part = crate::fsutil::with_suffix(dest, ".part");
+    if fs::try_exists(&part).await? {
+        fs::remove_dir_all(&part).await?;
+    }
+    fs::create_dir_all(&part).await?;
+    let output = Command::new("tar")
+        .arg("-xzf")
+        .arg(tarball)
+        .arg("-C")
+        .arg(&part)
+        .arg("--strip-components=1")
+        .output()
+        .await?;
+    if !output.status.success() {
+        let _ = fs::remove_dir_all(&part).await;
+        return Err(format!(
+            "Could not unpack {:?}: {}",
+            tarball,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    events::log(format!("Unpacked the remote server to {:?}", part));
+    Ok(part)
+}
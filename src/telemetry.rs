@@ -0,0 +1,91 @@
+//! Opt-in anonymous usage ping. When `telemetry_url` is set, each install
+//! POSTs a `Ping` there so the maintainers can tell which platforms are in
+//! use and how often updates fail. It carries no identifiers, paths, Cursor
+//! versions or error messages; `cursorup telemetry show` prints exactly what
+//! is sent.
+
+use crate::config::Config;
+use serde::Serialize;
+use std::fmt;
+use std::time::Duration;
+
+/// A ping must never hold up or fail an update.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize, Debug)]
+pub struct Ping {
+    pub cursorup_version: &'static str,
+    pub os: &'static str,
+    pub arch: &'static str,
+    /// The configured Cursor platform, e.g. `linux-x64`.
+    pub platform: String,
+    pub success: bool,
+}
+
+impl Ping {
+    pub fn new(config: &Config, success: bool) -> Ping {
+        Ping {
+            cursorup_version: env!("CARGO_PKG_VERSION"),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            platform: config.platform.to_string(),
+            success,
+        }
+    }
+}
+
+/// What `cursorup telemetry show` reports.
+#[derive(Serialize, Debug)]
+pub struct Telemetry {
+    pub enabled: bool,
+    pub url: Option<String>,
+    /// The ping a successful install would send.
+    pub payload: Ping,
+}
+
+impl fmt::Display for Telemetry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.url {
+            Some(url) => writeln!(f, "Telemetry: on, sent to {}", url)?,
+            None => writeln!(f, "Telemetry: off (set telemetry_url to opt in)")?,
+        }
+        writeln!(f, "Payload sent after each install:")?;
+        write!(
+            f,
+            "{}",
+            serde_json::to_string_pretty(&self.payload).map_err(|_| fmt::Error)?
+        )
+    }
+}
+
+pub fn show(config: &Config) -> Telemetry {
+    Telemetry {
+        enabled: config.telemetry_url.is_some(),
+        url: config.telemetry_url.clone(),
+        payload: Ping::new(config, true),
+    }
+}
+
+/// Sends the ping if the user opted in. Failures are only logged at debug
+/// level.
+pub async fn ping(config: &Config, success: bool) {
+    let Some(url) = &config.telemetry_url else {
+        return;
+    };
+    let ping = Ping::new(config, success);
+    let sent = async {
+        let client = crate::http::client(config)?;
+        client
+            .post(url)
+            .timeout(TIMEOUT)
+            .json(&ping)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok::<_, Box<dyn std::error::Error>>(())
+    }
+    .await;
+    if let Err(e) = sent {
+        tracing::debug!("telemetry ping to {} failed: {}", url, e);
+    }
+}
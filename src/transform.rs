@@ -0,0 +1,101 @@
+//! The `transform` hook: a command run on every verified download before it
+//! is installed, e.g. to re-sign the AppImage for internal policy or to embed
+//! a wrapper. It works on a copy, so the cache keeps the upstream file that
+//! the published checksum describes, and the copy's own hash is recorded
+//! next to the upstream one in `provenance.json`.
+
+use crate::provenance::Transformed;
+use crate::{Result, cache, events};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::fs;
+use tokio::process::Command;
+
+/// Dir next to a cached download that holds its transformed copy.
+const DIR: &str = "transformed";
+
+/// Where the transformed copy of the cached download `upstream` goes.
+pub fn output_path(upstream: &Path) -> PathBuf {
+    upstream
+        .with_file_name(DIR)
+        .join(upstream.file_name().unwrap_or_default())
+}
+
+/// Copies `upstream` and runs `command` with `sh -c` on the copy, passing
+/// its path as `$1` and in `CURSORUP_ARTIFACT`. The command changes the file
+/// in place; a non-zero exit fails the install.
+pub async fn apply(
+    command: &str,
+    upstream: &Path,
+    version: &str,
+) -> Result<(PathBuf, Transformed)> {
+    let output_path = output_path(upstream);
+    fs::create_dir_all(output_path.parent().ok_or("Cache path has no parent")?).await?;
+    fs::copy(upstream, &output_path).await?;
+    events::log(format!(
+        "Running transform `{}` on {:?}",
+        command, output_path
+    ));
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .arg("cursorup-transform")
+        .arg(&output_path)
+        .env("CURSORUP_ARTIFACT", &output_path)
+        .env("CURSORUP_VERSION", version)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| format!("Could not run the transform `{}`: {}", command, e))?;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        events::log(format!("transform: {}", line));
+    }
+    if !output.status.success() {
+        let _ = fs::remove_file(&output_path).await;
+        return Err(format!(
+            "The transform `{}` failed ({}): {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    let size = fs::metadata(&output_path)
+        .await
+        .map_err(|e| {
+            format!(
+                "The transform `{}` removed {:?}: {}",
+                command, output_path, e
+            )
+        })?
+        .len();
+    if size == 0 {
+        return Err(format!("The transform `{}` left {:?} empty", command, output_path).into());
+    }
+    let sha256 = cache::sha256_file(&output_path).await?;
+    events::log(format!("Transformed {:?}; SHA-256 {}", output_path, sha256));
+    Ok((
+        output_path,
+        Transformed {
+            command: command.to_string(),
+            sha256,
+            size,
+        },
+    ))
+}
+
+/// Checks that the transformed copy at `path` is still what the transform
+/// produced, before a resumed install uses it.
+pub async fn verify(path: &Path, transformed: &Transformed) -> Result<()> {
+    let actual = cache::sha256_file(path)
+        .await
+        .map_err(|e| format!("No transformed download at {:?}: {}", path, e))?;
+    if actual != transformed.sha256 {
+        return Err(format!(
+            "Transformed download {:?} changed: expected sha256 {}, found {}",
+            path, transformed.sha256, actual
+        )
+        .into());
+    }
+    Ok(())
+}
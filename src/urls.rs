@@ -0,0 +1,168 @@
+//! Pure functions computing where Cursor's artifacts live, so mirror tooling
+//! and tests can reason about URLs and cache paths without network access.
+
+use crate::{API_URL, Platform, ReleaseTrack};
+use serde::Serialize;
+use std::path::PathBuf;
+
+const CHANGELOG_URL: &str = "https://cursor.com/changelog";
+
+/// Everything addressable for one build.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactUrls {
+    /// The API query that announces the latest build of the track.
+    pub metadata: String,
+    pub appimage: String,
+    pub reh: String,
+    /// Cache location relative to the artifacts dir, see [`cache_key`].
+    pub cache_key: PathBuf,
+}
+
+/// All URLs and the cache key for `version`/`commit_sha` on `platform`.
+pub fn artifact_urls(
+    commit_sha: &str,
+    version: &str,
+    platform: Platform,
+    release_track: ReleaseTrack,
+) -> ArtifactUrls {
+    ArtifactUrls {
+        metadata: metadata_url(API_URL, platform, release_track),
+        appimage: appimage_url(commit_sha, version, platform),
+        reh: reh_url(commit_sha, platform),
+        cache_key: cache_key(platform, version),
+    }
+}
+
+/// The metadata query for `api_url`, which is `API_URL` unless a mirror is
+/// configured.
+pub fn metadata_url(api_url: &str, platform: Platform, release_track: ReleaseTrack) -> String {
+    format!(
+        "{}?platform={}&releaseTrack={}",
+        api_url, platform, release_track
+    )
+}
+
+/// `url` fetched from `download_base` instead of its own host, for mirrors
+/// that keep the CDN's paths: with `https://mirror.corp/cursor`,
+/// `https://downloads.cursor.com/production/<commit>/...` becomes
+/// `https://mirror.corp/cursor/production/<commit>/...`.
+pub fn mirrored(url: &str, download_base: Option<&str>) -> String {
+    let Some(base) = download_base else {
+        return url.to_string();
+    };
+    let path = match url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("", |index| &rest[index..]),
+        None => url,
+    };
+    format!("{}{}", base.trim_end_matches('/'), path)
+}
+
+/// AppImage URL for a given build on Cursor's CDN, mirroring the layout of
+/// the `downloadUrl` returned by the API.
+pub fn appimage_url(commit_sha: &str, version: &str, platform: Platform) -> String {
+    format!(
+        "https://downloads.cursor.com/production/{}/linux/{}/{}",
+        commit_sha,
+        platform.cdn_arch(),
+        appimage_file_name(version, platform)
+    )
+}
+
+/// Remote-development server tarball for a given build.
+pub fn reh_url(commit_sha: &str, platform: Platform) -> String {
+    format!(
+        "https://cursor.blob.core.windows.net/remote-releases/{}/vscode-reh-linux-{}.tar.gz",
+        commit_sha,
+        platform.cdn_arch()
+    )
+}
+
+/// Release notes of `version`. Cursor's changelog has one page per minor
+/// release, e.g. `/changelog/1-2` for every 1.2.x.
+pub fn changelog_url(version: &str) -> String {
+    match version.parse::<crate::Version>() {
+        Ok(version) if version.numbers().len() >= 2 => format!(
+            "{}/{}-{}",
+            CHANGELOG_URL,
+            version.numbers()[0],
+            version.numbers()[1]
+        ),
+        _ => CHANGELOG_URL.to_string(),
+    }
+}
+
+pub fn appimage_file_name(version: &str, platform: Platform) -> String {
+    format!("Cursor-{}-{}.AppImage", version, platform.appimage_arch())
+}
+
+/// Directory of a build inside the artifacts cache: `<platform>/<version>`.
+pub fn cache_key(platform: Platform, version: &str) -> PathBuf {
+    PathBuf::from(platform.to_string()).join(version)
+}
+
+/// File name a download is stored under, taken from its URL.
+pub fn file_name_from_url(url: &str) -> &str {
+    url.split('/').next_back().unwrap_or("cursor-download.tmp")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn artifact_urls_for_a_build() {
+        let urls = artifact_urls(
+            "abc123",
+            "1.2.3",
+            Platform::LinuxArm64,
+            ReleaseTrack::Latest,
+        );
+        assert_eq!(
+            urls.metadata,
+            "https://cursor.com/api/download?platform=linux-arm64&releaseTrack=latest"
+        );
+        assert_eq!(
+            urls.appimage,
+            "https://downloads.cursor.com/production/abc123/linux/arm64/Cursor-1.2.3-aarch64.AppImage"
+        );
+        assert_eq!(
+            urls.reh,
+            "https://cursor.blob.core.windows.net/remote-releases/abc123/vscode-reh-linux-arm64.tar.gz"
+        );
+        assert_eq!(urls.cache_key, PathBuf::from("linux-arm64/1.2.3"));
+    }
+
+    #[test]
+    fn mirrored_keeps_the_path() {
+        let url =
+            "https://downloads.cursor.com/production/abc/linux/x64/Cursor-1.2.3-x86_64.AppImage";
+        assert_eq!(mirrored(url, None), url);
+        assert_eq!(
+            mirrored(url, Some("https://mirror.corp/cursor/")),
+            "https://mirror.corp/cursor/production/abc/linux/x64/Cursor-1.2.3-x86_64.AppImage"
+        );
+        assert_eq!(
+            mirrored("https://downloads.cursor.com", Some("https://mirror.corp")),
+            "https://mirror.corp"
+        );
+    }
+
+    #[test]
+    fn changelog_url_per_minor_release() {
+        assert_eq!(changelog_url("1.2.3"), "https://cursor.com/changelog/1-2");
+        assert_eq!(
+            changelog_url("v0.45.14"),
+            "https://cursor.com/changelog/0-45"
+        );
+        assert_eq!(changelog_url("1"), "https://cursor.com/changelog");
+        assert_eq!(changelog_url("nightly"), "https://cursor.com/changelog");
+    }
+
+    #[test]
+    fn file_name_from_url_takes_the_last_segment() {
+        assert_eq!(
+            file_name_from_url("https://host/a/Cursor-1.2.3-x86_64.AppImage"),
+            "Cursor-1.2.3-x86_64.AppImage"
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Cursor version strings as an ordered type, so update decisions compare
+//! `1.10.0` above `1.9.3` instead of testing strings for equality.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// A version such as `1.2.3`, `0.45.14.1`, `v1.2` or `1.3.0-pre.2+build`.
+///
+/// Numeric components compare numerically and missing ones count as zero,
+/// so `1.2` equals `1.2.0`. A pre-release suffix sorts before the release
+/// it precedes, and build metadata after `+` is ignored, as in semver.
+/// Display gives back the string the version was parsed from.
+#[derive(Debug, Clone)]
+pub struct Version {
+    original: String,
+    numbers: Vec<u64>,
+    pre: Vec<Identifier>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Identifier {
+    // Declared first: numeric identifiers sort below alphanumeric ones.
+    Numeric(u64),
+    Text(String),
+}
+
+impl Version {
+    /// Numeric components, e.g. `[1, 2, 3]` for `1.2.3`.
+    pub fn numbers(&self) -> &[u64] {
+        &self.numbers
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+
+    /// Whether `self` and `other` share the leading `components` numbers,
+    /// e.g. the same minor series for 2.
+    pub fn same_series(&self, other: &Version, components: usize) -> bool {
+        (0..components).all(|index| self.number(index) == other.number(index))
+    }
+
+    fn number(&self, index: usize) -> u64 {
+        self.numbers.get(index).copied().unwrap_or(0)
+    }
+}
+
+impl FromStr for Version {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid version `{}`", s);
+        let trimmed = s.trim();
+        let unprefixed = trimmed.strip_prefix(['v', 'V']).unwrap_or(trimmed);
+        let without_build = unprefixed.split('+').next().unwrap_or_default();
+        let (core, pre) = match without_build.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (without_build, None),
+        };
+        let numbers = core
+            .split('.')
+            .map(|part| part.parse::<u64>().map_err(|_| invalid()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let pre = match pre {
+            Some(pre) => pre
+                .split('.')
+                .map(|part| match part.parse::<u64>() {
+                    Ok(number) => Ok(Identifier::Numeric(number)),
+                    Err(_) if !part.is_empty() => Ok(Identifier::Text(part.to_string())),
+                    Err(_) => Err(invalid()),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            None => Vec::new(),
+        };
+        Ok(Version {
+            original: trimmed.to_string(),
+            numbers,
+            pre,
+        })
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let components = self.numbers.len().max(other.numbers.len());
+        (0..components)
+            .map(|index| self.number(index).cmp(&other.number(index)))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => self.pre.cmp(&other.pre),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Version {}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.original)
+    }
+}
+
+impl Serialize for Version {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.original)
+    }
+}
+
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Orders two version strings, or `None` if either doesn't parse.
+pub fn compare(a: &str, b: &str) -> Option<Ordering> {
+    Some(a.parse::<Version>().ok()?.cmp(&b.parse::<Version>().ok()?))
+}
+
+/// Whether `version` matches `pattern`, a version whose trailing components
+/// may be `x` or `*`: `1.2.x` matches `1.2.0` and `1.2.7` but neither
+/// `1.3.0` nor a pre-release. A pattern without wildcards must equal the
+/// version.
+pub fn matches(pattern: &str, version: &str) -> bool {
+    let is_wildcard = |part: &str| matches!(part, "x" | "X" | "*");
+    let parts: Vec<&str> = pattern.trim().split('.').collect();
+    let Some(fixed) = parts.iter().position(|part| is_wildcard(part)) else {
+        return compare(pattern, version) == Some(Ordering::Equal);
+    };
+    if !parts[fixed..].iter().all(|part| is_wildcard(part)) {
+        return false;
+    }
+    let Ok(version) = version.parse::<Version>() else {
+        return false;
+    };
+    !version.is_prerelease()
+        && parts[..fixed]
+            .iter()
+            .enumerate()
+            .all(|(index, part)| part.parse::<u64>().ok() == Some(version.number(index)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(s: &str) -> Version {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn orders_numerically() {
+        assert!(v("1.10.0") > v("1.9.3"));
+        assert!(v("0.45.14.1") > v("0.45.14"));
+        assert!(v("2.0") > v("1.99.99"));
+        assert_eq!(v("1.2"), v("1.2.0"));
+        assert_eq!(v("v1.2.3"), v("1.2.3"));
+    }
+
+    #[test]
+    fn prereleases_sort_before_their_release() {
+        assert!(v("1.3.0-pre.2") < v("1.3.0"));
+        assert!(v("1.3.0-pre.2") > v("1.2.9"));
+        assert!(v("1.3.0-pre.2") < v("1.3.0-pre.10"));
+        assert!(v("1.3.0-alpha") < v("1.3.0-beta"));
+        // Numeric identifiers sort below alphanumeric ones.
+        assert!(v("1.3.0-1") < v("1.3.0-alpha"));
+        assert!(v("1.3.0-pre").is_prerelease());
+    }
+
+    #[test]
+    fn ignores_build_metadata() {
+        assert_eq!(v("1.3.0+build.5"), v("1.3.0"));
+        assert_eq!(v("1.3.0+build.5").to_string(), "1.3.0+build.5");
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        for s in ["", "1..2", "1.x", "latest", "1.2.3-", "1.2.3-a..b"] {
+            assert!(s.parse::<Version>().is_err(), "{:?} parsed", s);
+        }
+        assert_eq!(compare("1.2.3", "nightly"), None);
+    }
+
+    #[test]
+    fn same_series() {
+        assert!(v("1.2.3").same_series(&v("1.2.9"), 2));
+        assert!(!v("1.2.3").same_series(&v("1.3.0"), 2));
+        assert!(v("1.2").same_series(&v("1.2.0"), 3));
+    }
+
+    #[test]
+    fn wildcard_patterns() {
+        assert!(matches("1.2.x", "1.2.0"));
+        assert!(matches("1.2.*", "1.2.7"));
+        assert!(!matches("1.2.x", "1.3.0"));
+        assert!(!matches("1.2.x", "1.2.1-pre"));
+        assert!(matches("1.2.3", "1.2.3"));
+        assert!(!matches("1.2.3", "1.2.4"));
+    }
+}
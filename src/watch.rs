@@ -0,0 +1,275 @@
+//! `cursorup watch`: follows the install dir with inotify and, when other
+//! tooling (a dotfile manager, a hand-run download) replaces the AppImage,
+//! checks the new file, records it in the state file and manifest, and
+//! rewrites the `current` link, wrapper, desktop entry and profile
+//! launchers for it.
+
+use crate::config::Config;
+use crate::state::State;
+use crate::{Result, adopt, events, fsutil, manifest, wrapper};
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::AsyncReadExt;
+
+/// How long the dir must stay quiet before the new file is looked at, so a
+/// copy in progress isn't mistaken for a broken AppImage.
+#[cfg(target_os = "linux")]
+const SETTLE: std::time::Duration = std::time::Duration::from_secs(2);
+
+#[derive(Serialize, Debug)]
+pub struct Repaired {
+    pub appimage: PathBuf,
+    pub from: Option<String>,
+    pub to: String,
+    pub sha256: String,
+}
+
+impl fmt::Display for Repaired {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} was replaced: Cursor {} -> {}; state and launchers updated",
+            self.appimage.display(),
+            self.from.as_deref().unwrap_or("-"),
+            self.to
+        )
+    }
+}
+
+/// Watches until interrupted. The install dir is checked once at startup,
+/// then again whenever an AppImage in it changes.
+#[cfg(target_os = "linux")]
+pub async fn run(config: &Config) -> Result<()> {
+    let install_dir = &config.install_dir;
+    if !fs::try_exists(install_dir).await? {
+        return Err(format!("{:?} does not exist; install Cursor first", install_dir).into());
+    }
+    let watcher = inotify::Watcher::new(install_dir)?;
+    events::log(format!(
+        "Watching {:?} for AppImages replaced by other tools",
+        install_dir
+    ));
+    report(config).await;
+    loop {
+        let names = watcher.next().await?;
+        if !names.iter().any(|name| is_appimage(Path::new(name))) {
+            continue;
+        }
+        while let Ok(names) = tokio::time::timeout(SETTLE, watcher.next()).await {
+            names?;
+        }
+        report(config).await;
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub async fn run(_config: &Config) -> Result<()> {
+    Err("`cursorup watch` needs inotify, which only Linux has".into())
+}
+
+#[cfg(target_os = "linux")]
+async fn report(config: &Config) {
+    match repair(config).await {
+        Ok(Some(repaired)) => events::log(repaired.to_string()),
+        Ok(None) => {}
+        Err(e) => tracing::warn!("could not take over the new AppImage: {}", e),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn is_appimage(path: &Path) -> bool {
+    path.extension().and_then(|s| s.to_str()) == Some("AppImage")
+}
+
+/// Takes over the most recently modified AppImage in the install dir if it
+/// is not the one cursorup recorded. Returns `None` when nothing changed or
+/// cursorup itself is in the middle of an install.
+pub async fn repair(config: &Config) -> Result<Option<Repaired>> {
+    let install_dir = &config.install_dir;
+    if crate::journal::load(None).await?.is_some() {
+        events::log("An install is in progress; leaving the install dir alone");
+        return Ok(None);
+    }
+    let Some(appimage) = newest(install_dir).await? else {
+        return Ok(None);
+    };
+    let record = manifest::Manifest::load(install_dir)
+        .await?
+        .and_then(|manifest| manifest.appimage);
+    let recorded = record
+        .as_ref()
+        .is_some_and(|record| appimage.file_name() == Some(std::ffi::OsStr::new(&record.file)));
+    if recorded && !manifest::modified_externally(install_dir, &appimage).await? {
+        return Ok(None);
+    }
+
+    events::log(format!("{:?} changed; checking it", appimage));
+    check_appimage(&appimage).await?;
+    fsutil::set_mode(&appimage, fsutil::executable_mode(config.file_mode)).await?;
+    let embedded = adopt::inspect(&appimage, config.execution_mode).await;
+    let to = embedded
+        .version
+        .or_else(|| crate::status::version_from_file_name(&appimage))
+        .ok_or_else(|| format!("Cannot tell the version of {:?}", appimage))?;
+    let sha256 = crate::cache::sha256_file(&appimage).await?;
+
+    wrapper::link_current(install_dir, &appimage).await?;
+    let mut manifest = manifest::Manifest::load(install_dir)
+        .await?
+        .unwrap_or_default();
+    manifest.add(&appimage);
+    manifest.appimage = Some(manifest::FileRecord::new(&appimage, &sha256).await?);
+    manifest.save(install_dir).await?;
+
+    let previous_state = State::load().await?;
+    let from = previous_state.as_ref().map(|state| state.version.clone());
+    let mut state = State::new(
+        &config.desktop_name,
+        &to,
+        &embedded.commit_sha.unwrap_or_default(),
+        &appimage,
+        config.platform,
+        config.release_track,
+    );
+    state.sha256 = Some(sha256.clone());
+    state.downloads = previous_state.map(|s| s.downloads).unwrap_or_default();
+    state.save().await?;
+
+    let mode = fsutil::executable_mode(config.file_mode);
+    if config.cli_wrapper {
+        wrapper::write_wrapper(install_dir, mode, &config.launch_args()).await?;
+    }
+    if config.desktop_entry && !config.system_install {
+        crate::echo_2_desktop(
+            &config.desktop_name,
+            &appimage,
+            &install_dir.join("code.png"),
+            &config.launch_args(),
+            false,
+        )
+        .await?;
+    }
+    crate::profiles::refresh(config).await?;
+
+    Ok(Some(Repaired {
+        appimage,
+        from,
+        to,
+        sha256,
+    }))
+}
+
+/// The most recently modified AppImage in `install_dir`; on a tie, the
+/// one whose name sorts last.
+async fn newest(install_dir: &Path) -> Result<Option<PathBuf>> {
+    let mut newest = None;
+    for appimage in adopt::appimages(install_dir).await? {
+        let modified = fs::metadata(&appimage).await?.modified()?;
+        if newest.as_ref().is_none_or(|(time, _)| modified >= *time) {
+            newest = Some((modified, appimage));
+        }
+    }
+    Ok(newest.map(|(_, appimage)| appimage))
+}
+
+/// An AppImage (type 2) is an ELF binary with `AI\x02` at offset 8.
+async fn check_appimage(path: &Path) -> Result<()> {
+    let mut header = [0; 11];
+    fs::File::open(path)
+        .await?
+        .read_exact(&mut header)
+        .await
+        .map_err(|e| format!("{:?} is too short to be an AppImage: {}", path, e))?;
+    if &header[..4] != b"\x7fELF" || &header[8..] != b"AI\x02" {
+        return Err(format!("{:?} is not an AppImage", path).into());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::ffi::{CString, OsStr, OsString};
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+    use tokio::io::unix::AsyncFd;
+
+    const MASK: u32 = libc::IN_CLOSE_WRITE
+        | libc::IN_MOVED_TO
+        | libc::IN_MOVED_FROM
+        | libc::IN_CREATE
+        | libc::IN_DELETE
+        | libc::IN_ATTRIB;
+
+    pub struct Watcher {
+        fd: AsyncFd<OwnedFd>,
+    }
+
+    impl Watcher {
+        pub fn new(dir: &Path) -> io::Result<Watcher> {
+            // SAFETY: inotify_init1 takes no pointers; a valid result is a
+            // fresh descriptor that nothing else owns.
+            let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+            let path = CString::new(dir.as_os_str().as_bytes())?;
+            // SAFETY: `path` is a NUL-terminated string that outlives the call.
+            if unsafe { libc::inotify_add_watch(fd.as_raw_fd(), path.as_ptr(), MASK) } < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Watcher {
+                fd: AsyncFd::new(fd)?,
+            })
+        }
+
+        /// Waits for the next batch of events and returns the file names
+        /// they are about.
+        pub async fn next(&self) -> io::Result<Vec<OsString>> {
+            let mut buffer = [0u8; 4096];
+            loop {
+                let mut guard = self.fd.readable().await?;
+                let read = guard.try_io(|fd| {
+                    // SAFETY: the buffer is valid for writes of its length.
+                    let read = unsafe {
+                        libc::read(fd.as_raw_fd(), buffer.as_mut_ptr().cast(), buffer.len())
+                    };
+                    if read < 0 {
+                        Err(io::Error::last_os_error())
+                    } else {
+                        Ok(read as usize)
+                    }
+                });
+                if let Ok(read) = read {
+                    return Ok(names(&buffer[..read?]));
+                }
+            }
+        }
+    }
+
+    /// Names from a buffer of `struct inotify_event`s, each followed by its
+    /// NUL-padded name.
+    fn names(buffer: &[u8]) -> Vec<OsString> {
+        const HEADER: usize = std::mem::size_of::<libc::inotify_event>();
+        let mut names = Vec::new();
+        let mut offset = 0;
+        while offset + HEADER <= buffer.len() {
+            let len = u32::from_ne_bytes(
+                buffer[offset + 12..offset + 16]
+                    .try_into()
+                    .unwrap_or_default(),
+            ) as usize;
+            let name = buffer
+                .get(offset + HEADER..offset + HEADER + len)
+                .unwrap_or_default();
+            let name = name.split(|byte| *byte == 0).next().unwrap_or_default();
+            names.push(OsStr::from_bytes(name).to_os_string());
+            offset += HEADER + len;
+        }
+        names
+    }
+}
@@ -0,0 +1,69 @@
+use crate::Result;
+use crate::config::{self, Config};
+use std::io::{BufRead, Write, stdin, stdout};
+
+/// Walks the user through the first-run questions, starting from the defaults.
+/// Settings not covered here keep their default values.
+pub fn run() -> Result<Config> {
+    let mut config = Config::default();
+    println!("No cursorup config found, let's create one (press Enter to accept defaults).");
+
+    config.install_dir = loop {
+        let dir = ask("Install directory", &config.install_dir.to_string_lossy())?;
+        // Expanded like the config file would, e.g. `~/Applications`.
+        match config::expand_path(&dir) {
+            Ok(dir) => break dir,
+            Err(e) => println!("Please enter an absolute path: {}", e),
+        }
+    };
+    config.release_track = loop {
+        let track = ask(
+            "Release track (stable/latest)",
+            &config.release_track.to_string(),
+        )?;
+        match track.parse() {
+            Ok(track) => break track,
+            Err(_) => println!("Please answer `stable` or `latest`."),
+        }
+    };
+    config.auto_update = ask_bool("Enable automatic updates?", config.auto_update)?;
+    config.desktop_entry = ask_bool("Create a desktop launcher entry?", config.desktop_entry)?;
+    if config.desktop_entry {
+        config.desktop_name = ask("Launcher name", &config.desktop_name)?;
+    }
+    config.cli_wrapper = ask_bool(
+        "Install the `cursor` command in ~/.local/bin?",
+        config.cli_wrapper,
+    )?;
+
+    Ok(config)
+}
+
+fn ask(question: &str, default: &str) -> Result<String> {
+    print!("{} [{}]: ", question, default);
+    stdout().flush()?;
+    let mut answer = String::new();
+    stdin().lock().read_line(&mut answer)?;
+    let answer = answer.trim();
+    if answer.is_empty() {
+        Ok(default.to_string())
+    } else {
+        Ok(answer.to_string())
+    }
+}
+
+pub(crate) fn ask_bool(question: &str, default: bool) -> Result<bool> {
+    let hint = if default { "Y/n" } else { "y/N" };
+    loop {
+        print!("{} [{}]: ", question, hint);
+        stdout().flush()?;
+        let mut answer = String::new();
+        stdin().lock().read_line(&mut answer)?;
+        match answer.trim().to_lowercase().as_str() {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => println!("Please answer y or n."),
+        }
+    }
+}
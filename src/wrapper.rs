@@ -0,0 +1,207 @@
+use crate::Result;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+/// Name of the stable symlink inside the install dir that always points at
+/// the current AppImage, so nothing outside the install dir has to change
+/// when the versioned AppImage file name does.
+pub const CURRENT_LINK: &str = "cursor";
+
+pub fn wrapper_path() -> Result<PathBuf> {
+    Ok(crate::home_dir()?.join(".local/bin/cursor"))
+}
+
+/// Whether the directory holding the `cursor` wrapper is on `PATH`, with
+/// what to do about it if not.
+#[derive(Serialize, Debug)]
+pub struct PathCheck {
+    pub dir: PathBuf,
+    pub on_path: bool,
+    /// Shell startup file the `PATH` line belongs in.
+    pub rc_file: Option<PathBuf>,
+    pub fix: String,
+}
+
+impl PathCheck {
+    pub fn new() -> Result<PathCheck> {
+        let dir = wrapper_path()?
+            .parent()
+            .ok_or("Wrapper path has no parent")?
+            .to_path_buf();
+        let on_path = std::env::var_os("PATH")
+            .is_some_and(|path| std::env::split_paths(&path).any(|entry| entry == dir));
+        let shell = std::env::var("SHELL").unwrap_or_default();
+        let home = crate::home_dir()?;
+        let (rc_file, fix) = match shell.rsplit('/').next().unwrap_or_default() {
+            "fish" => (
+                Some(home.join(".config/fish/config.fish")),
+                format!("fish_add_path {}", dir.display()),
+            ),
+            shell @ ("bash" | "zsh") => (
+                Some(match std::env::var_os("ZDOTDIR") {
+                    Some(zdotdir) if shell == "zsh" => PathBuf::from(zdotdir).join(".zshrc"),
+                    _ => home.join(format!(".{}rc", shell)),
+                }),
+                format!("export PATH=\"{}:$PATH\"", dir.display()),
+            ),
+            _ => (None, format!("export PATH=\"{}:$PATH\"", dir.display())),
+        };
+        Ok(PathCheck {
+            dir,
+            on_path,
+            rc_file,
+            fix,
+        })
+    }
+
+    /// Copy-pasteable instructions for adding `dir` to `PATH`.
+    pub fn instructions(&self) -> String {
+        match &self.rc_file {
+            Some(rc_file) => format!(
+                "{} is not on your PATH, so the `cursor` command won't be found. Add this line to {}:\n  {}",
+                self.dir.display(),
+                rc_file.display(),
+                self.fix
+            ),
+            None => format!(
+                "{} is not on your PATH, so the `cursor` command won't be found. Add it in your shell's startup file, e.g.:\n  {}",
+                self.dir.display(),
+                self.fix
+            ),
+        }
+    }
+}
+
+/// After writing the wrapper: if its directory is not on `PATH`, offers to
+/// append the fix to the shell's startup file in an interactive terminal,
+/// and prints instructions otherwise. A startup file that already has the
+/// line only needs a new shell.
+pub async fn ensure_on_path() -> Result<()> {
+    let check = PathCheck::new()?;
+    if check.on_path {
+        return Ok(());
+    }
+    let Some(rc_file) = &check.rc_file else {
+        crate::events::log(check.instructions());
+        return Ok(());
+    };
+    let existing = fs::read_to_string(rc_file).await.unwrap_or_default();
+    if existing.lines().any(|line| line.trim() == check.fix) {
+        crate::events::log(format!(
+            "{} is set up in {}; open a new shell to use the `cursor` command",
+            check.dir.display(),
+            rc_file.display()
+        ));
+        return Ok(());
+    }
+    let prompt = crate::events::Prompt::AddToPath {
+        dir: check.dir.clone(),
+        rc_file: rc_file.clone(),
+        line: check.fix.clone(),
+    };
+    match crate::events::ask(prompt).await? {
+        Some(true) => {}
+        Some(false) => return Ok(()),
+        None => {
+            crate::events::log(check.instructions());
+            return Ok(());
+        }
+    }
+    if let Some(parent) = rc_file.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(rc_file)
+        .await?;
+    let separator = if existing.is_empty() || existing.ends_with('\n') {
+        ""
+    } else {
+        "\n"
+    };
+    file.write_all(format!("{}\n# Added by cursorup\n{}\n", separator, check.fix).as_bytes())
+        .await?;
+    crate::events::log(format!(
+        "Added {} to PATH in {}; open a new shell to use the `cursor` command",
+        check.dir.display(),
+        rc_file.display()
+    ));
+    Ok(())
+}
+
+/// Points `<install_dir>/cursor` at the freshly installed AppImage.
+pub async fn link_current(install_dir: &Path, appimage_path: &Path) -> Result<PathBuf> {
+    let link_path = install_dir.join(CURRENT_LINK);
+    if fs::symlink_metadata(&link_path).await.is_ok() {
+        fs::remove_file(&link_path).await?;
+    }
+    let target = appimage_path
+        .file_name()
+        .ok_or("AppImage path has no file name")?;
+    #[cfg(unix)]
+    fs::symlink(target, &link_path).await?;
+    #[cfg(windows)]
+    fs::symlink_file(target, &link_path).await?;
+    Ok(link_path)
+}
+
+/// Writes the `cursor` CLI wrapper. All arguments (`--wait`, `--diff a b`,
+/// `-g file:line`, ...) are forwarded untouched, and the script only refers to
+/// the stable symlink, so it is byte-identical across updates and is left
+/// alone when already up to date. `mode` is applied whenever it is written.
+/// `launch_args` go before the forwarded ones.
+pub async fn write_wrapper(install_dir: &Path, mode: u32, launch_args: &[&str]) -> Result<PathBuf> {
+    let wrapper_path = wrapper_path()?;
+    write_launcher(&wrapper_path, install_dir, mode, launch_args).await?;
+    Ok(wrapper_path)
+}
+
+/// Writes a wrapper script like the `cursor` one to `path`.
+pub async fn write_launcher(
+    path: &Path,
+    install_dir: &Path,
+    mode: u32,
+    launch_args: &[&str],
+) -> Result<()> {
+    let target = install_dir.join(CURRENT_LINK);
+    let contents = format!(
+        r#"#!/bin/sh
+# Generated by cursorup. Forwards all arguments to the managed Cursor AppImage.
+exec {}{} "$@"
+"#,
+        shell_quote(target.to_str().ok_or("Install path is not valid UTF-8")?),
+        launch_args
+            .iter()
+            .map(|arg| format!(" {}", shell_quote(arg)))
+            .collect::<String>(),
+    );
+
+    if fs::read_to_string(path).await.ok().as_deref() == Some(contents.as_str()) {
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    fs::write(path, contents).await?;
+    crate::fsutil::set_mode(path, mode).await?;
+    crate::events::log(format!("Wrote CLI wrapper to {:?}", path));
+    Ok(())
+}
+
+/// Single-quotes `arg` for `sh` unless it consists of characters that are
+/// safe as they are, such as flags.
+fn shell_quote(arg: &str) -> String {
+    let safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if safe {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}